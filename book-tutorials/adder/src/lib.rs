@@ -2,21 +2,9 @@ pub fn add(left: usize, right: usize) -> usize {
     left + right
 }
 
-#[derive(Debug)]
-struct Rectangle {
-    width: u32,
-    height: u32,
-}
-
-impl Rectangle {
-    fn can_hold(&self, other: &Rectangle) -> bool {
-        self.width > other.width && self.height > other.height
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use geometry::Rectangle;
 
     #[test]
     fn exploration() {
@@ -26,28 +14,16 @@ mod tests {
 
     #[test]
     fn larger_can_hold_smaller() {
-        let larger = Rectangle {
-            width: 8,
-            height: 7,
-        };
-        let smaller = Rectangle {
-            width: 5,
-            height: 1,
-        };
+        let larger = Rectangle::new(8, 7);
+        let smaller = Rectangle::new(5, 1);
 
         assert!(larger.can_hold(&smaller));
     }
 
     #[test]
     fn smaller_cannot_hold_larger() {
-        let larger = Rectangle {
-            width: 8,
-            height: 7,
-        };
-        let smaller = Rectangle {
-            width: 5,
-            height: 1,
-        };
+        let larger = Rectangle::new(8, 7);
+        let smaller = Rectangle::new(5, 1);
 
         assert!(!smaller.can_hold(&larger));
     }
@@ -1,5 +1,7 @@
 use std::thread;
 
+use geometry::Rectangle;
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 enum ShirtColor {
     Red,
@@ -33,12 +35,6 @@ impl Inventory {
     }
 }
 
-#[derive(Debug)]
-struct Rectangle {
-    width: u32,
-    height: u32,
-}
-
 fn main() {
     let store = Inventory {
         shirts: vec![ShirtColor::Blue, ShirtColor::Red, ShirtColor::Blue],
@@ -195,25 +191,10 @@ fn main() {
 
     println!("\nExample of moving values out of closures");
 
-    #[derive(Debug)]
-    struct Rectangle {
-        width: u32,
-        height: u32,
-    }
-
     let mut list = [
-        Rectangle {
-            width: 10,
-            height: 1,
-        },
-        Rectangle {
-            width: 3,
-            height: 5,
-        },
-        Rectangle {
-            width: 7,
-            height: 12,
-        },
+        Rectangle::new(10, 1),
+        Rectangle::new(3, 5),
+        Rectangle::new(7, 12),
     ];
 
     // sort_by_key requires the FnMut trait because it calls the closure several times
@@ -223,7 +204,7 @@ fn main() {
     // the rust requirement that 'if a mutable reference exists it must be the only mutable reference' be enough
     // to cause a compile error if a mutable reference was taken while the closure still had an immutable ref to
     // the variable?)
-    list.sort_by_key(|r| r.width);
+    list.sort_by_key(|r| r.width());
     println!("{:#?}", list);
 
     // now: a demonstration of a closure which would not be usable with sort_by_key
@@ -248,14 +229,14 @@ fn main() {
     // let value = String::from("by key called");
     // list.sort_by_key(|r| {
     //     sort_operations.push(value);
-    //     r.width
+    //     r.width()
     // });
 
     // A proper way to count code like this would be just to increment a number in the closure
     let mut count = 0;
     list.sort_by_key(|r| {
         count += 1;
-        r.width
+        r.width()
     });
     println!("the sort closure was called {count} times");
     println!("\
@@ -0,0 +1,263 @@
+use std::error::Error;
+use std::fmt;
+
+// laying rectangles out in containers, built on the containment checks below
+pub mod packing;
+pub use packing::{Heuristic, Packer, Packing, PackingError, Placement};
+
+// a rectangle with whole-number sides. the sides are private so that one made with try_new
+// stays one whose area and perimeter can be measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rectangle {
+    width: u32,
+    height: u32,
+}
+
+impl Rectangle {
+    // takes any sides at all, so the area and perimeter of what it makes might not fit
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    // only a rectangle whose area and perimeter both fit in a u32
+    pub fn try_new(width: u32, height: u32) -> Result<Self, RectangleError> {
+        let rectangle = Self::new(width, height);
+        if width == 0 || height == 0 {
+            return Err(RectangleError::ZeroSide);
+        }
+        if rectangle.area().is_none() || rectangle.perimeter().is_none() {
+            return Err(RectangleError::TooBig { width, height });
+        }
+        Ok(rectangle)
+    }
+
+    pub fn square(side: u32) -> Self {
+        Self::new(side, side)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // None when it doesn't fit in a u32, rather than wrapping around to something small
+    pub fn area(&self) -> Option<u32> {
+        self.width.checked_mul(self.height)
+    }
+
+    pub fn perimeter(&self) -> Option<u32> {
+        self.width.checked_add(self.height)?.checked_mul(2)
+    }
+
+    // both sides times `factor`, held to the same rules as try_new. one that comes out too big
+    // is reported by the sides it had before it was scaled, however it overflowed
+    pub fn scale(&self, factor: u32) -> Result<Self, RectangleError> {
+        let scaled = match (
+            self.width.checked_mul(factor),
+            self.height.checked_mul(factor),
+        ) {
+            (Some(width), Some(height)) => Self::try_new(width, height),
+            _ => Err(self.too_big()),
+        };
+        scaled.map_err(|e| match e {
+            RectangleError::TooBig { .. } => self.too_big(),
+            e => e,
+        })
+    }
+
+    fn too_big(&self) -> RectangleError {
+        RectangleError::TooBig {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    // width over height, so anything wider than it is tall is above 1. None with no height
+    pub fn aspect_ratio(&self) -> Option<f64> {
+        if self.height == 0 {
+            return None;
+        }
+        Some(f64::from(self.width) / f64::from(self.height))
+    }
+
+    // the same rectangle turned a quarter turn, so its width and height swap
+    pub fn rotated(&self) -> Self {
+        Self::new(self.height, self.width)
+    }
+
+    // whether `other` fits inside this one with room to spare on both sides, without turning it
+    pub fn can_hold(&self, other: &Rectangle) -> bool {
+        self.fits_as(other, Containment::Strict)
+    }
+
+    // how `item` fits inside this one, if it does at all. it's only turned when it has to be, so
+    // Orientation::AsIs whenever both ways fit
+    pub fn fit(&self, item: &Rectangle, containment: Containment) -> Option<Orientation> {
+        if self.fits_as(item, containment) {
+            Some(Orientation::AsIs)
+        } else if self.fits_as(&item.rotated(), containment) {
+            Some(Orientation::Rotated)
+        } else {
+            None
+        }
+    }
+
+    // whether `item` fits inside this one either way round
+    pub fn holds(&self, item: &Rectangle, containment: Containment) -> bool {
+        self.fit(item, containment).is_some()
+    }
+
+    fn fits_as(&self, item: &Rectangle, containment: Containment) -> bool {
+        match containment {
+            Containment::Strict => self.width > item.width && self.height > item.height,
+            Containment::Inclusive => self.width >= item.width && self.height >= item.height,
+        }
+    }
+}
+
+// "420 x 17"
+impl fmt::Display for Rectangle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} x {}", self.width, self.height)
+    }
+}
+
+// how tight a fit is allowed to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Containment {
+    // smaller on both sides, like can_hold
+    Strict,
+    // a side may be exactly as long as the one it goes against, the way a box fits its lid
+    Inclusive,
+}
+
+// which way round something goes in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    AsIs,
+    // turned a quarter turn, so its width runs along the height
+    Rotated,
+}
+
+// "as is" or "rotated"
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Orientation::AsIs => write!(f, "as is"),
+            Orientation::Rotated => write!(f, "rotated"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RectangleError {
+    ZeroSide,
+    // the sides asked for, before any scaling
+    TooBig { width: u32, height: u32 },
+}
+
+impl fmt::Display for RectangleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RectangleError::ZeroSide => write!(f, "a rectangle can't have a side of zero"),
+            RectangleError::TooBig { width, height } => {
+                write!(
+                    f,
+                    "a {} x {} rectangle is too big to measure",
+                    width, height
+                )
+            }
+        }
+    }
+}
+
+impl Error for RectangleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_what_fits() {
+        let rect = Rectangle::try_new(30, 120).unwrap();
+        assert_eq!((rect.area(), rect.perimeter()), (Some(3_600), Some(300)));
+        assert_eq!(rect.scale(2).unwrap(), Rectangle::new(60, 240));
+        assert_eq!(rect.aspect_ratio(), Some(0.25));
+        assert_eq!(rect.to_string(), "30 x 120");
+    }
+
+    #[test]
+    fn rectangles_that_would_overflow_are_turned_away() {
+        assert_eq!(Rectangle::try_new(0, 5), Err(RectangleError::ZeroSide));
+        let too_big = RectangleError::TooBig {
+            width: 70_000,
+            height: 70_000,
+        };
+        assert_eq!(Rectangle::try_new(70_000, 70_000), Err(too_big));
+        assert!(Rectangle::try_new(1, u32::MAX).is_err());
+        let rect = Rectangle::new(30, 120);
+        assert_eq!(
+            rect.scale(u32::MAX),
+            Err(RectangleError::TooBig {
+                width: 30,
+                height: 120
+            })
+        );
+        // the sides fit once they're doubled, but the area doesn't
+        assert_eq!(
+            Rectangle::new(50_000, 50_000).scale(2),
+            Err(RectangleError::TooBig {
+                width: 50_000,
+                height: 50_000
+            })
+        );
+        // new still takes anything, but what it can't measure comes back as None
+        assert_eq!(Rectangle::new(70_000, 70_000).area(), None);
+    }
+
+    #[test]
+    fn holds_only_what_is_smaller_both_ways() {
+        let larger = Rectangle::new(8, 7);
+        let smaller = Rectangle::new(5, 1);
+        assert!(larger.can_hold(&smaller));
+        assert!(!smaller.can_hold(&larger));
+        assert!(!larger.can_hold(&larger));
+    }
+
+    #[test]
+    fn turns_things_only_when_they_have_to_be() {
+        let shelf = Rectangle::new(10, 4);
+        let tall_box = Rectangle::new(3, 9);
+        assert!(!shelf.can_hold(&tall_box));
+        assert_eq!(
+            shelf.fit(&tall_box, Containment::Strict),
+            Some(Orientation::Rotated)
+        );
+        assert_eq!(
+            shelf.fit(&Rectangle::new(3, 3), Containment::Strict),
+            Some(Orientation::AsIs)
+        );
+        assert_eq!(
+            shelf.fit(&Rectangle::new(11, 1), Containment::Inclusive),
+            None
+        );
+    }
+
+    #[test]
+    fn inclusive_lets_sides_touch() {
+        let lid = Rectangle::new(6, 4);
+        let flush = Rectangle::new(4, 6);
+        assert!(!lid.holds(&flush, Containment::Strict));
+        assert_eq!(
+            lid.fit(&flush, Containment::Inclusive),
+            Some(Orientation::Rotated)
+        );
+        assert_eq!(
+            lid.fit(&lid, Containment::Inclusive),
+            Some(Orientation::AsIs)
+        );
+    }
+}
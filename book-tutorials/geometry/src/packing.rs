@@ -0,0 +1,585 @@
+// packing rectangles into as few same-sized containers as we can, e.g. the pieces of a cut list
+// onto sheets of plywood.
+//
+// two ways of going about it:
+// - shelves: pieces go left to right in rows, tallest first, and a new row starts on top of the
+//   last one when a piece won't fit on any row. quick, and easy to cut, but it wastes the space
+//   above the shorter pieces in a row
+// - guillotine: every piece goes into the free space it fills best, and what's left of that space
+//   is split in two with one straight cut. slower, but usually tighter
+//
+// neither is guaranteed to find the fewest containers - nothing quick is - but both do well when
+// the pieces are sorted biggest first, which they are.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::{Containment, Orientation, Rectangle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    Shelf,
+    Guillotine,
+}
+
+// where one piece went. (x, y) is its corner nearest the container's, with x along the width
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    // its position in the list that was packed
+    pub item: usize,
+    // which container, counting from 0
+    pub bin: usize,
+    pub x: u32,
+    pub y: u32,
+    pub orientation: Orientation,
+    // the piece the way it lies, so already turned if it was rotated
+    pub size: Rectangle,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packing {
+    pub container: Rectangle,
+    pub bins: usize,
+    // in the order they were placed
+    pub placements: Vec<Placement>,
+}
+
+impl Packing {
+    // how much of all the containers the pieces cover, as a percentage
+    pub fn utilization(&self) -> f64 {
+        percent(
+            self.placements.iter().map(|p| area(&p.size)).sum(),
+            area(&self.container) * self.bins as u64,
+        )
+    }
+
+    // the same for a single container
+    pub fn bin_utilization(&self, bin: usize) -> f64 {
+        let used = self
+            .placements
+            .iter()
+            .filter(|p| p.bin == bin)
+            .map(|p| area(&p.size))
+            .sum();
+        percent(used, area(&self.container))
+    }
+
+    pub fn placements_in(&self, bin: usize) -> impl Iterator<Item = &Placement> {
+        self.placements.iter().filter(move |p| p.bin == bin)
+    }
+}
+
+// one line per piece, container by container, then how much got used
+impl fmt::Display for Packing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for bin in 0..self.bins {
+            writeln!(
+                f,
+                "container {} ({:.1}% used):",
+                bin + 1,
+                self.bin_utilization(bin)
+            )?;
+            for p in self.placements_in(bin) {
+                write!(
+                    f,
+                    "  piece {} at ({}, {}): {}",
+                    p.item + 1,
+                    p.x,
+                    p.y,
+                    p.size
+                )?;
+                if p.orientation == Orientation::Rotated {
+                    write!(f, " (rotated)")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        writeln!(
+            f,
+            "{} container(s), {:.1}% used",
+            self.bins,
+            self.utilization()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackingError {
+    // the container has a side of zero
+    EmptyContainer,
+    // a piece with a side of zero, which takes no room and can't be cut
+    EmptyItem { item: usize, size: Rectangle },
+    // a piece that wouldn't fit in an empty container, even turned if turning is allowed
+    TooBig { item: usize, size: Rectangle },
+}
+
+impl fmt::Display for PackingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackingError::EmptyContainer => {
+                write!(f, "nothing fits in a container with a side of zero")
+            }
+            PackingError::EmptyItem { item, size } => {
+                write!(f, "piece {} ({}) has a side of zero", item + 1, size)
+            }
+            PackingError::TooBig { item, size } => {
+                write!(
+                    f,
+                    "piece {} ({}) is bigger than the container",
+                    item + 1,
+                    size
+                )
+            }
+        }
+    }
+}
+
+impl Error for PackingError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packer {
+    container: Rectangle,
+    heuristic: Heuristic,
+    rotation: bool,
+}
+
+impl Packer {
+    // shelves, and no turning pieces, until told otherwise
+    pub fn new(container: Rectangle) -> Self {
+        Self {
+            container,
+            heuristic: Heuristic::Shelf,
+            rotation: false,
+        }
+    }
+
+    pub fn heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    // whether pieces may be turned a quarter turn, e.g. not when the grain has to run one way
+    pub fn allow_rotation(mut self, rotation: bool) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn pack(&self, items: &[Rectangle]) -> Result<Packing, PackingError> {
+        if self.container.width() == 0 || self.container.height() == 0 {
+            return Err(PackingError::EmptyContainer);
+        }
+        for (item, size) in items.iter().enumerate() {
+            if size.width() == 0 || size.height() == 0 {
+                return Err(PackingError::EmptyItem { item, size: *size });
+            }
+            if !self.fits_empty(size) {
+                return Err(PackingError::TooBig { item, size: *size });
+            }
+        }
+        let placements = match self.heuristic {
+            Heuristic::Shelf => self.shelves(items),
+            Heuristic::Guillotine => self.guillotine(items),
+        };
+        Ok(Packing {
+            container: self.container,
+            bins: placements.iter().map(|p| p.bin + 1).max().unwrap_or(0),
+            placements,
+        })
+    }
+
+    fn fits_empty(&self, item: &Rectangle) -> bool {
+        match self.container.fit(item, Containment::Inclusive) {
+            Some(Orientation::AsIs) => true,
+            Some(Orientation::Rotated) => self.rotation,
+            None => false,
+        }
+    }
+
+    // the ways a piece may lie, the way it was given first
+    fn orientations(&self, item: &Rectangle) -> Vec<(Orientation, Rectangle)> {
+        let mut ways = vec![(Orientation::AsIs, *item)];
+        if self.rotation && item.width() != item.height() {
+            ways.push((Orientation::Rotated, item.rotated()));
+        }
+        ways
+    }
+
+    fn shelves(&self, items: &[Rectangle]) -> Vec<Placement> {
+        // lay each piece flat when it's allowed to turn (and still fits that way), so the rows
+        // stay low, then go tallest first
+        let mut order: Vec<(usize, Orientation, Rectangle)> = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let turned = item.rotated();
+                let flat =
+                    self.rotation && item.width() < item.height() && fits(&self.container, &turned);
+                // anything that doesn't fit as it is was only let through because it can turn
+                if flat || !fits(&self.container, item) {
+                    (index, Orientation::Rotated, turned)
+                } else {
+                    (index, Orientation::AsIs, *item)
+                }
+            })
+            .collect();
+        order.sort_by_key(|(index, _, size)| (std::cmp::Reverse(size.height()), *index));
+
+        let mut bins: Vec<Vec<Shelf>> = Vec::new();
+        let mut placements = Vec::new();
+        for (item, orientation, size) in order {
+            let on_shelf = bins.iter_mut().enumerate().find_map(|(bin, shelves)| {
+                let shelf = shelves
+                    .iter_mut()
+                    .find(|shelf| shelf.has_room(&size, self.container.width()))?;
+                Some((bin, shelf.put(&size)))
+            });
+            let (bin, x, y) = match on_shelf {
+                Some((bin, (x, y))) => (bin, x, y),
+                None => {
+                    let height = self.container.height();
+                    let bin = bins
+                        .iter()
+                        .position(|shelves| ends_within(top(shelves), size.height(), height));
+                    let bin = bin.unwrap_or_else(|| {
+                        bins.push(Vec::new());
+                        bins.len() - 1
+                    });
+                    let mut shelf = Shelf {
+                        y: top(&bins[bin]),
+                        height: size.height(),
+                        used: 0,
+                    };
+                    let (x, y) = shelf.put(&size);
+                    bins[bin].push(shelf);
+                    (bin, x, y)
+                }
+            };
+            placements.push(Placement {
+                item,
+                bin,
+                x,
+                y,
+                orientation,
+                size,
+            });
+        }
+        placements
+    }
+
+    fn guillotine(&self, items: &[Rectangle]) -> Vec<Placement> {
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        order.sort_by_key(|&index| (std::cmp::Reverse(area(&items[index])), index));
+
+        let mut bins: Vec<Vec<Space>> = Vec::new();
+        let mut placements = Vec::new();
+        for item in order {
+            let ways = self.orientations(&items[item]);
+            // the first container with room, and in it the space the piece fills best
+            let best = bins.iter().enumerate().find_map(|(bin, spaces)| {
+                let mut options = Vec::new();
+                for (space, free) in spaces.iter().enumerate() {
+                    for (orientation, size) in &ways {
+                        if fits(&free.size, size) {
+                            options.push((free.waste(size), space, *orientation, *size));
+                        }
+                    }
+                }
+                let (_, space, orientation, size) =
+                    options.into_iter().min_by_key(|option| option.0)?;
+                Some((bin, space, orientation, size))
+            });
+            let (bin, space, orientation, size) = best.unwrap_or_else(|| {
+                bins.push(vec![Space {
+                    x: 0,
+                    y: 0,
+                    size: self.container,
+                }]);
+                let (orientation, size) = ways
+                    .into_iter()
+                    .find(|(_, size)| fits(&self.container, size))
+                    .expect("every piece was checked against an empty container");
+                (bins.len() - 1, 0, orientation, size)
+            });
+            let free = bins[bin].remove(space);
+            bins[bin].extend(free.split(&size));
+            placements.push(Placement {
+                item,
+                bin,
+                x: free.x,
+                y: free.y,
+                orientation,
+                size,
+            });
+        }
+        placements
+    }
+}
+
+// a row of pieces across a container, as tall as the first piece put on it
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    // how far across it's filled
+    used: u32,
+}
+
+impl Shelf {
+    fn has_room(&self, size: &Rectangle, width: u32) -> bool {
+        size.height() <= self.height && ends_within(self.used, size.width(), width)
+    }
+
+    fn put(&mut self, size: &Rectangle) -> (u32, u32) {
+        let x = self.used;
+        self.used += size.width();
+        (x, self.y)
+    }
+}
+
+// whether something `length` long starting at `start` stops at or before `limit`. sides can be
+// anything up to u32::MAX, so the end is only worked out if it can be counted
+fn ends_within(start: u32, length: u32, limit: u32) -> bool {
+    start.checked_add(length).is_some_and(|end| end <= limit)
+}
+
+// where the next shelf would start
+fn top(shelves: &[Shelf]) -> u32 {
+    shelves.last().map_or(0, |shelf| shelf.y + shelf.height)
+}
+
+// an empty part of a container, for the guillotine
+#[derive(Debug, Clone, Copy)]
+struct Space {
+    x: u32,
+    y: u32,
+    size: Rectangle,
+}
+
+impl Space {
+    // how badly `size` fits: the area it leaves, then the shorter of the strips left beside it
+    fn waste(&self, size: &Rectangle) -> (u64, u32) {
+        let left_over = area(&self.size) - area(size);
+        let short_side = (self.size.width() - size.width()).min(self.size.height() - size.height());
+        (left_over, short_side)
+    }
+
+    // what's left once `size` is cut from the corner: one straight cut across the whole space,
+    // then one across the piece's strip. the first cut goes along the longer leftover, so the
+    // bigger of the two spaces stays in one piece
+    fn split(&self, size: &Rectangle) -> Vec<Space> {
+        let right = self.size.width() - size.width();
+        let above = self.size.height() - size.height();
+        let (right_height, above_width) = if right < above {
+            (size.height(), self.size.width())
+        } else {
+            (self.size.height(), size.width())
+        };
+        let spaces = [
+            Space {
+                x: self.x + size.width(),
+                y: self.y,
+                size: Rectangle::new(right, right_height),
+            },
+            Space {
+                x: self.x,
+                y: self.y + size.height(),
+                size: Rectangle::new(above_width, above),
+            },
+        ];
+        spaces
+            .into_iter()
+            .filter(|space| space.size.area() != Some(0))
+            .collect()
+    }
+}
+
+// whether `item` fits in `space` as it lies, sides touching allowed
+fn fits(space: &Rectangle, item: &Rectangle) -> bool {
+    space.fit(item, Containment::Inclusive) == Some(Orientation::AsIs)
+}
+
+// areas as u64, which every u32 by u32 rectangle fits in
+fn area(size: &Rectangle) -> u64 {
+    u64::from(size.width()) * u64::from(size.height())
+}
+
+fn percent(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    used as f64 * 100.0 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every piece inside its container, and no two in the same container overlapping
+    fn assert_laid_out(packing: &Packing) {
+        let sheet = packing.container;
+        for (i, a) in packing.placements.iter().enumerate() {
+            assert!(
+                a.x + a.size.width() <= sheet.width() && a.y + a.size.height() <= sheet.height()
+            );
+            for b in &packing.placements[i + 1..] {
+                let apart = a.x + a.size.width() <= b.x
+                    || b.x + b.size.width() <= a.x
+                    || a.y + a.size.height() <= b.y
+                    || b.y + b.size.height() <= a.y;
+                assert!(a.bin != b.bin || apart, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    fn cut_list() -> Vec<Rectangle> {
+        vec![
+            Rectangle::new(120, 60),
+            Rectangle::new(60, 40),
+            Rectangle::new(40, 120),
+            Rectangle::new(100, 30),
+            Rectangle::new(30, 30),
+            Rectangle::new(80, 50),
+        ]
+    }
+
+    #[test]
+    fn shelves_go_tallest_first() {
+        let sheet = Rectangle::new(240, 120);
+        let packing = Packer::new(sheet).pack(&cut_list()).unwrap();
+        assert_laid_out(&packing);
+        // the 120 tall piece makes the first shelf the whole sheet high, so the rest of the
+        // pieces only get what's left of that one row, and then a second sheet
+        let first: Vec<(usize, u32, u32)> = packing
+            .placements_in(0)
+            .map(|p| (p.item, p.x, p.y))
+            .collect();
+        assert_eq!(first, vec![(2, 0, 0), (0, 40, 0), (5, 160, 0)]);
+        assert_eq!(packing.bins, 2);
+
+        // turned pieces and tighter spaces get it all onto one
+        let packing = Packer::new(sheet)
+            .heuristic(Heuristic::Guillotine)
+            .allow_rotation(true)
+            .pack(&cut_list())
+            .unwrap();
+        assert_laid_out(&packing);
+        assert_eq!(packing.bins, 1);
+        assert_eq!(format!("{:.1}", packing.utilization()), "77.4");
+    }
+
+    #[test]
+    fn guillotine_fills_space_the_shelves_leave() {
+        let sheet = Rectangle::new(100, 100);
+        let pieces = vec![
+            Rectangle::new(60, 100),
+            Rectangle::new(40, 50),
+            Rectangle::new(40, 50),
+        ];
+        let shelves = Packer::new(sheet).pack(&pieces).unwrap();
+        let guillotine = Packer::new(sheet)
+            .heuristic(Heuristic::Guillotine)
+            .pack(&pieces)
+            .unwrap();
+        assert_laid_out(&shelves);
+        assert_laid_out(&guillotine);
+        assert_eq!(guillotine.bins, 1);
+        assert_eq!(guillotine.utilization(), 100.0);
+        // the second 40 x 50 can't sit on the 100 tall shelf's row and needs a shelf of its own
+        assert_eq!(shelves.bins, 2);
+        assert!(shelves
+            .to_string()
+            .ends_with("2 container(s), 50.0% used\n"));
+    }
+
+    #[test]
+    fn turning_pieces_saves_sheets() {
+        let sheet = Rectangle::new(100, 50);
+        let pieces = vec![Rectangle::new(50, 100), Rectangle::new(50, 100)];
+        assert_eq!(
+            Packer::new(sheet).pack(&pieces),
+            Err(PackingError::TooBig {
+                item: 0,
+                size: Rectangle::new(50, 100)
+            })
+        );
+        for heuristic in [Heuristic::Shelf, Heuristic::Guillotine] {
+            let packing = Packer::new(sheet)
+                .heuristic(heuristic)
+                .allow_rotation(true)
+                .pack(&pieces)
+                .unwrap();
+            assert_laid_out(&packing);
+            assert_eq!(packing.bins, 2);
+            assert!(packing
+                .placements
+                .iter()
+                .all(|p| p.orientation == Orientation::Rotated));
+            assert_eq!(packing.bin_utilization(1), 100.0);
+        }
+    }
+
+    #[test]
+    fn pieces_with_a_side_of_zero_are_turned_away() {
+        let pieces = vec![Rectangle::new(10, 10), Rectangle::new(0, 30)];
+        for heuristic in [Heuristic::Shelf, Heuristic::Guillotine] {
+            let err = Packer::new(Rectangle::new(100, 100))
+                .heuristic(heuristic)
+                .pack(&pieces)
+                .unwrap_err();
+            assert_eq!(
+                err,
+                PackingError::EmptyItem {
+                    item: 1,
+                    size: Rectangle::new(0, 30)
+                }
+            );
+            assert_eq!(err.to_string(), "piece 2 (0 x 30) has a side of zero");
+        }
+    }
+
+    #[test]
+    fn huge_containers_dont_overflow() {
+        let pieces = vec![Rectangle::new(1, 2_500_000_000); 2];
+        let tall = Rectangle::new(1, 3_000_000_000);
+        let wide = Rectangle::new(3_000_000_000, 1);
+        let rotated: Vec<Rectangle> = pieces.iter().map(Rectangle::rotated).collect();
+        for heuristic in [Heuristic::Shelf, Heuristic::Guillotine] {
+            for (sheet, pieces) in [(tall, &pieces), (wide, &rotated)] {
+                let packing = Packer::new(sheet)
+                    .heuristic(heuristic)
+                    .pack(pieces)
+                    .unwrap();
+                assert_laid_out(&packing);
+                assert_eq!(packing.bins, 2);
+            }
+        }
+    }
+
+    #[test]
+    fn big_cut_lists_stay_in_bounds() {
+        let sheet = Rectangle::new(244, 122);
+        let pieces: Vec<Rectangle> = (0..40)
+            .map(|i| Rectangle::new(10 + (i * 37) % 90, 8 + (i * 23) % 70))
+            .collect();
+        for heuristic in [Heuristic::Shelf, Heuristic::Guillotine] {
+            for rotation in [false, true] {
+                let packing = Packer::new(sheet)
+                    .heuristic(heuristic)
+                    .allow_rotation(rotation)
+                    .pack(&pieces)
+                    .unwrap();
+                assert_laid_out(&packing);
+                let mut packed: Vec<usize> = packing.placements.iter().map(|p| p.item).collect();
+                packed.sort();
+                assert_eq!(packed, (0..40).collect::<Vec<_>>());
+                assert!(
+                    packing.utilization() > 50.0,
+                    "{:?} {}: {}",
+                    heuristic,
+                    rotation,
+                    packing.utilization()
+                );
+            }
+        }
+    }
+}
@@ -1,26 +1,14 @@
+use geometry::Rectangle;
+
 fn main() {
-    let rect1 = Rectangle {
-        width: 420,
-        height: dbg!(17),
-    };
+    let rect1 = Rectangle::new(420, dbg!(17));
 
     println!(
         "The area of the rectangle is {} square units.",
-        rect1.area()
+        rect1.area().expect("a 420 x 17 rectangle is small enough to measure")
     );
 
-    println!("the height was {} units", rect1.height);
+    println!("the height was {} units", rect1.height());
     dbg!(&rect1);
     println!("the full struct was: {:#?}", rect1);
 }
-
-#[derive(Debug)]
-struct Rectangle {
-    width: u32,
-    height: u32,
-}
-impl Rectangle {
-    fn area(&self) -> u32 {
-        self.width * self.height
-    }
-}
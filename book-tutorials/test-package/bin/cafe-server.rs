@@ -0,0 +1,83 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use test_package::http::Server;
+use test_package::loyalty::CustomerBook;
+use test_package::policy::{AgePolicy, AuditLog};
+use test_package::{Context, Error, PriceTable};
+
+const USAGE: &str =
+  "usage: cafe-server [address] [--customers <path>] [--policy <file> --jurisdiction <name>] [--audit <path>]";
+
+// only reachable from this machine unless told otherwise
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+// the same loyalty card as the till
+const DEFAULT_CUSTOMERS: &str = "customers.csv";
+// member lookups are only served when this is set, and need it as a bearer token
+const STAFF_TOKEN_VAR: &str = "CAFE_STAFF_TOKEN";
+// the same ID check trail as the till
+const DEFAULT_AUDIT: &str = "id-checks.csv";
+// used when no policy file is given. beer is never sold without an ID check
+const DEFAULT_MINIMUM_AGE: u32 = 21;
+
+fn main() {
+  let mut address = String::from(DEFAULT_ADDRESS);
+  let mut customers_path = String::from(DEFAULT_CUSTOMERS);
+  let mut audit_path = String::from(DEFAULT_AUDIT);
+  let mut policy_path = None;
+  let mut jurisdiction = None;
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().unwrap_or_else(|| fail(USAGE));
+    match arg.as_str() {
+      "--customers" => customers_path = value(),
+      "--audit" => audit_path = value(),
+      "--policy" => policy_path = Some(value()),
+      "--jurisdiction" => jurisdiction = Some(value()),
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        return;
+      }
+      _ if arg.starts_with('-') => fail(USAGE),
+      _ => address = arg,
+    }
+  }
+
+  let policy = match (policy_path, jurisdiction) {
+    (None, None) => AgePolicy::new("default", DEFAULT_MINIMUM_AGE),
+    (Some(path), Some(jurisdiction)) => read_policy(&path, &jurisdiction).unwrap_or_else(|e| e.exit()),
+    _ => fail("--policy and --jurisdiction go together"),
+  };
+  let mut server = open_server(&address, &customers_path).unwrap_or_else(|e| e.exit());
+  server.set_policy(policy, AuditLog::with_file(audit_path));
+  match server.local_addr() {
+    Ok(addr) => println!("test-package cafe taking orders on http://{}", addr),
+    Err(_) => println!("test-package cafe taking orders on http://{}", address),
+  }
+  server.serve();
+}
+
+fn open_server(address: &str, customers_path: &str) -> Result<Server, Error> {
+  let mut server =
+    Server::bind(address, PriceTable::standard()).context(format!("couldn't listen on {}", address))?;
+  match env::var(STAFF_TOKEN_VAR) {
+    Ok(token) if !token.trim().is_empty() => {
+      // opened once up front so a bad file stops the server now, not on the first lookup
+      CustomerBook::open(customers_path).context(customers_path)?;
+      server.set_customers(customers_path, token.trim());
+    }
+    _ => eprintln!("{} isn't set, so members can't be looked up", STAFF_TOKEN_VAR),
+  }
+  Ok(server)
+}
+
+fn read_policy(path: &str, jurisdiction: &str) -> Result<AgePolicy, Error> {
+  let text = fs::read_to_string(path).context(path)?;
+  AgePolicy::from_config(&text, jurisdiction).context(path)
+}
+
+fn fail(message: &str) -> ! {
+  eprintln!("{}", message);
+  process::exit(1);
+}
@@ -0,0 +1,26 @@
+use std::env;
+use std::process;
+
+use test_package::date::Date;
+use test_package::Farmstand;
+
+const USAGE: &str = "usage: farmstand-board [<YYYY-MM-DD>]";
+
+// prints the week's availability board for the farm stand: this week, or the week of the date given
+fn main() {
+  let date = match env::args().skip(1).collect::<Vec<String>>().as_slice() {
+    [] => Date::today(),
+    [flag] if flag == "-h" || flag == "--help" => {
+      println!("{}", USAGE);
+      return;
+    }
+    [date] => date.parse().unwrap_or_else(|e| fail(&format!("{}\n{}", e, USAGE))),
+    _ => fail(USAGE),
+  };
+  print!("{}", Farmstand::standard().availability_board(date));
+}
+
+fn fail(message: &str) -> ! {
+  eprintln!("{}", message);
+  process::exit(1);
+}
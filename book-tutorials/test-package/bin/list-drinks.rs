@@ -1,10 +1,77 @@
-use crate::Drinks;
+use std::env;
+use std::process;
+
+use test_package::{Allergen, Drinks, MenuQuery, Nutrition};
+
+const USAGE: &str = "usage: list-drinks [--labels] [--without <dairy|soy|nuts>]... [--caffeine-under <mg>] \
+[--calories-under <kcal>]";
 
 fn main () {
+  let mut labels = false;
+  let mut query = MenuQuery::new();
+  let mut filtered = false;
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().unwrap_or_else(|| fail(USAGE));
+    match arg.as_str() {
+      "--labels" => labels = true,
+      "--without" => {
+        let name = value();
+        let allergen = Allergen::from_name(&name).unwrap_or_else(|| fail(&format!("unknown allergen: {}", name)));
+        query = query.without(allergen);
+        filtered = true;
+      }
+      "--caffeine-under" => {
+        query = query.caffeine_under(number(&value()));
+        filtered = true;
+      }
+      "--calories-under" => {
+        query = query.calories_under(number(&value()));
+        filtered = true;
+      }
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        return;
+      }
+      _ => fail(USAGE),
+    }
+  }
 
   println!("Hello, and welcome to the test-package cafe!");
-  println!("The available drinks are: ");
-  for name in Drinks {
-    println!(name);
+  if !filtered {
+    println!("The available drinks are: ");
+    for drink in Drinks::all() {
+      print_drink(drink, labels);
+    }
+    return;
+  }
+  // asking is only worth it with the answers showing
+  println!("The drinks that fit are: ");
+  for drink in query.drinks() {
+    print_drink(drink, true);
+  }
+  let others = query.with_other_milk();
+  if !others.is_empty() {
+    println!("And with a different milk: ");
+    for item in others {
+      println!("{:<40}{}", item.to_string(), Nutrition::of(&item));
+    }
+  }
+}
+
+fn print_drink(drink: Drinks, labels: bool) {
+  if labels {
+    println!("{:<24}{}", drink.to_string(), Nutrition::of_drink(drink));
+  } else {
+    println!("{}", drink);
   }
 }
+
+fn number(text: &str) -> u32 {
+  text.parse().unwrap_or_else(|_| fail(&format!("not a number: {}", text)))
+}
+
+fn fail(message: &str) -> ! {
+  eprintln!("{}", message);
+  process::exit(1);
+}
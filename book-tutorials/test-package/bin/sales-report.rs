@@ -0,0 +1,53 @@
+use std::env;
+use std::process;
+
+use test_package::date::Date;
+use test_package::ledger::{Ledger, SalesReport};
+use test_package::Context;
+
+const USAGE: &str = "usage: sales-report [--ledger <path>] [<from YYYY-MM-DD> [<to YYYY-MM-DD>]]";
+
+// the till's default ledger
+const DEFAULT_LEDGER: &str = "sales-ledger.csv";
+
+fn main() {
+  let mut ledger_path = String::from(DEFAULT_LEDGER);
+  let mut dates = Vec::new();
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--ledger" => match args.next() {
+        Some(path) => ledger_path = path,
+        None => fail(USAGE),
+      },
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        return;
+      }
+      other => match other.parse::<Date>() {
+        Ok(date) => dates.push(date),
+        Err(e) => fail(&format!("{}\n{}", e, USAGE)),
+      },
+    }
+  }
+
+  // no dates is today, one date is just that day
+  let (from, to) = match dates.as_slice() {
+    [] => (Date::today(), Date::today()),
+    [day] => (*day, *day),
+    [from, to] if from <= to => (*from, *to),
+    [_, _] => fail("the range has to start before it ends"),
+    _ => fail(USAGE),
+  };
+
+  let sales = Ledger::open(&ledger_path).and_then(|ledger| ledger.read_between(from, to));
+  match sales.context(&ledger_path) {
+    Ok(sales) => print!("{}", SalesReport::new(from, to, &sales)),
+    Err(e) => e.exit(),
+  }
+}
+
+fn fail(message: &str) -> ! {
+  eprintln!("{}", message);
+  process::exit(1);
+}
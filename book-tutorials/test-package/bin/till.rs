@@ -0,0 +1,104 @@
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::process;
+
+use test_package::inventory::StockLedger;
+use test_package::ledger::Ledger;
+use test_package::loyalty::CustomerBook;
+use test_package::owen_oblivion::PerishableStock;
+use test_package::policy::{AgePolicy, AuditLog};
+use test_package::till::Till;
+use test_package::{Context, Error, Farmstand, PriceTable, Promotions};
+
+const USAGE: &str = "usage: till [--ledger <path>] [--policy <file> --jurisdiction <name>] [--audit <path>] \
+[--promotions <file>] [--customers <path>] [--stock <file>] [--perishables <file>]";
+
+// where sales and ID checks are written unless told otherwise
+const DEFAULT_LEDGER: &str = "sales-ledger.csv";
+const DEFAULT_AUDIT: &str = "id-checks.csv";
+const DEFAULT_CUSTOMERS: &str = "customers.csv";
+// used when no policy file is given. beer is never sold without an ID check
+const DEFAULT_MINIMUM_AGE: u32 = 21;
+
+fn main() {
+  let mut ledger_path = String::from(DEFAULT_LEDGER);
+  let mut audit_path = String::from(DEFAULT_AUDIT);
+  let mut policy_path = None;
+  let mut jurisdiction = None;
+  let mut promotions_path = None;
+  let mut customers_path = String::from(DEFAULT_CUSTOMERS);
+  let mut stock_path = None;
+  let mut perishables_path = None;
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().unwrap_or_else(|| fail(USAGE));
+    match arg.as_str() {
+      "--ledger" => ledger_path = value(),
+      "--audit" => audit_path = value(),
+      "--policy" => policy_path = Some(value()),
+      "--jurisdiction" => jurisdiction = Some(value()),
+      "--promotions" => promotions_path = Some(value()),
+      "--customers" => customers_path = value(),
+      "--stock" => stock_path = Some(value()),
+      "--perishables" => perishables_path = Some(value()),
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        return;
+      }
+      _ => fail(USAGE),
+    }
+  }
+
+  let policy = match (policy_path, jurisdiction) {
+    (None, None) => AgePolicy::new("default", DEFAULT_MINIMUM_AGE),
+    (Some(path), Some(jurisdiction)) => read_config(&path, |text| AgePolicy::from_config(text, &jurisdiction)),
+    _ => fail("--policy and --jurisdiction go together"),
+  };
+  // no file, no promotions
+  let promotions = match promotions_path {
+    None => Promotions::new(),
+    Some(path) => read_config(&path, Promotions::from_config),
+  };
+  // no file, no stock check: anything on the menu can be sold
+  let stock = stock_path.map(|path| read_config(&path, StockLedger::from_config));
+  // no file, nothing expires
+  let perishables = perishables_path.map(|path| read_config(&path, PerishableStock::from_config));
+  let ledger = Ledger::open(&ledger_path).context(&ledger_path).unwrap_or_else(|e| e.exit());
+  let customers = CustomerBook::open(&customers_path).context(&customers_path).unwrap_or_else(|e| e.exit());
+
+  let stdin = io::stdin();
+  let mut till = Till::new(PriceTable::standard());
+  till.set_farmstand(Farmstand::standard());
+  till.set_promotions(promotions);
+  till.set_ledger(ledger);
+  till.set_customers(customers);
+  if let Some(stock) = stock {
+    till.set_stock(stock);
+  }
+  if let Some(perishables) = perishables {
+    till.set_perishables(perishables);
+  }
+  till.set_policy(policy, AuditLog::with_file(audit_path));
+  if let Ok(user) = env::var("USER") {
+    till.set_operator(user);
+  }
+  // only prompt when a person is typing, so piped-in scripts get clean output
+  till.show_prompt(stdin.is_terminal());
+
+  println!("test-package cafe till - type \"help\" for commands");
+  if let Err(e) = till.run(stdin.lock(), io::stdout()).context("till stopped") {
+    e.exit();
+  }
+}
+
+// a config file read and made sense of, or the reason why not
+fn read_config<T, E: Into<Error>>(path: &str, parse: impl FnOnce(&str) -> Result<T, E>) -> T {
+  let text = fs::read_to_string(path).context(path).unwrap_or_else(|e| e.exit());
+  parse(&text).context(path).unwrap_or_else(|e| e.exit())
+}
+
+fn fail(message: &str) -> ! {
+  eprintln!("{}", message);
+  process::exit(1);
+}
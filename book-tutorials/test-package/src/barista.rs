@@ -0,0 +1,214 @@
+// the bar: orders go into a queue and a crew of barista threads takes them off it one at a time.
+// this is the thread pool from the fearless_concurrency notes - the workers share one Receiver
+// behind an Arc<Mutex<...>>, and report what they're doing back over a second channel.
+//
+// making a drink takes real minutes, so the queue can be sped up (a speedup of 60 turns every
+// minute into a second) for demos and tests. for planning there is also estimate_waits, which
+// works the same queue out on paper without any threads or sleeping.
+
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::custom::{CustomDrink, Size};
+use crate::order::Order;
+use crate::{CoffeeFlavor, Drinks};
+
+// how long one of this drink takes to make, at normal speed
+pub fn prep_time(item: &CustomDrink) -> Duration {
+  let base = match item.drink() {
+    Drinks::Water => 10,
+    Drinks::Soda => 15,
+    Drinks::Beer => 30,
+    Drinks::Coffee(CoffeeFlavor::Americano) => 90,
+    Drinks::Coffee(CoffeeFlavor::Latte) => 150,
+    Drinks::Coffee(CoffeeFlavor::Capuchino) => 150,
+    Drinks::Coffee(CoffeeFlavor::Mocha) => 180,
+  };
+  let size = match item.size() {
+    Size::Large => 15,
+    _ => 0,
+  };
+  let shots = 20 * u64::from(item.extra_shots());
+  let syrups = 10 * item.syrups().len() as u64;
+  Duration::from_secs(base + size + shots + syrups)
+}
+
+// how long a whole order takes one barista
+pub fn order_prep_time(order: &Order) -> Duration {
+  order.lines().iter().map(|line| prep_time(&line.item) * line.quantity).sum()
+}
+
+// when each order (in the order given) would be ready if they all arrived at once and
+// `baristas` people worked through them first come first served
+pub fn estimate_waits(orders: &[Order], baristas: usize) -> Vec<Duration> {
+  assert!(baristas > 0, "somebody has to make the drinks");
+  // when each barista will next be free
+  let mut free_at = vec![Duration::ZERO; baristas];
+  orders
+    .iter()
+    .map(|order| {
+      let next = free_at.iter_mut().min().expect("there is at least one barista");
+      *next += order_prep_time(order);
+      *next
+    })
+    .collect()
+}
+
+// the fewest baristas that get every order out within `max_wait`, or None if even one barista
+// per order isn't enough (a single order takes longer than that on its own)
+pub fn baristas_needed(orders: &[Order], max_wait: Duration) -> Option<usize> {
+  if orders.is_empty() {
+    return Some(0);
+  }
+  (1..=orders.len()).find(|&baristas| {
+    estimate_waits(orders, baristas)
+      .iter()
+      .all(|wait| *wait <= max_wait)
+  })
+}
+
+// handed out by submit so the caller can match events back up to orders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ticket(pub u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BarEvent {
+  Started { ticket: Ticket, barista: usize },
+  // prep_time is the normal-speed time, however much the queue was sped up
+  Completed { ticket: Ticket, barista: usize, prep_time: Duration },
+}
+
+struct Job {
+  ticket: Ticket,
+  order: Order,
+}
+
+pub struct OrderQueue {
+  // None once the queue has been shut down
+  jobs: Option<mpsc::Sender<Job>>,
+  events: mpsc::Receiver<BarEvent>,
+  workers: Vec<thread::JoinHandle<()>>,
+  next_ticket: u64,
+}
+impl OrderQueue {
+  pub fn new(baristas: usize, speedup: u32) -> Self {
+    assert!(baristas > 0, "somebody has to make the drinks");
+    assert!(speedup > 0, "a speedup of zero would never finish anything");
+
+    let (jobs, queue) = mpsc::channel::<Job>();
+    let (event_sender, events) = mpsc::channel();
+    // every barista pulls from the same queue, so it gets shared the same way the counter was
+    let queue = Arc::new(Mutex::new(queue));
+
+    let workers = (0..baristas)
+      .map(|barista| {
+        let queue = Arc::clone(&queue);
+        let events = event_sender.clone();
+        thread::spawn(move || loop {
+          // hold the lock only long enough to take the next job, not while making it
+          let job = queue.lock().unwrap().recv();
+          let job = match job {
+            Ok(job) => job,
+            // the sending side is gone: the queue was shut down and drained
+            Err(_) => break,
+          };
+          // nobody listening for events isn't a reason to stop making drinks
+          let _ = events.send(BarEvent::Started { ticket: job.ticket, barista });
+          let prep_time = order_prep_time(&job.order);
+          thread::sleep(prep_time / speedup);
+          let _ = events.send(BarEvent::Completed { ticket: job.ticket, barista, prep_time });
+        })
+      })
+      .collect();
+
+    Self {
+      jobs: Some(jobs),
+      events,
+      workers,
+      next_ticket: 1,
+    }
+  }
+
+  pub fn submit(&mut self, order: Order) -> Ticket {
+    let ticket = Ticket(self.next_ticket);
+    self.next_ticket += 1;
+    self
+      .jobs
+      .as_ref()
+      .expect("the queue is only shut down when it is dropped")
+      .send(Job { ticket, order })
+      .expect("baristas only stop once the queue is shut down");
+    ticket
+  }
+
+  // what the baristas have been up to. the channel closes once every barista has gone home
+  pub fn events(&self) -> &mpsc::Receiver<BarEvent> {
+    &self.events
+  }
+
+  // block until the next order is finished, returning its ticket
+  pub fn wait_for_next(&self) -> Option<Ticket> {
+    self.events.iter().find_map(|event| match event {
+      BarEvent::Completed { ticket, .. } => Some(ticket),
+      BarEvent::Started { .. } => None,
+    })
+  }
+
+  // stop taking orders, let the baristas finish everything already queued and send them home
+  pub fn shutdown(mut self) -> Vec<BarEvent> {
+    self.close();
+    self.events.try_iter().collect()
+  }
+
+  fn close(&mut self) {
+    drop(self.jobs.take());
+    for worker in self.workers.drain(..) {
+      worker.join().unwrap();
+    }
+  }
+}
+impl Drop for OrderQueue {
+  fn drop(&mut self) {
+    self.close();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn order_of(drink: Drinks, quantity: u32) -> Order {
+    let mut order = Order::new();
+    order.add(drink, quantity).unwrap();
+    order
+  }
+
+  #[test]
+  fn estimates_waits_with_a_shared_queue() {
+    let latte = Drinks::Coffee(CoffeeFlavor::Latte);
+    let orders = vec![order_of(latte, 2), order_of(Drinks::Water, 1), order_of(latte, 1)];
+
+    let one = estimate_waits(&orders, 1);
+    assert_eq!(one, vec![Duration::from_secs(300), Duration::from_secs(310), Duration::from_secs(460)]);
+
+    // the second barista takes the water, and then the last latte
+    let two = estimate_waits(&orders, 2);
+    assert_eq!(two, vec![Duration::from_secs(300), Duration::from_secs(10), Duration::from_secs(160)]);
+
+    assert_eq!(baristas_needed(&orders, Duration::from_secs(300)), Some(2));
+    assert_eq!(baristas_needed(&orders, Duration::from_secs(200)), None);
+  }
+
+  #[test]
+  fn baristas_report_every_order() {
+    let mut queue = OrderQueue::new(3, 10_000);
+    let tickets: Vec<Ticket> = (0..5).map(|_| queue.submit(order_of(Drinks::Soda, 1))).collect();
+
+    let mut done: Vec<Ticket> = (0..5).filter_map(|_| queue.wait_for_next()).collect();
+    done.sort();
+    assert_eq!(done, tickets);
+    assert!(queue.shutdown().is_empty());
+  }
+}
@@ -0,0 +1,357 @@
+// one cart for anything we sell: drinks from the cafe, produce and pickles from the farm stand,
+// and boards cut to size. anything Sellable can go in it, and checking out prices every line
+// against one PriceBook and taxes it the way the till taxes a bill (see PriceTable::tax_on).
+//
+// a cart doesn't know prices until checkout, so anything wrong with a line (not for sale, or
+// counted when it has to be weighed) comes out then, with the line it's on.
+
+use std::fmt;
+
+use crate::custom::CustomDrink;
+use crate::felicity_farmstand::{Farmstand, Measure, Pricing, Vegetables};
+use crate::money::Money;
+use crate::pricing::{PriceTable, TaxCategory};
+use crate::{Drinks, MyRectangle, RectangleError};
+
+// every price list a cart can be checked out against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceBook {
+  drinks: PriceTable,
+  farmstand: Farmstand,
+  // boards are priced by area
+  board_per_square_meter: Money,
+}
+impl PriceBook {
+  // boards aren't for sale until they're given a price
+  pub fn new(drinks: PriceTable, farmstand: Farmstand) -> Self {
+    Self {
+      drinks,
+      farmstand,
+      board_per_square_meter: Money::ZERO,
+    }
+  }
+
+  pub fn standard() -> Self {
+    let mut book = Self::new(PriceTable::standard(), Farmstand::standard());
+    book.set_board_price(Money::from_cents(4_000));
+    book
+  }
+
+  pub fn set_board_price(&mut self, per_square_meter: Money) {
+    self.board_per_square_meter = per_square_meter;
+  }
+
+  pub fn drinks(&self) -> &PriceTable {
+    &self.drinks
+  }
+
+  pub fn farmstand(&self) -> &Farmstand {
+    &self.farmstand
+  }
+
+  pub fn board_price(&self) -> Money {
+    self.board_per_square_meter
+  }
+}
+
+// anything that can go in a cart
+pub trait Sellable: fmt::Debug {
+  fn name(&self) -> String;
+
+  fn tax_category(&self) -> TaxCategory;
+
+  // each, or by the kilogram. None when the book doesn't sell it
+  fn pricing(&self, book: &PriceBook) -> Option<Pricing>;
+
+  // the price of one, or of a kilogram of something sold by weight
+  fn unit_price(&self, book: &PriceBook) -> Option<Money> {
+    self.pricing(book).map(|pricing| pricing.rate())
+  }
+
+  // has to be weighed to be sold
+  fn needs_weighing(&self, book: &PriceBook) -> bool {
+    matches!(self.pricing(book), Some(Pricing::PerKilogram(_)))
+  }
+}
+
+impl Sellable for Drinks {
+  fn name(&self) -> String {
+    self.to_string()
+  }
+
+  fn tax_category(&self) -> TaxCategory {
+    TaxCategory::Prepared
+  }
+
+  fn pricing(&self, book: &PriceBook) -> Option<Pricing> {
+    book.drinks.price_of(self).map(Pricing::PerUnit)
+  }
+}
+
+// a drink made a particular way costs what its customizations add up to
+impl Sellable for CustomDrink {
+  fn name(&self) -> String {
+    self.to_string()
+  }
+
+  fn tax_category(&self) -> TaxCategory {
+    TaxCategory::Prepared
+  }
+
+  fn pricing(&self, book: &PriceBook) -> Option<Pricing> {
+    book.drinks.unit_price(self).map(Pricing::PerUnit)
+  }
+}
+
+impl Sellable for Vegetables {
+  fn name(&self) -> String {
+    Vegetables::name(self)
+  }
+
+  fn tax_category(&self) -> TaxCategory {
+    TaxCategory::Grocery
+  }
+
+  fn pricing(&self, book: &PriceBook) -> Option<Pricing> {
+    book.farmstand.price_of(self)
+  }
+}
+
+// a board cut to size from whatever wood is on hand, measured in centimeters
+#[derive(Debug)]
+pub struct Board {
+  pub wood: String,
+  pub size: MyRectangle,
+}
+impl Board {
+  // a board has to be something we can measure before it can be priced
+  pub fn new(wood: &str, width_cm: u32, height_cm: u32) -> Result<Self, RectangleError> {
+    Ok(Self {
+      wood: wood.to_lowercase(),
+      size: MyRectangle::try_new(width_cm, height_cm)?,
+    })
+  }
+}
+// priced by area. a square meter is 10,000 square centimeters, so each one is a basis point
+impl Sellable for Board {
+  fn name(&self) -> String {
+    format!("{} board, {} x {} cm", self.wood, self.size.width(), self.size.height())
+  }
+
+  fn tax_category(&self) -> TaxCategory {
+    TaxCategory::General
+  }
+
+  fn pricing(&self, book: &PriceBook) -> Option<Pricing> {
+    if book.board_per_square_meter.is_zero() {
+      return None;
+    }
+    // a board too big to put a price on can't be sold either
+    Some(Pricing::PerUnit(book.board_per_square_meter.checked_basis_points(self.size.area()?)?))
+  }
+}
+
+#[derive(Debug)]
+struct CartLine {
+  item: Box<dyn Sellable>,
+  measure: Measure,
+}
+
+#[derive(Debug, Default)]
+pub struct Cart {
+  lines: Vec<CartLine>,
+}
+impl Cart {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // `count` of something sold by the piece. adding none of something is a no-op
+  pub fn add(&mut self, item: impl Sellable + 'static, count: u32) {
+    if count > 0 {
+      self.lines.push(CartLine { item: Box::new(item), measure: Measure::Units(count) });
+    }
+  }
+
+  // something sold by weight, off the scale
+  pub fn weigh(&mut self, item: impl Sellable + 'static, grams: u32) {
+    if grams > 0 {
+      self.lines.push(CartLine { item: Box::new(item), measure: Measure::Grams(grams) });
+    }
+  }
+
+  // remove a line by its position, numbered from 0
+  pub fn remove(&mut self, index: usize) -> bool {
+    if index < self.lines.len() {
+      self.lines.remove(index);
+      true
+    } else {
+      false
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.lines.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.lines.is_empty()
+  }
+
+  pub fn checkout(&self, book: &PriceBook) -> Result<Checkout, CartError> {
+    if self.lines.is_empty() {
+      return Err(CartError::Empty);
+    }
+    let mut lines = Vec::new();
+    for (index, line) in self.lines.iter().enumerate() {
+      let name = line.item.name();
+      let problem = |reason| CartError::Line { line: index + 1, item: name.clone(), reason };
+      let pricing = line.item.pricing(book).ok_or_else(|| problem(LineProblem::NotForSale))?;
+      let total = pricing.charge(line.measure).ok_or_else(|| match (pricing, line.measure) {
+        (Pricing::PerKilogram(_), Measure::Units(_)) => problem(LineProblem::NeedsWeighing),
+        (Pricing::PerUnit(_), Measure::Grams(_)) => problem(LineProblem::SoldEach),
+        _ => problem(LineProblem::TooMuch),
+      })?;
+      lines.push(CheckoutLine {
+        name,
+        measure: line.measure,
+        rate: pricing.rate(),
+        total,
+        category: line.item.tax_category(),
+      });
+    }
+    let subtotal: Money = lines.iter().map(|line| line.total).sum();
+    let tax = book.drinks.tax_on(lines.iter().map(|line| (line.category, line.total)));
+    Ok(Checkout {
+      lines,
+      subtotal,
+      tax,
+      total: subtotal + tax,
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckoutLine {
+  pub name: String,
+  pub measure: Measure,
+  // per unit or per kilogram, going by the measure
+  pub rate: Money,
+  pub total: Money,
+  pub category: TaxCategory,
+}
+
+// a checked-out cart
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkout {
+  pub lines: Vec<CheckoutLine>,
+  pub subtotal: Money,
+  pub tax: Money,
+  pub total: Money,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineProblem {
+  NotForSale,
+  // counted, but it's sold by weight
+  NeedsWeighing,
+  // weighed, but it's sold by the piece
+  SoldEach,
+  // so much of it that the total is too big to count
+  TooMuch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CartError {
+  Empty,
+  // lines are numbered from 1
+  Line { line: usize, item: String, reason: LineProblem },
+}
+impl fmt::Display for CartError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CartError::Empty => write!(f, "the cart is empty"),
+      CartError::Line { line, item, reason } => {
+        write!(f, "line {} ({}): ", line, item)?;
+        match reason {
+          LineProblem::NotForSale => write!(f, "not for sale"),
+          LineProblem::NeedsWeighing => write!(f, "sold by weight, so it needs weighing"),
+          LineProblem::SoldEach => write!(f, "sold by the piece, not by weight"),
+          LineProblem::TooMuch => write!(f, "too much to ring up"),
+        }
+      }
+    }
+  }
+}
+impl std::error::Error for CartError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::order::Order;
+  use crate::CoffeeFlavor;
+
+  #[test]
+  fn a_latte_and_a_jar_of_pickles_check_out_together() {
+    let mut cart = Cart::new();
+    cart.add(Drinks::Coffee(CoffeeFlavor::Latte), 1);
+    cart.add(Vegetables::pickle("dill"), 1);
+    cart.weigh(Vegetables::Beets, 412);
+    let checkout = cart.checkout(&PriceBook::standard()).unwrap();
+
+    let names: Vec<&str> = checkout.lines.iter().map(|line| line.name.as_str()).collect();
+    assert_eq!(names, vec!["Latte (coffee)", "Pickles (dill)", "Beets"]);
+    assert_eq!(checkout.subtotal, Money::from_cents(450 + 800 + 247));
+    // 8.25% of the lot
+    assert_eq!(checkout.tax, Money::from_cents(124));
+    assert_eq!(checkout.total, Money::from_cents(1_621));
+
+    // and the till's bill for the same sale comes to the same
+    let stand = Farmstand::standard();
+    let mut order = Order::new();
+    order.add(Drinks::Coffee(CoffeeFlavor::Latte), 1).unwrap();
+    order.add_produce(stand.sell(&Vegetables::pickle("dill"), 1).unwrap()).unwrap();
+    order.add_produce(stand.weigh(&Vegetables::Beets, 412).unwrap()).unwrap();
+    let bill = PriceTable::standard().price(&order).unwrap();
+    assert_eq!((bill.tax, bill.total), (checkout.tax, checkout.total));
+  }
+
+  #[test]
+  fn boards_are_priced_by_area() {
+    let board = Board::new("Pine", 30, 120).unwrap();
+    let book = PriceBook::standard();
+    assert_eq!(board.name(), "pine board, 30 x 120 cm");
+    // 0.36 square meters at $40.00
+    assert_eq!(board.unit_price(&book), Some(Money::from_cents(1_440)));
+    assert!(!board.needs_weighing(&book));
+    assert_eq!(board.unit_price(&PriceBook::new(PriceTable::standard(), Farmstand::new())), None);
+  }
+
+  #[test]
+  fn lines_that_cant_be_sold_say_which_they_are() {
+    let mut cart = Cart::new();
+    cart.add(Drinks::Water, 1);
+    cart.add(Vegetables::Asparagus, 2);
+    let err = cart.checkout(&PriceBook::standard()).unwrap_err();
+    assert_eq!(err.to_string(), "line 2 (Asparagus): sold by weight, so it needs weighing");
+
+    assert!(cart.remove(1));
+    cart.weigh(Drinks::Water, 500);
+    assert!(matches!(
+      cart.checkout(&PriceBook::standard()),
+      Err(CartError::Line { line: 2, reason: LineProblem::SoldEach, .. })
+    ));
+    assert_eq!(Cart::new().checkout(&PriceBook::standard()), Err(CartError::Empty));
+  }
+
+  #[test]
+  fn lines_too_big_to_count_say_so() {
+    let mut book = PriceBook::standard();
+    book.set_board_price(Money::from_cents(i64::MAX / 2));
+    let mut cart = Cart::new();
+    cart.add(Board::new("oak", 100, 100).unwrap(), 3);
+    let err = cart.checkout(&book).unwrap_err();
+    assert!(matches!(err, CartError::Line { line: 1, reason: LineProblem::TooMuch, .. }));
+    assert_eq!(err.to_string(), "line 1 (oak board, 100 x 100 cm): too much to ring up");
+  }
+}
@@ -1 +1,227 @@
 // module definition (declaration?) for ChrisCataclysm
+//
+// when it all goes wrong: one error for the whole crate. every module keeps its own error type,
+// which says exactly what went wrong, and each of them converts into this one, which sorts it
+// into the kind of failure it is. that's enough for a binary to pick an exit code, and for
+// anything that calls into several modules to return a single error type with `?`.
+//
+// what was being done when it failed is added on the way out with `context`, so "No such file or
+// directory" turns into "sales-ledger.csv: couldn't use the ledger file: No such file or
+// directory". the kinds themselves are transparent: they print the error they wrap and hand on its
+// source, so walking the sources never shows the same message twice.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::process;
+
+use crate::cart::CartError;
+use crate::config::ConfigError;
+use crate::custom::CustomizeError;
+use crate::date::{ParseDateError, ParseTimestampError};
+use crate::felicity_farmstand::FarmstandError;
+use crate::inventory::StockError;
+use crate::json::JsonError;
+use crate::ledger::LedgerError;
+use crate::lifecycle::TransitionError;
+use crate::loyalty::LoyaltyError;
+use crate::money::ParseMoneyError;
+use crate::order::QuantityError;
+use crate::owen_oblivion::ExpiryError;
+use crate::parse::ParseDrinkError;
+use crate::policy::PolicyViolation;
+use crate::pricing::PricingError;
+use crate::split::SplitError;
+use crate::till::CommandError;
+use crate::RectangleError;
+
+type Source = Box<dyn StdError + Send + Sync>;
+
+#[derive(Debug)]
+pub enum Error {
+  // an order that can't go through as it is: a drink with no price, a customization that doesn't
+  // go, a board with no size, a bill that doesn't split, an order in the wrong state
+  InvalidOrder(Source),
+  // not enough of something to make or sell it
+  OutOfStock(Source),
+  // text that doesn't say what it should: a drink name, a date, a config or ledger file
+  Parse(Source),
+  Io(Source),
+  // a sale the rules don't allow, e.g. beer without an ID check
+  Policy(PolicyViolation),
+  // what was being done when `source` happened
+  Context { context: String, source: Box<Error> },
+}
+impl Error {
+  pub fn invalid_order(e: impl Into<Source>) -> Self {
+    Error::InvalidOrder(e.into())
+  }
+
+  pub fn out_of_stock(e: impl Into<Source>) -> Self {
+    Error::OutOfStock(e.into())
+  }
+
+  pub fn parse(e: impl Into<Source>) -> Self {
+    Error::Parse(e.into())
+  }
+
+  // wrap this error in what was being done, e.g. the file being read
+  pub fn context(self, context: impl Into<String>) -> Self {
+    Error::Context {
+      context: context.into(),
+      source: Box::new(self),
+    }
+  }
+
+  // the error under all the context
+  pub fn root(&self) -> &Error {
+    match self {
+      Error::Context { source, .. } => source.root(),
+      _ => self,
+    }
+  }
+
+  // what a binary exits with. 1 is left for usage mistakes, which the binaries report themselves
+  pub fn exit_code(&self) -> i32 {
+    match self.root() {
+      Error::Parse(_) => 2,
+      Error::InvalidOrder(_) => 3,
+      Error::OutOfStock(_) => 4,
+      Error::Policy(_) => 5,
+      Error::Io(_) => 6,
+      Error::Context { .. } => unreachable!("root() looks through context"),
+    }
+  }
+
+  // the end of a binary that can't go on: the error on stderr, and its exit code
+  pub fn exit(self) -> ! {
+    eprintln!("{}", self);
+    process::exit(self.exit_code());
+  }
+}
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::InvalidOrder(e) | Error::OutOfStock(e) | Error::Parse(e) | Error::Io(e) => write!(f, "{}", e),
+      Error::Policy(e) => write!(f, "{}", e),
+      Error::Context { context, source } => write!(f, "{}: {}", context, source),
+    }
+  }
+}
+impl StdError for Error {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Error::InvalidOrder(e) | Error::OutOfStock(e) | Error::Parse(e) | Error::Io(e) => e.source(),
+      Error::Policy(e) => e.source(),
+      Error::Context { source, .. } => Some(source.as_ref()),
+    }
+  }
+}
+
+// `.context(...)` straight on a result, for anything that converts into an `Error`
+pub trait Context<T> {
+  fn context(self, context: impl Into<String>) -> Result<T, Error>;
+}
+impl<T, E: Into<Error>> Context<T> for Result<T, E> {
+  fn context(self, context: impl Into<String>) -> Result<T, Error> {
+    self.map_err(|e| e.into().context(context))
+  }
+}
+
+// one From for each error that's always the same kind of failure
+macro_rules! kind_of {
+  ($variant:ident: $($error:ty),+) => {
+    $(
+      impl From<$error> for Error {
+        fn from(e: $error) -> Self {
+          Error::$variant(Box::new(e))
+        }
+      }
+    )+
+  };
+}
+
+kind_of!(
+  InvalidOrder: CustomizeError,
+  QuantityError,
+  PricingError,
+  SplitError,
+  TransitionError,
+  FarmstandError,
+  CartError,
+  RectangleError
+);
+kind_of!(OutOfStock: StockError, ExpiryError);
+kind_of!(Parse: ParseDrinkError, ParseMoneyError, ParseDateError, ParseTimestampError, JsonError, ConfigError);
+kind_of!(Io: io::Error);
+
+impl From<PolicyViolation> for Error {
+  fn from(e: PolicyViolation) -> Self {
+    Error::Policy(e)
+  }
+}
+
+// a till command is mistyped unless it's a customization that doesn't go
+impl From<CommandError> for Error {
+  fn from(e: CommandError) -> Self {
+    match e {
+      CommandError::Customize(e) => e.into(),
+      e => Error::Parse(Box::new(e)),
+    }
+  }
+}
+
+impl From<LedgerError> for Error {
+  fn from(e: LedgerError) -> Self {
+    match e {
+      LedgerError::Io(_) => Error::Io(Box::new(e)),
+      e => Error::Parse(Box::new(e)),
+    }
+  }
+}
+
+// the file is either unreadable or unreadable-as-customers; everything else is a request the
+// customer book turned down
+impl From<LoyaltyError> for Error {
+  fn from(e: LoyaltyError) -> Self {
+    match e {
+      LoyaltyError::Io(_) => Error::Io(Box::new(e)),
+      LoyaltyError::NotACustomerFile | LoyaltyError::Malformed { .. } => Error::Parse(Box::new(e)),
+      e => Error::InvalidOrder(Box::new(e)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ledger::Ledger;
+  use crate::{CoffeeFlavor, Drinks};
+
+  #[test]
+  fn module_errors_sort_into_kinds() {
+    let parse: Error = "kombucha".parse::<Drinks>().unwrap_err().into();
+    assert!(matches!(parse, Error::Parse(_)));
+    assert_eq!(parse.exit_code(), 2);
+    let pricing: Error = PricingError::Unpriced(Drinks::Coffee(CoffeeFlavor::Latte)).into();
+    assert_eq!(pricing.exit_code(), 3);
+    let policy: Error = PolicyViolation::IdRequired.into();
+    assert_eq!(policy.exit_code(), 5);
+    let command: Error = CommandError::ZeroQuantity.into();
+    assert_eq!(command.exit_code(), 2);
+  }
+
+  #[test]
+  fn context_keeps_the_kind_and_the_source() {
+    // a directory opens, but can't be read as a file
+    let err = Ledger::open(std::env::temp_dir()).map(drop).context("sales-ledger.csv").unwrap_err();
+    assert_eq!(err.exit_code(), 6);
+    assert!(err.to_string().starts_with("sales-ledger.csv: couldn't use the ledger file: "));
+
+    // context -> the ledger error -> the io error underneath, with nothing in between for the kind
+    let ledger = err.source().unwrap();
+    assert!(ledger.to_string().starts_with("couldn't use the ledger file: "));
+    assert!(matches!(ledger.downcast_ref::<Error>(), Some(Error::Io(_))));
+    assert!(ledger.source().unwrap().downcast_ref::<io::Error>().is_some());
+  }
+}
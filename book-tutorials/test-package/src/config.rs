@@ -0,0 +1,116 @@
+// a small ini-style format for the settings that change without a code edit:
+//
+//   # comments start with a hash
+//   [section name]
+//   key = value
+//
+// keys before the first section header belong to a section with an empty name. this only splits
+// the text up - what the keys mean is up to whoever reads the section.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+  pub name: String,
+  // key, value and line number, in file order, so a key given twice can be told apart from a key
+  // given once
+  pub entries: Vec<(String, String, usize)>,
+  // line number of the header, for error messages
+  pub line: usize,
+}
+impl Section {
+  // the last value given for `key`
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self.entries.iter().rev().find(|(k, _, _)| k == key).map(|(_, v, _)| v.as_str())
+  }
+
+  // every value given for `key`, in order
+  pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    self.entries.iter().filter(move |(k, _, _)| k == key).map(|(_, v, _)| v.as_str())
+  }
+
+  // the line the value `get` gives for `key` is on, or the header's when it isn't given, so an
+  // error about a key points at the key
+  pub fn line_of(&self, key: &str) -> usize {
+    self.entries.iter().rev().find(|(k, _, _)| k == key).map_or(self.line, |(_, _, line)| *line)
+  }
+}
+
+pub fn parse(text: &str) -> Result<Vec<Section>, ConfigError> {
+  let mut sections: Vec<Section> = Vec::new();
+  for (index, raw) in text.lines().enumerate() {
+    let line_number = index + 1;
+    let line = raw.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    if let Some(header) = line.strip_prefix('[') {
+      let name = header.strip_suffix(']').ok_or(ConfigError {
+        line: line_number,
+        message: String::from("section header is missing its closing ]"),
+      })?;
+      sections.push(Section {
+        name: name.trim().to_string(),
+        entries: Vec::new(),
+        line: line_number,
+      });
+      continue;
+    }
+    let (key, value) = line.split_once('=').ok_or_else(|| ConfigError {
+      line: line_number,
+      message: format!("expected key = value, found \"{}\"", line),
+    })?;
+    if sections.is_empty() {
+      sections.push(Section {
+        name: String::new(),
+        entries: Vec::new(),
+        line: line_number,
+      });
+    }
+    let section = sections.last_mut().expect("pushed above");
+    section.entries.push((key.trim().to_string(), value.trim().to_string(), line_number));
+  }
+  Ok(sections)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+  pub line: usize,
+  pub message: String,
+}
+impl ConfigError {
+  pub fn new(line: usize, message: impl Into<String>) -> Self {
+    Self {
+      line,
+      message: message.into(),
+    }
+  }
+}
+impl fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn remembers_where_each_key_is() {
+    let sections = parse("# stock\n[monday]\ncups = 10\n\nmilk = 4\ncups = 12\n").unwrap();
+    let monday = &sections[0];
+    assert_eq!((monday.name.as_str(), monday.line), ("monday", 2));
+    assert_eq!(monday.get("cups"), Some("12"));
+    assert_eq!(monday.get_all("cups").collect::<Vec<_>>(), vec!["10", "12"]);
+    assert_eq!((monday.line_of("milk"), monday.line_of("cups"), monday.line_of("beer")), (5, 6, 2));
+  }
+
+  #[test]
+  fn says_which_line_is_wrong() {
+    let err = parse("[a]\nkey = 1\nno equals\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 3: expected key = value, found \"no equals\"");
+    assert_eq!(parse("[a\n").unwrap_err().line, 1);
+  }
+}
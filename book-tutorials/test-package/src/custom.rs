@@ -0,0 +1,396 @@
+// a drink the way the customer actually ordered it: size, milk, extra shots, syrups and how hot.
+// CustomDrink can only be made through CustomDrinkBuilder::build, which checks that the
+// combination makes sense for the drink (no shots in a water, no hot beer...) so anything
+// holding a CustomDrink can trust it.
+
+use std::fmt;
+
+use crate::{CoffeeFlavor, Drinks};
+
+// the most espresso shots we'll add on top of what the drink comes with
+pub const MAX_EXTRA_SHOTS: u8 = 4;
+// the most syrups that can go in one drink
+pub const MAX_SYRUPS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Size {
+  Small,
+  Medium,
+  Large,
+}
+impl Size {
+  pub const ALL: [Size; 3] = [Size::Small, Size::Medium, Size::Large];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    find_by_name(&Self::ALL, name, Self::name)
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Size::Small => "small",
+      Size::Medium => "medium",
+      Size::Large => "large",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Milk {
+  Whole,
+  Skim,
+  Oat,
+  Soy,
+  Almond,
+}
+impl Milk {
+  pub const ALL: [Milk; 5] = [Milk::Whole, Milk::Skim, Milk::Oat, Milk::Soy, Milk::Almond];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    find_by_name(&Self::ALL, name, Self::name)
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Milk::Whole => "whole",
+      Milk::Skim => "skim",
+      Milk::Oat => "oat",
+      Milk::Soy => "soy",
+      Milk::Almond => "almond",
+    }
+  }
+
+  // dairy milk is included in the price, the alternatives cost extra
+  pub fn is_dairy(&self) -> bool {
+    matches!(self, Milk::Whole | Milk::Skim)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Syrup {
+  Vanilla,
+  Caramel,
+  Hazelnut,
+}
+impl Syrup {
+  pub const ALL: [Syrup; 3] = [Syrup::Vanilla, Syrup::Caramel, Syrup::Hazelnut];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    find_by_name(&Self::ALL, name, Self::name)
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Syrup::Vanilla => "vanilla",
+      Syrup::Caramel => "caramel",
+      Syrup::Hazelnut => "hazelnut",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Temperature {
+  Cold,
+  Iced,
+  Hot,
+  ExtraHot,
+}
+impl Temperature {
+  pub const ALL: [Temperature; 4] = [
+    Temperature::Cold,
+    Temperature::Iced,
+    Temperature::Hot,
+    Temperature::ExtraHot,
+  ];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    find_by_name(&Self::ALL, name, Self::name)
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Temperature::Cold => "cold",
+      Temperature::Iced => "iced",
+      Temperature::Hot => "hot",
+      Temperature::ExtraHot => "extra hot",
+    }
+  }
+
+  fn is_hot(&self) -> bool {
+    matches!(self, Temperature::Hot | Temperature::ExtraHot)
+  }
+}
+
+// the opposite of `name_of` over a list of choices: whichever one is called `name`, ignoring case
+// and surrounding spaces
+pub(crate) fn find_by_name<T: Copy>(all: &[T], name: &str, name_of: fn(&T) -> &'static str) -> Option<T> {
+  all.iter().copied().find(|x| name_of(x).eq_ignore_ascii_case(name.trim()))
+}
+
+// what a drink comes with when nobody asks for anything different
+fn default_milk(drink: &Drinks) -> Option<Milk> {
+  match drink {
+    Drinks::Coffee(CoffeeFlavor::Americano) => None,
+    Drinks::Coffee(_) => Some(Milk::Whole),
+    _ => None,
+  }
+}
+
+fn default_temperature(drink: &Drinks) -> Temperature {
+  match drink {
+    Drinks::Coffee(_) => Temperature::Hot,
+    _ => Temperature::Cold,
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CustomDrink {
+  drink: Drinks,
+  size: Size,
+  milk: Option<Milk>,
+  extra_shots: u8,
+  syrups: Vec<Syrup>,
+  temperature: Temperature,
+}
+impl CustomDrink {
+  pub fn builder(drink: Drinks) -> CustomDrinkBuilder {
+    CustomDrinkBuilder::new(drink)
+  }
+
+  pub fn drink(&self) -> Drinks {
+    self.drink
+  }
+
+  pub fn size(&self) -> Size {
+    self.size
+  }
+
+  pub fn milk(&self) -> Option<Milk> {
+    self.milk
+  }
+
+  pub fn extra_shots(&self) -> u8 {
+    self.extra_shots
+  }
+
+  pub fn syrups(&self) -> &[Syrup] {
+    &self.syrups
+  }
+
+  pub fn temperature(&self) -> Temperature {
+    self.temperature
+  }
+
+  // the size and the drink, e.g. "large Latte (coffee)"
+  pub fn name(&self) -> String {
+    format!("{} {}", self.size.name(), self.drink)
+  }
+
+  // the ways this drink differs from how it normally comes, e.g. ["oat milk", "2 extra shots"].
+  // the size isn't a modifier - it's part of the name
+  pub fn modifiers(&self) -> Vec<String> {
+    let mut modifiers = Vec::new();
+    if self.milk != default_milk(&self.drink) {
+      match self.milk {
+        Some(milk) => modifiers.push(format!("{} milk", milk.name())),
+        None => modifiers.push(String::from("no milk")),
+      }
+    }
+    match self.extra_shots {
+      0 => {}
+      1 => modifiers.push(String::from("1 extra shot")),
+      n => modifiers.push(format!("{} extra shots", n)),
+    }
+    for syrup in &self.syrups {
+      modifiers.push(format!("{} syrup", syrup.name()));
+    }
+    if self.temperature != default_temperature(&self.drink) {
+      modifiers.push(String::from(self.temperature.name()));
+    }
+    modifiers
+  }
+}
+// every plain drink is also a valid customized one, made the usual way in a medium
+impl From<Drinks> for CustomDrink {
+  fn from(drink: Drinks) -> Self {
+    Self {
+      drink,
+      size: Size::Medium,
+      milk: default_milk(&drink),
+      extra_shots: 0,
+      syrups: Vec::new(),
+      temperature: default_temperature(&drink),
+    }
+  }
+}
+// "large Latte (coffee), oat milk, 1 extra shot"
+impl fmt::Display for CustomDrink {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name())?;
+    for modifier in self.modifiers() {
+      write!(f, ", {}", modifier)?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct CustomDrinkBuilder {
+  drink: CustomDrink,
+}
+impl CustomDrinkBuilder {
+  pub fn new(drink: Drinks) -> Self {
+    Self {
+      drink: CustomDrink::from(drink),
+    }
+  }
+
+  pub fn size(mut self, size: Size) -> Self {
+    self.drink.size = size;
+    self
+  }
+
+  // asking for milk is how an americano gets any - it never comes with it by default
+  pub fn milk(mut self, milk: Milk) -> Self {
+    self.drink.milk = Some(milk);
+    self
+  }
+
+  pub fn no_milk(mut self) -> Self {
+    self.drink.milk = None;
+    self
+  }
+
+  pub fn extra_shots(mut self, shots: u8) -> Self {
+    self.drink.extra_shots = shots;
+    self
+  }
+
+  pub fn syrup(mut self, syrup: Syrup) -> Self {
+    self.drink.syrups.push(syrup);
+    self
+  }
+
+  pub fn temperature(mut self, temperature: Temperature) -> Self {
+    self.drink.temperature = temperature;
+    self
+  }
+
+  pub fn build(self) -> Result<CustomDrink, CustomizeError> {
+    let drink = self.drink;
+    let base = drink.drink;
+    let is_coffee = matches!(base, Drinks::Coffee(_));
+
+    if drink.milk.is_some() && !is_coffee {
+      return Err(CustomizeError::MilkNotAllowed(base));
+    }
+    if let Drinks::Coffee(flavor) = base {
+      if drink.milk.is_none() && default_milk(&base).is_some() {
+        return Err(CustomizeError::MilkRequired(flavor));
+      }
+    }
+    if drink.extra_shots > 0 && !is_coffee {
+      return Err(CustomizeError::ShotsNotAllowed(base));
+    }
+    if drink.extra_shots > MAX_EXTRA_SHOTS {
+      return Err(CustomizeError::TooManyShots(drink.extra_shots));
+    }
+    if !drink.syrups.is_empty() && !matches!(base, Drinks::Coffee(_) | Drinks::Soda) {
+      return Err(CustomizeError::SyrupNotAllowed(base));
+    }
+    if drink.syrups.len() > MAX_SYRUPS {
+      return Err(CustomizeError::TooManySyrups(drink.syrups.len()));
+    }
+    if drink.temperature.is_hot() && matches!(base, Drinks::Soda | Drinks::Beer) {
+      return Err(CustomizeError::TemperatureNotAllowed(base, drink.temperature));
+    }
+    Ok(drink)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CustomizeError {
+  // milk was asked for on something that isn't a coffee
+  MilkNotAllowed(Drinks),
+  // the coffee is made with milk, so it can't be left out
+  MilkRequired(CoffeeFlavor),
+  // extra shots only go in coffee
+  ShotsNotAllowed(Drinks),
+  // more than MAX_EXTRA_SHOTS
+  TooManyShots(u8),
+  // syrups only go in coffee and soda
+  SyrupNotAllowed(Drinks),
+  // more than MAX_SYRUPS
+  TooManySyrups(usize),
+  TemperatureNotAllowed(Drinks, Temperature),
+}
+impl fmt::Display for CustomizeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CustomizeError::MilkNotAllowed(drink) => write!(f, "{} can't have milk", drink),
+      CustomizeError::MilkRequired(flavor) => write!(f, "a {} can't be made without milk", flavor),
+      CustomizeError::ShotsNotAllowed(drink) => write!(f, "{} can't have extra shots", drink),
+      CustomizeError::TooManyShots(shots) => {
+        write!(f, "{} extra shots is too many (at most {})", shots, MAX_EXTRA_SHOTS)
+      }
+      CustomizeError::SyrupNotAllowed(drink) => write!(f, "{} can't have syrup", drink),
+      CustomizeError::TooManySyrups(count) => {
+        write!(f, "{} syrups is too many (at most {})", count, MAX_SYRUPS)
+      }
+      CustomizeError::TemperatureNotAllowed(drink, temperature) => {
+        write!(f, "{} can't be served {}", drink, temperature.name())
+      }
+    }
+  }
+}
+impl std::error::Error for CustomizeError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const LATTE: Drinks = Drinks::Coffee(CoffeeFlavor::Latte);
+  const AMERICANO: Drinks = Drinks::Coffee(CoffeeFlavor::Americano);
+
+  #[test]
+  fn plain_drinks_come_the_usual_way() {
+    let latte = CustomDrink::from(LATTE);
+    assert_eq!(latte.milk(), Some(Milk::Whole));
+    assert_eq!(latte.temperature(), Temperature::Hot);
+    assert!(latte.modifiers().is_empty());
+    assert_eq!(CustomDrink::from(AMERICANO).milk(), None);
+  }
+
+  #[test]
+  fn builds_a_customized_coffee() {
+    let drink = CustomDrink::builder(LATTE)
+      .size(Size::Large)
+      .milk(Milk::Oat)
+      .extra_shots(1)
+      .syrup(Syrup::Vanilla)
+      .temperature(Temperature::Iced)
+      .build()
+      .unwrap();
+    assert_eq!(drink.to_string(), "large Latte (coffee), oat milk, 1 extra shot, vanilla syrup, iced");
+  }
+
+  #[test]
+  fn americano_only_gets_milk_when_asked() {
+    let drink = CustomDrink::builder(AMERICANO).milk(Milk::Skim).build().unwrap();
+    assert_eq!(drink.modifiers(), vec!["skim milk"]);
+  }
+
+  #[test]
+  fn rejects_combinations_that_make_no_sense() {
+    let water = CustomDrink::builder(Drinks::Water).extra_shots(1).build();
+    assert_eq!(water, Err(CustomizeError::ShotsNotAllowed(Drinks::Water)));
+
+    let latte = CustomDrink::builder(LATTE).no_milk().build();
+    assert_eq!(latte, Err(CustomizeError::MilkRequired(CoffeeFlavor::Latte)));
+
+    let beer = CustomDrink::builder(Drinks::Beer).temperature(Temperature::Hot).build();
+    assert_eq!(beer, Err(CustomizeError::TemperatureNotAllowed(Drinks::Beer, Temperature::Hot)));
+
+    let shots = CustomDrink::builder(AMERICANO).extra_shots(MAX_EXTRA_SHOTS + 1).build();
+    assert_eq!(shots, Err(CustomizeError::TooManyShots(MAX_EXTRA_SHOTS + 1)));
+  }
+}
@@ -0,0 +1,245 @@
+// dates and timestamps without pulling in a crate for it.
+// everything is kept in UTC and converted with the usual days-from-civil arithmetic
+// (http://howardhinnant.github.io/date_algorithms.html), which is exact for any date we'll see.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Date {
+  year: i32,
+  month: u32,
+  day: u32,
+}
+impl Date {
+  // None if the day doesn't exist (february 30th, month 13...)
+  pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+    if (1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month) {
+      Some(Self { year, month, day })
+    } else {
+      None
+    }
+  }
+
+  pub fn year(&self) -> i32 {
+    self.year
+  }
+
+  pub fn month(&self) -> u32 {
+    self.month
+  }
+
+  pub fn day(&self) -> u32 {
+    self.day
+  }
+
+  // days since 1970-01-01 (negative before it)
+  pub fn days_since_epoch(&self) -> i64 {
+    let year = i64::from(self.year) - i64::from(self.month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(self.month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+  }
+
+  pub fn from_days_since_epoch(days: i64) -> Self {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    Self {
+      year: year as i32,
+      month: month as u32,
+      day: day as u32,
+    }
+  }
+
+  pub fn add_days(&self, days: i64) -> Self {
+    Self::from_days_since_epoch(self.days_since_epoch() + days)
+  }
+
+  // how many days from `earlier` to this date (negative if `earlier` is actually later)
+  pub fn days_since(&self, earlier: &Date) -> i64 {
+    self.days_since_epoch() - earlier.days_since_epoch()
+  }
+
+  // 1 is monday and 7 is sunday, like ISO 8601
+  pub fn weekday(&self) -> u32 {
+    // 1970-01-01 was a thursday
+    ((self.days_since_epoch() + 3).rem_euclid(7) + 1) as u32
+  }
+
+  pub fn today() -> Self {
+    Timestamp::now().date()
+  }
+}
+impl fmt::Display for Date {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDateError {
+  input: String,
+}
+impl fmt::Display for ParseDateError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "\"{}\" is not a date (expected YYYY-MM-DD)", self.input)
+  }
+}
+impl std::error::Error for ParseDateError {}
+
+// YYYY-MM-DD
+impl FromStr for Date {
+  type Err = ParseDateError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let error = || ParseDateError { input: s.to_string() };
+    let mut parts = s.trim().splitn(3, '-');
+    let mut next = || parts.next().ok_or_else(error);
+    let year = next()?.parse().map_err(|_| error())?;
+    let month = next()?.parse().map_err(|_| error())?;
+    let day = next()?.parse().map_err(|_| error())?;
+    Date::new(year, month, day).ok_or_else(error)
+  }
+}
+
+fn is_leap_year(year: i32) -> bool {
+  (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+  match month {
+    2 if is_leap_year(year) => 29,
+    2 => 28,
+    4 | 6 | 9 | 11 => 30,
+    _ => 31,
+  }
+}
+
+// a moment in time, to the second, in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Timestamp {
+  unix_seconds: i64,
+}
+impl Timestamp {
+  pub fn from_unix_seconds(unix_seconds: i64) -> Self {
+    Self { unix_seconds }
+  }
+
+  // hours, minutes and seconds are not checked beyond being added up
+  pub fn from_date_time(date: Date, hour: u32, minute: u32, second: u32) -> Self {
+    let seconds = i64::from(hour * 3_600 + minute * 60 + second);
+    Self::from_unix_seconds(date.days_since_epoch() * SECONDS_PER_DAY + seconds)
+  }
+
+  pub fn now() -> Self {
+    let since_epoch = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .expect("the clock is set after 1970");
+    Self::from_unix_seconds(since_epoch.as_secs() as i64)
+  }
+
+  pub fn unix_seconds(&self) -> i64 {
+    self.unix_seconds
+  }
+
+  pub fn date(&self) -> Date {
+    Date::from_days_since_epoch(self.unix_seconds.div_euclid(SECONDS_PER_DAY))
+  }
+
+  // (hour, minute, second)
+  pub fn time_of_day(&self) -> (u32, u32, u32) {
+    let seconds = self.unix_seconds.rem_euclid(SECONDS_PER_DAY) as u32;
+    (seconds / 3_600, seconds / 60 % 60, seconds % 60)
+  }
+
+  pub fn add_seconds(&self, seconds: i64) -> Self {
+    Self::from_unix_seconds(self.unix_seconds + seconds)
+  }
+}
+// ISO 8601, e.g. 2024-05-01T13:45:00Z
+impl fmt::Display for Timestamp {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let (hour, minute, second) = self.time_of_day();
+    write!(f, "{}T{:02}:{:02}:{:02}Z", self.date(), hour, minute, second)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTimestampError {
+  input: String,
+}
+impl fmt::Display for ParseTimestampError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "\"{}\" is not a timestamp (expected YYYY-MM-DDTHH:MM:SSZ)", self.input)
+  }
+}
+impl std::error::Error for ParseTimestampError {}
+
+// the same format Display writes
+impl FromStr for Timestamp {
+  type Err = ParseTimestampError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let error = || ParseTimestampError { input: s.to_string() };
+    let (date, time) = s.trim().split_once('T').ok_or_else(error)?;
+    let date: Date = date.parse().map_err(|_| error())?;
+    let time = time.strip_suffix('Z').ok_or_else(error)?;
+    let parts: Vec<u32> = time
+      .split(':')
+      .map(|part| part.parse().map_err(|_| error()))
+      .collect::<Result<_, _>>()?;
+    match parts.as_slice() {
+      [hour, minute, second] if *hour < 24 && *minute < 60 && *second < 60 => {
+        Ok(Timestamp::from_date_time(date, *hour, *minute, *second))
+      }
+      _ => Err(error()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn converts_dates_to_and_from_days() {
+    assert_eq!(Date::new(1970, 1, 1).unwrap().days_since_epoch(), 0);
+    assert_eq!(Date::new(2000, 3, 1).unwrap().days_since_epoch(), 11_017);
+    for days in [-1, 0, 59, 365, 11_016, 19_782, 50_000] {
+      assert_eq!(Date::from_days_since_epoch(days).days_since_epoch(), days);
+    }
+  }
+
+  #[test]
+  fn rejects_days_that_dont_exist() {
+    assert!(Date::new(2023, 2, 29).is_none());
+    assert!(Date::new(2024, 2, 29).is_some());
+    assert!("2024-13-01".parse::<Date>().is_err());
+  }
+
+  #[test]
+  fn knows_the_day_of_the_week() {
+    // 2024-05-01 was a wednesday
+    assert_eq!(Date::new(2024, 5, 1).unwrap().weekday(), 3);
+  }
+
+  #[test]
+  fn timestamps_round_trip_through_text() {
+    let stamp = Timestamp::from_date_time(Date::new(2024, 5, 1).unwrap(), 13, 45, 9);
+    assert_eq!(stamp.to_string(), "2024-05-01T13:45:09Z");
+    assert_eq!("2024-05-01T13:45:09Z".parse(), Ok(stamp));
+  }
+}
@@ -0,0 +1,415 @@
+// the farm stand next to the cafe. it sells what comes out of the ground, some of it by the piece
+// and some by weight off the scale, plus jars of pickles in however many varieties we're making
+// that season.
+//
+// produce is priced the moment it's counted or weighed - like the label the scale prints - so a
+// ProduceLine already knows what it costs and goes onto the same order as the drinks. what's in
+// season when lives in the season submodule (src/felicity_farmstand/season.rs).
+
+use std::fmt;
+
+use crate::date::Date;
+use crate::money::Money;
+
+pub mod season;
+pub use season::{Availability, Season, Window};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Vegetables {
+  Asparagus,
+  Squash,
+  Beets,
+  // the variety, e.g. "dill" or "bread and butter", always lowercase
+  Pickle(String),
+}
+impl Vegetables {
+  pub fn pickle(variety: &str) -> Self {
+    Vegetables::Pickle(variety.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase())
+  }
+
+  // "Asparagus", or "Pickles (dill)"
+  pub fn name(&self) -> String {
+    match self {
+      Vegetables::Asparagus => String::from("Asparagus"),
+      Vegetables::Squash => String::from("Squash"),
+      Vegetables::Beets => String::from("Beets"),
+      Vegetables::Pickle(variety) => format!("Pickles ({})", variety),
+    }
+  }
+
+  // forgiving about case, punctuation and plurals. pickles are "dill pickles", "pickles dill" or
+  // "pickles (dill)", and a pickle has to say which variety it is
+  pub fn from_name(name: &str) -> Option<Self> {
+    let cleaned: String = name
+      .chars()
+      .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' })
+      .collect();
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    match words.as_slice() {
+      ["asparagus"] => Some(Vegetables::Asparagus),
+      ["squash" | "squashes"] => Some(Vegetables::Squash),
+      ["beet" | "beets"] => Some(Vegetables::Beets),
+      [variety @ .., "pickle" | "pickles"] | ["pickle" | "pickles", variety @ ..] if !variety.is_empty() => {
+        Some(Vegetables::pickle(&variety.join(" ")))
+      }
+      _ => None,
+    }
+  }
+}
+impl fmt::Display for Vegetables {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&self.name())
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pricing {
+  // each, counted
+  PerUnit(Money),
+  // weighed, priced by the kilogram
+  PerKilogram(Money),
+}
+impl Pricing {
+  // per unit or per kilogram
+  pub fn rate(&self) -> Money {
+    match self {
+      Pricing::PerUnit(price) | Pricing::PerKilogram(price) => *price,
+    }
+  }
+
+  // what this much costs at this price, rounded to the nearest cent. None when it's measured the
+  // wrong way (counted when it's sold by weight, or weighed when it's sold by the piece) or when
+  // the total is too big to count
+  pub fn charge(&self, measure: Measure) -> Option<Money> {
+    match (self, measure) {
+      (Pricing::PerUnit(price), Measure::Units(count)) => price.checked_times(count),
+      // a gram is 10 basis points of a kilogram
+      (Pricing::PerKilogram(price), Measure::Grams(grams)) => price.checked_basis_points(grams.checked_mul(10)?),
+      _ => None,
+    }
+  }
+}
+impl fmt::Display for Pricing {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Pricing::PerUnit(price) => write!(f, "{} each", price),
+      Pricing::PerKilogram(price) => write!(f, "{}/kg", price),
+    }
+  }
+}
+
+// how much of something was sold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Measure {
+  Units(u32),
+  Grams(u32),
+}
+impl fmt::Display for Measure {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Measure::Units(count) => write!(f, "{} x", count),
+      Measure::Grams(grams) => write!(f, "{} g", grams),
+    }
+  }
+}
+
+// produce counted or weighed and priced, ready to go on an order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProduceLine {
+  pub item: Vegetables,
+  pub measure: Measure,
+  // per unit or per kilogram, going by the measure
+  pub rate: Money,
+  pub total: Money,
+}
+impl ProduceLine {
+  // how many to show on a receipt. a weighed line is one item, whatever it weighs
+  pub fn quantity(&self) -> u32 {
+    match self.measure {
+      Measure::Units(count) => count,
+      Measure::Grams(_) => 1,
+    }
+  }
+
+  // the price of one of quantity()
+  pub fn unit_price(&self) -> Money {
+    match self.measure {
+      Measure::Units(_) => self.rate,
+      Measure::Grams(_) => self.total,
+    }
+  }
+
+  // what the scale printed: "412 g @ $6.00/kg". nothing to say for counted produce
+  pub fn weighing(&self) -> Option<String> {
+    match self.measure {
+      Measure::Grams(grams) => Some(format!("{} g @ {}/kg", grams, self.rate)),
+      Measure::Units(_) => None,
+    }
+  }
+}
+// "3 x Squash = $7.50" or "412 g Beets = $2.47"
+impl fmt::Display for ProduceLine {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} {} = {}", self.measure, self.item, self.total)
+  }
+}
+
+// what the stand sells, how each thing is priced and when it's in season. items come out in the
+// order they were priced, which is the order they're on the board
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Farmstand {
+  prices: Vec<(Vegetables, Pricing)>,
+  // anything without a season is available all year
+  seasons: Vec<(Vegetables, Season)>,
+}
+impl Farmstand {
+  // an empty stand. produce has to be priced before it can be sold
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // the chalkboard out front
+  pub fn standard() -> Self {
+    let mut stand = Self::new();
+    stand.set_price(Vegetables::Asparagus, Pricing::PerKilogram(Money::from_cents(1_100)));
+    stand.set_price(Vegetables::Squash, Pricing::PerUnit(Money::from_cents(250)));
+    stand.set_price(Vegetables::Beets, Pricing::PerKilogram(Money::from_cents(600)));
+    stand.set_price(Vegetables::pickle("dill"), Pricing::PerUnit(Money::from_cents(800)));
+    stand.set_price(Vegetables::pickle("bread and butter"), Pricing::PerUnit(Money::from_cents(800)));
+    stand.set_price(Vegetables::pickle("spicy garlic"), Pricing::PerUnit(Money::from_cents(900)));
+    let window = |from, until| Window::new(from, until).expect("the standard seasons are real days");
+    stand.set_season(Vegetables::Asparagus, Season::new(vec![window((4, 1), (6, 15))], Measure::Grams(8_000)));
+    stand.set_season(Vegetables::Squash, Season::new(vec![window((9, 1), (11, 30))], Measure::Units(40)));
+    // sown twice: an early crop and a late one
+    let beets = vec![window((6, 1), (7, 31)), window((9, 15), (10, 31))];
+    stand.set_season(Vegetables::Beets, Season::new(beets, Measure::Grams(12_000)));
+    stand
+  }
+
+  pub fn set_season(&mut self, item: Vegetables, season: Season) {
+    match self.seasons.iter_mut().find(|(with_season, _)| *with_season == item) {
+      Some(entry) => entry.1 = season,
+      None => self.seasons.push((item, season)),
+    }
+  }
+
+  pub fn season_of(&self, item: &Vegetables) -> Option<&Season> {
+    self.seasons.iter().find(|(with_season, _)| with_season == item).map(|(_, season)| season)
+  }
+
+  pub fn availability(&self, item: &Vegetables, date: Date) -> Availability {
+    match self.season_of(item) {
+      None => Availability::AllYear,
+      Some(season) if season.contains(date) => Availability::InSeason { harvest: season.daily_harvest },
+      Some(season) => Availability::OutOfSeason { back: season.next_start(date) },
+    }
+  }
+
+  // the board as it should read on `date`: only what's actually there to sell
+  pub fn available_on(&self, date: Date) -> Vec<(&Vegetables, Pricing)> {
+    self.items().filter(|(item, _)| self.availability(item, date).is_available()).collect()
+  }
+
+  // the week `date` falls in, monday to sunday: what's priced, and how much of it each day
+  pub fn availability_board(&self, date: Date) -> String {
+    let monday = date.add_days(-i64::from(date.weekday() - 1));
+    let days: Vec<Date> = (0..7).map(|offset| monday.add_days(offset)).collect();
+    let mut out = format!("farm stand availability, week of {}\n", monday);
+    out.push_str(&format!("{:<40}", ""));
+    for day in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
+      out.push_str(&format!("{:>7}", day));
+    }
+    out.push('\n');
+    for (item, pricing) in self.items() {
+      out.push_str(&format!("{:<28}{:<12}", item.to_string(), pricing.to_string()));
+      for day in &days {
+        let cell = match self.availability(item, *day) {
+          Availability::AllYear => String::from("yes"),
+          Availability::InSeason { harvest } => season::harvest_amount(harvest),
+          Availability::OutOfSeason { .. } => String::from("-"),
+        };
+        out.push_str(&format!("{:>7}", cell));
+      }
+      // nothing all week: say when it's back, since someone will ask
+      let sunday = days[6];
+      if let Availability::OutOfSeason { back: Some(back) } = self.availability(item, sunday) {
+        if days.iter().all(|day| !self.availability(item, *day).is_available()) {
+          out.push_str(&format!("  back {}", back));
+        }
+      }
+      out.push('\n');
+    }
+    out
+  }
+
+  // pricing something already on the board changes its price and keeps its place
+  pub fn set_price(&mut self, item: Vegetables, pricing: Pricing) {
+    match self.prices.iter_mut().find(|(priced, _)| *priced == item) {
+      Some(entry) => entry.1 = pricing,
+      None => self.prices.push((item, pricing)),
+    }
+  }
+
+  pub fn price_of(&self, item: &Vegetables) -> Option<Pricing> {
+    self.prices.iter().find(|(priced, _)| priced == item).map(|(_, pricing)| *pricing)
+  }
+
+  pub fn items(&self) -> impl Iterator<Item = (&Vegetables, Pricing)> {
+    self.prices.iter().map(|(item, pricing)| (item, *pricing))
+  }
+
+  // the pickles we have a price for, e.g. ["dill", "bread and butter"]
+  pub fn pickle_varieties(&self) -> Vec<&str> {
+    self
+      .prices
+      .iter()
+      .filter_map(|(item, _)| match item {
+        Vegetables::Pickle(variety) => Some(variety.as_str()),
+        _ => None,
+      })
+      .collect()
+  }
+
+  // `count` of something sold by the piece
+  pub fn sell(&self, item: &Vegetables, count: u32) -> Result<ProduceLine, FarmstandError> {
+    self.line(item, Measure::Units(count))
+  }
+
+  // something sold by weight, off the scale. the price is rounded to the nearest cent
+  pub fn weigh(&self, item: &Vegetables, grams: u32) -> Result<ProduceLine, FarmstandError> {
+    self.line(item, Measure::Grams(grams))
+  }
+
+  fn line(&self, item: &Vegetables, measure: Measure) -> Result<ProduceLine, FarmstandError> {
+    let pricing = self.price_of(item).ok_or_else(|| FarmstandError::NotSold(item.clone()))?;
+    if matches!(measure, Measure::Units(0) | Measure::Grams(0)) {
+      return Err(FarmstandError::NothingToSell);
+    }
+    let total = pricing.charge(measure).ok_or_else(|| match (pricing, measure) {
+      (Pricing::PerKilogram(_), Measure::Units(_)) => FarmstandError::SoldByWeight(item.clone()),
+      (Pricing::PerUnit(_), Measure::Grams(_)) => FarmstandError::SoldByUnit(item.clone()),
+      _ => FarmstandError::TooMuch(item.clone()),
+    })?;
+    Ok(ProduceLine {
+      item: item.clone(),
+      measure,
+      rate: pricing.rate(),
+      total,
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FarmstandError {
+  // not on the board
+  NotSold(Vegetables),
+  // counted, but it has to be weighed
+  SoldByWeight(Vegetables),
+  // weighed, but it's sold by the piece
+  SoldByUnit(Vegetables),
+  NothingToSell,
+  // more than the till can ring up
+  TooMuch(Vegetables),
+}
+impl fmt::Display for FarmstandError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FarmstandError::NotSold(item) => write!(f, "the farm stand doesn't sell {}", item),
+      FarmstandError::SoldByWeight(item) => write!(f, "{} is sold by weight, so it needs weighing", item),
+      FarmstandError::SoldByUnit(item) => write!(f, "{} is sold by the piece, not by weight", item),
+      FarmstandError::NothingToSell => write!(f, "can't sell none of something"),
+      FarmstandError::TooMuch(item) => write!(f, "that much {} is too much to ring up", item),
+    }
+  }
+}
+impl std::error::Error for FarmstandError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn weighed_produce_is_priced_to_the_nearest_cent() {
+    let stand = Farmstand::standard();
+    // 412 g at $6.00/kg is $2.472
+    let beets = stand.weigh(&Vegetables::Beets, 412).unwrap();
+    assert_eq!(beets.total, Money::from_cents(247));
+    assert_eq!(beets.to_string(), "412 g Beets = $2.47");
+    assert_eq!(beets.weighing().as_deref(), Some("412 g @ $6.00/kg"));
+    assert_eq!((beets.quantity(), beets.unit_price()), (1, Money::from_cents(247)));
+    // 45 g at $11.00/kg is 49.5 cents, and half a cent rounds up
+    assert_eq!(stand.weigh(&Vegetables::Asparagus, 45).unwrap().total, Money::from_cents(50));
+    // the most that can be weighed is 429,496,729 g, and any more isn't rung up as that much
+    assert!(stand.weigh(&Vegetables::Beets, 429_496_729).is_ok());
+    let err = stand.weigh(&Vegetables::Beets, 429_496_730).unwrap_err();
+    assert_eq!(err, FarmstandError::TooMuch(Vegetables::Beets));
+  }
+
+  #[test]
+  fn produce_is_sold_the_way_it_is_priced() {
+    let stand = Farmstand::standard();
+    let squash = stand.sell(&Vegetables::Squash, 3).unwrap();
+    assert_eq!(squash.total, Money::from_cents(750));
+    assert_eq!(stand.sell(&Vegetables::Beets, 2), Err(FarmstandError::SoldByWeight(Vegetables::Beets)));
+    assert_eq!(stand.weigh(&Vegetables::Squash, 900), Err(FarmstandError::SoldByUnit(Vegetables::Squash)));
+    assert_eq!(
+      stand.sell(&Vegetables::pickle("sweet"), 1).unwrap_err().to_string(),
+      "the farm stand doesn't sell Pickles (sweet)"
+    );
+  }
+
+  fn day(month: u32, day: u32) -> Date {
+    Date::new(2024, month, day).unwrap()
+  }
+
+  #[test]
+  fn nobody_gets_asparagus_in_october() {
+    let stand = Farmstand::standard();
+    let october = day(10, 15);
+    let back = Date::new(2025, 4, 1);
+    assert_eq!(stand.availability(&Vegetables::Asparagus, october), Availability::OutOfSeason { back });
+    assert_eq!(
+      stand.availability(&Vegetables::Squash, october),
+      Availability::InSeason { harvest: Measure::Units(40) }
+    );
+    assert_eq!(stand.availability(&Vegetables::pickle("dill"), october), Availability::AllYear);
+    // beets have a gap between their two crops
+    assert!(!stand.availability(&Vegetables::Beets, day(8, 20)).is_available());
+    assert_eq!(stand.availability(&Vegetables::Beets, day(8, 20)).to_string(), "out of season until 2024-09-15");
+
+    let on_the_board: Vec<&Vegetables> = stand.available_on(october).into_iter().map(|(item, _)| item).collect();
+    assert!(!on_the_board.contains(&&Vegetables::Asparagus));
+    assert_eq!(on_the_board.len(), 5);
+  }
+
+  #[test]
+  fn windows_can_run_over_new_year() {
+    let winter = Window::new((11, 15), (2, 28)).unwrap();
+    assert!(winter.contains(day(12, 31)) && winter.contains(day(1, 10)));
+    assert!(!winter.contains(day(3, 1)));
+    assert_eq!(winter.next_start(day(3, 1)), day(11, 15));
+    assert_eq!(winter.to_string(), "Nov 15 - Feb 28");
+    assert_eq!(Window::new((2, 30), (3, 1)), None);
+  }
+
+  #[test]
+  fn the_board_covers_monday_to_sunday() {
+    // a wednesday
+    let board = Farmstand::standard().availability_board(day(5, 29));
+    let lines: Vec<&str> = board.lines().collect();
+    assert_eq!(lines[0], "farm stand availability, week of 2024-05-27");
+    assert!(lines[2].starts_with("Asparagus") && lines[2].ends_with("8 kg"));
+    assert!(lines[3].ends_with("-  back 2024-09-01"));
+    // the early beets start on the saturday
+    assert!(lines[4].ends_with("-      -  12 kg  12 kg"));
+  }
+
+  #[test]
+  fn pickles_carry_their_variety() {
+    assert_eq!(Vegetables::from_name("Dill Pickles"), Some(Vegetables::pickle("dill")));
+    assert_eq!(Vegetables::from_name("pickles (bread and butter)"), Some(Vegetables::pickle("bread and butter")));
+    assert_eq!(Vegetables::from_name("pickles"), None);
+    assert_eq!(Vegetables::from_name("beet"), Some(Vegetables::Beets));
+    assert_eq!(Farmstand::standard().pickle_varieties(), vec!["dill", "bread and butter", "spicy garlic"]);
+  }
+}
@@ -0,0 +1,118 @@
+// when each thing comes out of the ground, and how much of it a day.
+//
+// a season is one or more windows of the year (asparagus in spring, beets early and late) plus
+// what a day's harvest comes to while it lasts. produce without a season - the pickles, which
+// come out of the pantry - is available all year.
+
+use std::fmt;
+
+use crate::date::Date;
+use crate::felicity_farmstand::Measure;
+
+// a stretch of the year from one day to another, both included. a window that ends before it
+// starts runs over new year, e.g. November 15 to February 28
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+  from: (u32, u32),
+  until: (u32, u32),
+}
+impl Window {
+  // (month, day) pairs. None for a day that isn't in the calendar
+  pub fn new(from: (u32, u32), until: (u32, u32)) -> Option<Self> {
+    // any leap year will do, so February 29 counts as a day
+    let real = |(month, day)| Date::new(2024, month, day).is_some();
+    if real(from) && real(until) {
+      Some(Self { from, until })
+    } else {
+      None
+    }
+  }
+
+  pub fn contains(&self, date: Date) -> bool {
+    let day = (date.month(), date.day());
+    if self.from <= self.until {
+      self.from <= day && day <= self.until
+    } else {
+      day >= self.from || day <= self.until
+    }
+  }
+
+  // the first day of this window on or after `date`
+  pub fn next_start(&self, date: Date) -> Date {
+    let (month, day) = self.from;
+    (date.year()..)
+      .filter_map(|year| Date::new(year, month, day))
+      .find(|start| *start >= date)
+      .expect("every window starts at least every four years")
+  }
+}
+// "Apr 1 - Jun 15"
+impl fmt::Display for Window {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let day = |(month, day): (u32, u32)| format!("{} {}", MONTHS[month as usize - 1], day);
+    write!(f, "{} - {}", day(self.from), day(self.until))
+  }
+}
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Season {
+  pub windows: Vec<Window>,
+  // counted or weighed, the way the item is sold
+  pub daily_harvest: Measure,
+}
+impl Season {
+  pub fn new(windows: Vec<Window>, daily_harvest: Measure) -> Self {
+    Self { windows, daily_harvest }
+  }
+
+  pub fn contains(&self, date: Date) -> bool {
+    self.windows.iter().any(|window| window.contains(date))
+  }
+
+  // the first day on or after `date` that's in season
+  pub fn next_start(&self, date: Date) -> Option<Date> {
+    if self.contains(date) {
+      return Some(date);
+    }
+    self.windows.iter().map(|window| window.next_start(date)).min()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Availability {
+  // no season: there's always some
+  AllYear,
+  InSeason { harvest: Measure },
+  // and the day it's back, if it ever is
+  OutOfSeason { back: Option<Date> },
+}
+impl Availability {
+  pub fn is_available(&self) -> bool {
+    !matches!(self, Availability::OutOfSeason { .. })
+  }
+}
+// "all year", "8 kg a day" or "out of season until 2025-04-01"
+impl fmt::Display for Availability {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Availability::AllYear => write!(f, "all year"),
+      Availability::InSeason { harvest } => write!(f, "{} a day", harvest_amount(*harvest)),
+      Availability::OutOfSeason { back: Some(back) } => write!(f, "out of season until {}", back),
+      Availability::OutOfSeason { back: None } => write!(f, "out of season"),
+    }
+  }
+}
+
+// "40" or "8.5 kg"
+pub fn harvest_amount(harvest: Measure) -> String {
+  match harvest {
+    Measure::Units(count) => count.to_string(),
+    Measure::Grams(grams) if grams % 1000 == 0 => format!("{} kg", grams / 1000),
+    Measure::Grams(grams) => {
+      let fraction = format!("{:03}", grams % 1000);
+      format!("{}.{} kg", grams / 1000, fraction.trim_end_matches('0'))
+    }
+  }
+}
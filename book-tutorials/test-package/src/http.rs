@@ -0,0 +1,810 @@
+// a small HTTP/1.1 server so the kiosk and the handhelds can share one order book.
+// it's built straight on TcpListener: one thread per connection, one request per connection
+// (every response says "Connection: close"), JSON in and out. only so many connections are
+// answered at once - past that, a connection gets a 503 instead of a thread.
+//
+//   GET    /menu          every drink with its price
+//   POST   /orders        place an order, e.g. {"items": [{"drink": "latte", "quantity": 2,
+//                         "size": "large", "milk": "oat", "extra_shots": 1,
+//                         "syrups": ["vanilla"], "temperature": "iced"}]}
+//                         beer needs the "date_of_birth" off the customer's ID as well
+//   GET    /orders/<id>   an order, its status and how it got there
+//   PATCH  /orders/<id>   move an order along, e.g. {"status": "brewing", "actor": "alex"}
+//   DELETE /orders/<id>   cancel an order
+//   GET    /customers/<phone or card>
+//                         a loyalty member's greeting, usual drink and free coffees. staff only:
+//                         needs "Authorization: Bearer <staff token>"
+//
+// the actor defaults to "api" when a request doesn't name one. orders with age-restricted drinks go
+// through the age policy when they're placed and again when they're paid for, the same as at the
+// till, with the actor as whoever checked the ID. a refusal is a 403, and every check goes into the
+// audit trail.
+//
+// Api::handle does all the work on an already-read Request, so it can be used without a socket.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::custom::{CustomDrink, Milk, Size, Syrup, Temperature};
+use crate::date::{Date, Timestamp};
+use crate::json::Json;
+use crate::lifecycle::{Lifecycle, OrderStatus};
+use crate::loyalty::CustomerBook;
+use crate::order::Order;
+use crate::policy::{AgePolicy, AuditLog, IdCheck};
+use crate::pricing::{Bill, PriceTable};
+use crate::Drinks;
+
+// requests bigger than this are refused rather than read into memory
+const MAX_BODY_BYTES: usize = 64 * 1024;
+// the request line and headers together
+const MAX_HEADER_BYTES: u64 = 8 * 1024;
+// how long a client gets to send its request, and to take the response, before it's dropped
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+// connections being answered at once, unless the server is told otherwise. each one can hold its
+// thread for up to IO_TIMEOUT, so without a cap a burst of idle clients could start any number
+pub const MAX_CONNECTIONS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+  pub method: String,
+  pub path: String,
+  // the Authorization header, if there was one
+  pub authorization: Option<String>,
+  pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+  pub status: u16,
+  pub body: Json,
+}
+impl Response {
+  pub fn json(status: u16, body: Json) -> Self {
+    Self { status, body }
+  }
+
+  pub fn error(status: u16, message: impl Into<String>) -> Self {
+    Self::json(status, Json::object().field("error", message.into()))
+  }
+
+  fn write_to(&self, stream: &mut impl Write) -> io::Result<()> {
+    let body = self.body.to_string();
+    write!(
+      stream,
+      "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+      self.status,
+      reason_phrase(self.status),
+      body.len(),
+      body
+    )?;
+    stream.flush()
+  }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+  match status {
+    200 => "OK",
+    201 => "Created",
+    400 => "Bad Request",
+    401 => "Unauthorized",
+    404 => "Not Found",
+    403 => "Forbidden",
+    405 => "Method Not Allowed",
+    408 => "Request Timeout",
+    409 => "Conflict",
+    413 => "Payload Too Large",
+    422 => "Unprocessable Entity",
+    431 => "Request Header Fields Too Large",
+    503 => "Service Unavailable",
+    _ => "Internal Server Error",
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookedOrder {
+  pub id: u64,
+  pub order: Order,
+  pub bill: Bill,
+  // the ID checked when the order was placed, for checking again when it's paid for
+  pub id_check: Option<IdCheck>,
+  pub lifecycle: Lifecycle,
+}
+impl BookedOrder {
+  fn to_json(&self) -> Json {
+    let lines: Vec<Json> = self
+      .bill
+      .lines
+      .iter()
+      .map(|line| {
+        Json::object()
+          .field("quantity", line.quantity)
+          .field("name", line.item.name())
+          .field("modifiers", line.item.modifiers())
+          .field("unit_price_cents", line.unit_price.cents())
+          .field("total_cents", line.total.cents())
+      })
+      .collect();
+    let history: Vec<Json> = self
+      .lifecycle
+      .history()
+      .iter()
+      .map(|transition| {
+        Json::object()
+          .field("status", transition.to.name())
+          .field("at", transition.at.to_string())
+          .field("actor", transition.actor.as_str())
+      })
+      .collect();
+    Json::object()
+      .field("id", self.id as i64)
+      .field("status", self.lifecycle.status().name())
+      .field("history", history)
+      .field("lines", lines)
+      .field("subtotal_cents", self.bill.subtotal.cents())
+      .field("tax_cents", self.bill.tax.cents())
+      .field("total_cents", self.bill.total.cents())
+  }
+}
+
+// every order placed through the API, by id
+#[derive(Debug, Default)]
+pub struct OrderBook {
+  orders: BTreeMap<u64, BookedOrder>,
+  next_id: u64,
+}
+impl OrderBook {
+  pub fn new() -> Self {
+    Self {
+      orders: BTreeMap::new(),
+      next_id: 1,
+    }
+  }
+
+  pub fn place(
+    &mut self,
+    order: Order,
+    bill: Bill,
+    id_check: Option<IdCheck>,
+    at: Timestamp,
+    actor: &str,
+  ) -> &BookedOrder {
+    let id = self.next_id;
+    self.next_id += 1;
+    self.orders.entry(id).or_insert(BookedOrder {
+      id,
+      order,
+      bill,
+      id_check,
+      lifecycle: Lifecycle::placed(at, actor),
+    })
+  }
+
+  pub fn get(&self, id: u64) -> Option<&BookedOrder> {
+    self.orders.get(&id)
+  }
+
+  pub fn get_mut(&mut self, id: u64) -> Option<&mut BookedOrder> {
+    self.orders.get_mut(&id)
+  }
+}
+
+// the routes, shared between every connection
+#[derive(Clone)]
+pub struct Api {
+  prices: Arc<PriceTable>,
+  book: Arc<Mutex<OrderBook>>,
+  // where the loyalty card is kept, when there is one, and the token staff use to look members
+  // up. read afresh for every lookup, since the till signs people up and punches their cards
+  // while the server is running
+  customers: Option<Arc<(PathBuf, String)>>,
+  policy: Arc<AgePolicy>,
+  audit: Arc<Mutex<AuditLog>>,
+}
+impl Api {
+  // beer is never sold without an ID check, even before a policy is set
+  pub fn new(prices: PriceTable) -> Self {
+    Self {
+      prices: Arc::new(prices),
+      book: Arc::new(Mutex::new(OrderBook::new())),
+      customers: None,
+      policy: Arc::new(AgePolicy::new("default", DEFAULT_MINIMUM_AGE)),
+      audit: Arc::new(Mutex::new(AuditLog::new())),
+    }
+  }
+
+  pub fn set_policy(&mut self, policy: AgePolicy, audit: AuditLog) {
+    self.policy = Arc::new(policy);
+    self.audit = Arc::new(Mutex::new(audit));
+  }
+
+  pub fn audit(&self) -> Arc<Mutex<AuditLog>> {
+    Arc::clone(&self.audit)
+  }
+
+  // member lookups are refused unless the request carries `staff_token`
+  pub fn set_customers(&mut self, path: impl Into<PathBuf>, staff_token: impl Into<String>) {
+    self.customers = Some(Arc::new((path.into(), staff_token.into())));
+  }
+
+  pub fn book(&self) -> Arc<Mutex<OrderBook>> {
+    Arc::clone(&self.book)
+  }
+
+  pub fn handle(&self, request: &Request) -> Response {
+    let segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+    match (request.method.as_str(), segments.as_slice()) {
+      ("GET", ["menu"]) => self.menu(),
+      ("POST", ["orders"]) => self.place_order(&request.body),
+      ("GET", ["orders", id]) => self.with_order(id, |order| Response::json(200, order.to_json())),
+      ("PATCH", ["orders", id]) => self.update_order(id, &request.body),
+      ("DELETE", ["orders", id]) => {
+        self.with_order(id, |order| move_order(order, OrderStatus::Cancelled, DEFAULT_ACTOR))
+      }
+      ("GET", ["customers", number]) => self.customer(number, request.authorization.as_deref()),
+      (_, ["menu"]) | (_, ["orders"]) | (_, ["orders", _]) | (_, ["customers", _]) => {
+        Response::error(405, format!("{} is not allowed on {}", request.method, request.path))
+      }
+      _ => Response::error(404, format!("nothing at {}", request.path)),
+    }
+  }
+
+  fn menu(&self) -> Response {
+    let drinks: Vec<Json> = Drinks::all()
+      .into_iter()
+      .filter_map(|drink| {
+        let price = self.prices.price_of(&drink)?;
+        Some(
+          Json::object()
+            .field("name", drink.to_string())
+            .field("price_cents", price.cents()),
+        )
+      })
+      .collect();
+    Response::json(200, Json::object().field("drinks", drinks))
+  }
+
+  fn place_order(&self, body: &str) -> Response {
+    let json = match Json::parse(body) {
+      Ok(json) => json,
+      Err(e) => return Response::error(400, e.to_string()),
+    };
+    let order = match order_from_json(&json) {
+      Ok(order) => order,
+      Err(message) => return Response::error(422, message),
+    };
+    let bill = match self.prices.price(&order) {
+      Ok(bill) => bill,
+      Err(e) => return Response::error(422, e.to_string()),
+    };
+    let actor = match actor_from_json(&json) {
+      Ok(actor) => actor,
+      Err(message) => return Response::error(422, message),
+    };
+    let id_check = match id_check_from_json(&json, actor) {
+      Ok(id_check) => id_check,
+      Err(message) => return Response::error(422, message),
+    };
+    let now = Timestamp::now();
+    if let Err(response) = self.authorize(&order, id_check.as_ref(), now) {
+      return response;
+    }
+    let mut book = self.book.lock().unwrap();
+    Response::json(201, book.place(order, bill, id_check, now, actor).to_json())
+  }
+
+  fn update_order(&self, id: &str, body: &str) -> Response {
+    let json = match Json::parse(body) {
+      Ok(json) => json,
+      Err(e) => return Response::error(400, e.to_string()),
+    };
+    let status = match json.get("status").and_then(Json::as_str) {
+      Some(name) => match OrderStatus::from_name(name) {
+        Some(status) => status,
+        None => return Response::error(422, format!("\"{}\" is not an order status", name)),
+      },
+      None => return Response::error(422, "the new \"status\" is missing"),
+    };
+    let actor = match actor_from_json(&json) {
+      Ok(actor) => actor,
+      Err(message) => return Response::error(422, message),
+    };
+    // the ID can be checked again at the counter, or the one from when the order was placed is used
+    let id_check = match id_check_from_json(&json, actor) {
+      Ok(id_check) => id_check,
+      Err(message) => return Response::error(422, message),
+    };
+    self.with_order(id, |order| {
+      if status == OrderStatus::Paid {
+        let id_check = id_check.as_ref().or(order.id_check.as_ref());
+        if let Err(response) = self.authorize(&order.order, id_check, Timestamp::now()) {
+          return response;
+        }
+      }
+      move_order(order, status, actor)
+    })
+  }
+
+  // the age policy, logged to the audit trail. a refusal comes back as the response to send
+  fn authorize(&self, order: &Order, id_check: Option<&IdCheck>, at: Timestamp) -> Result<(), Response> {
+    let mut audit = self.audit.lock().unwrap();
+    self
+      .policy
+      .authorize(order, id_check, at, &mut audit)
+      .map_err(|e| Response::error(403, e.to_string()))
+  }
+
+  fn customer(&self, number: &str, authorization: Option<&str>) -> Response {
+    let (path, staff_token) = match self.customers.as_deref() {
+      Some(customers) => customers,
+      None => return Response::error(404, "there is no loyalty card here"),
+    };
+    let given = authorization.and_then(|value| value.strip_prefix("Bearer ")).map(str::trim);
+    if !given.is_some_and(|given| same_token(given, staff_token)) {
+      return Response::error(401, "looking up members needs the staff token");
+    }
+    let customers = match CustomerBook::open(path) {
+      Ok(customers) => customers,
+      Err(e) => return Response::error(500, format!("couldn't read the loyalty card: {}", e)),
+    };
+    match customers.lookup(number) {
+      Some(customer) => Response::json(
+        200,
+        Json::object()
+          .field("id", customer.id as i64)
+          .field("name", customer.name.as_str())
+          .field("greeting", customer.greeting())
+          .field("usual", customer.favorite.map(|drink| drink.to_string()))
+          .field("free_coffees", customer.free_coffees),
+      ),
+      None => Response::error(404, format!("no member with the number {}", number)),
+    }
+  }
+
+  fn with_order(&self, id: &str, f: impl FnOnce(&mut BookedOrder) -> Response) -> Response {
+    let id: u64 = match id.parse() {
+      Ok(id) => id,
+      Err(_) => return Response::error(404, format!("\"{}\" is not an order id", id)),
+    };
+    let mut book = self.book.lock().unwrap();
+    match book.get_mut(id) {
+      Some(order) => f(order),
+      None => Response::error(404, format!("there is no order {}", id)),
+    }
+  }
+}
+
+// compared without stopping at the first difference, so how long it takes doesn't give the token away
+fn same_token(given: &str, token: &str) -> bool {
+  given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// who the lifecycle says made a change, when the client doesn't say
+const DEFAULT_ACTOR: &str = "api";
+// used until set_policy is called
+const DEFAULT_MINIMUM_AGE: u32 = 21;
+
+fn actor_from_json(json: &Json) -> Result<&str, String> {
+  match json.get("actor") {
+    None => Ok(DEFAULT_ACTOR),
+    Some(actor) => match actor.as_str().map(str::trim) {
+      Some(actor) if !actor.is_empty() => Ok(actor),
+      _ => Err(String::from("\"actor\" should be a non-empty string")),
+    },
+  }
+}
+
+// the "date_of_birth" off the customer's ID, checked by `actor`. None when there isn't one
+fn id_check_from_json(json: &Json, actor: &str) -> Result<Option<IdCheck>, String> {
+  match json.get("date_of_birth") {
+    None | Some(Json::Null) => Ok(None),
+    Some(date) => {
+      let date_of_birth: Date = date
+        .as_str()
+        .and_then(|date| date.parse().ok())
+        .ok_or("\"date_of_birth\" should be a YYYY-MM-DD date")?;
+      Ok(Some(IdCheck { date_of_birth, checked_by: actor.to_string() }))
+    }
+  }
+}
+
+// a refused transition is a conflict with the order's current state
+fn move_order(order: &mut BookedOrder, status: OrderStatus, actor: &str) -> Response {
+  match order.lifecycle.advance(status, Timestamp::now(), actor) {
+    Ok(()) => Response::json(200, order.to_json()),
+    Err(e) => Response::error(409, format!("order {}: {}", order.id, e)),
+  }
+}
+
+// the body of POST /orders. the error is a message for the client
+fn order_from_json(json: &Json) -> Result<Order, String> {
+  let items = json
+    .get("items")
+    .and_then(Json::as_array)
+    .ok_or("the order needs an \"items\" list")?;
+  if items.is_empty() {
+    return Err(String::from("the order has no items"));
+  }
+  let mut order = Order::new();
+  for (index, item) in items.iter().enumerate() {
+    let (drink, quantity) = item_from_json(item).map_err(|e| format!("item {}: {}", index + 1, e))?;
+    order.add(drink, quantity).map_err(|e| format!("item {}: {}", index + 1, e))?;
+  }
+  Ok(order)
+}
+
+fn item_from_json(item: &Json) -> Result<(CustomDrink, u32), String> {
+  let text = |key: &str| match item.get(key) {
+    None | Some(Json::Null) => Ok(None),
+    Some(Json::String(s)) => Ok(Some(s.as_str())),
+    Some(_) => Err(format!("\"{}\" should be a string", key)),
+  };
+  let number = |key: &str, default: i64| match item.get(key) {
+    None | Some(Json::Null) => Ok(default),
+    Some(Json::Number(n)) => Ok(*n),
+    Some(_) => Err(format!("\"{}\" should be a number", key)),
+  };
+
+  let drink: Drinks = text("drink")?
+    .ok_or("\"drink\" is required")?
+    .parse()
+    .map_err(|e: crate::ParseDrinkError| e.to_string())?;
+  let quantity = u32::try_from(number("quantity", 1)?)
+    .ok()
+    .filter(|q| *q > 0)
+    .ok_or("\"quantity\" should be at least 1")?;
+
+  let mut builder = CustomDrink::builder(drink);
+  if let Some(size) = text("size")? {
+    builder = builder.size(Size::from_name(size).ok_or(format!("unknown size \"{}\"", size))?);
+  }
+  match text("milk")? {
+    Some("none") => builder = builder.no_milk(),
+    Some(milk) => builder = builder.milk(Milk::from_name(milk).ok_or(format!("unknown milk \"{}\"", milk))?),
+    None => {}
+  }
+  let shots = u8::try_from(number("extra_shots", 0)?).map_err(|_| "\"extra_shots\" is out of range")?;
+  builder = builder.extra_shots(shots);
+  if let Some(syrups) = item.get("syrups") {
+    for syrup in syrups.as_array().ok_or("\"syrups\" should be a list")? {
+      let name = syrup.as_str().ok_or("\"syrups\" should be a list of names")?;
+      builder = builder.syrup(Syrup::from_name(name).ok_or(format!("unknown syrup \"{}\"", name))?);
+    }
+  }
+  if let Some(temperature) = text("temperature")? {
+    let temperature = Temperature::from_name(temperature).ok_or(format!("unknown temperature \"{}\"", temperature))?;
+    builder = builder.temperature(temperature);
+  }
+  let drink = builder.build().map_err(|e| e.to_string())?;
+  Ok((drink, quantity))
+}
+
+// something wrong with the bytes on the wire, before there's a Request to route
+#[derive(Debug)]
+enum ReadError {
+  Io(io::Error),
+  Malformed(&'static str),
+  TooLarge,
+  HeadersTooLarge,
+  // the client stopped sending partway through
+  TimedOut,
+}
+impl fmt::Display for ReadError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ReadError::Io(e) => write!(f, "{}", e),
+      ReadError::Malformed(reason) => write!(f, "malformed request: {}", reason),
+      ReadError::TooLarge => write!(f, "request body is larger than {} bytes", MAX_BODY_BYTES),
+      ReadError::HeadersTooLarge => write!(f, "request headers are larger than {} bytes", MAX_HEADER_BYTES),
+      ReadError::TimedOut => write!(f, "timed out waiting for the request"),
+    }
+  }
+}
+impl From<io::Error> for ReadError {
+  fn from(e: io::Error) -> Self {
+    match e.kind() {
+      // a read timeout shows up as either, depending on the platform
+      io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => ReadError::TimedOut,
+      _ => ReadError::Io(e),
+    }
+  }
+}
+
+fn read_request(stream: &mut impl Read) -> Result<Request, ReadError> {
+  let mut reader = BufReader::new(stream);
+  // a line that never ends stops here instead of growing until memory runs out
+  let mut head = (&mut reader).take(MAX_HEADER_BYTES);
+  let mut line = String::new();
+  read_head_line(&mut head, &mut line)?;
+  let mut parts = line.split_whitespace();
+  let (method, path) = match (parts.next(), parts.next(), parts.next()) {
+    (Some(method), Some(path), Some(version)) if version.starts_with("HTTP/1.") => {
+      (method.to_string(), path.to_string())
+    }
+    _ => return Err(ReadError::Malformed("bad request line")),
+  };
+
+  let mut content_length = 0;
+  let mut authorization = None;
+  loop {
+    line.clear();
+    if read_head_line(&mut head, &mut line)? == 0 {
+      return Err(ReadError::Malformed("headers never ended"));
+    }
+    let header = line.trim_end();
+    if header.is_empty() {
+      break;
+    }
+    if let Some((name, value)) = header.split_once(':') {
+      if name.trim().eq_ignore_ascii_case("content-length") {
+        content_length = value.trim().parse().map_err(|_| ReadError::Malformed("bad content-length"))?;
+      } else if name.trim().eq_ignore_ascii_case("authorization") {
+        authorization = Some(value.trim().to_string());
+      }
+    }
+  }
+  if content_length > MAX_BODY_BYTES {
+    return Err(ReadError::TooLarge);
+  }
+  let mut body = vec![0; content_length];
+  reader.read_exact(&mut body)?;
+  let body = String::from_utf8(body).map_err(|_| ReadError::Malformed("body is not UTF-8"))?;
+  // the query string isn't used by any route
+  let path = path.split('?').next().unwrap_or_default().to_string();
+  Ok(Request { method, path, authorization, body })
+}
+
+// one line of the request line and headers, as long as they're still under MAX_HEADER_BYTES
+fn read_head_line(head: &mut io::Take<impl BufRead>, line: &mut String) -> Result<usize, ReadError> {
+  let read = head.read_line(line)?;
+  if head.limit() == 0 && !line.ends_with('\n') {
+    return Err(ReadError::HeadersTooLarge);
+  }
+  Ok(read)
+}
+
+fn handle_connection(api: &Api, mut stream: TcpStream) -> io::Result<()> {
+  stream.set_read_timeout(Some(IO_TIMEOUT))?;
+  stream.set_write_timeout(Some(IO_TIMEOUT))?;
+  let response = match read_request(&mut stream) {
+    Ok(request) => api.handle(&request),
+    Err(ReadError::Io(e)) => return Err(e),
+    Err(e @ ReadError::TooLarge) => Response::error(413, e.to_string()),
+    Err(e @ ReadError::HeadersTooLarge) => Response::error(431, e.to_string()),
+    Err(e @ ReadError::TimedOut) => Response::error(408, e.to_string()),
+    Err(e) => Response::error(400, e.to_string()),
+  };
+  response.write_to(&mut stream)
+}
+
+// counts a connection as open until its thread is done with it, however that ends
+struct OpenConnection(Arc<AtomicUsize>);
+impl Drop for OpenConnection {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+pub struct Server {
+  listener: TcpListener,
+  api: Api,
+  max_connections: usize,
+}
+impl Server {
+  // port 0 picks any free port - see local_addr for which
+  pub fn bind(addr: impl ToSocketAddrs, prices: PriceTable) -> io::Result<Self> {
+    Ok(Self {
+      listener: TcpListener::bind(addr)?,
+      api: Api::new(prices),
+      max_connections: MAX_CONNECTIONS,
+    })
+  }
+
+  pub fn set_max_connections(&mut self, max_connections: usize) {
+    self.max_connections = max_connections;
+  }
+
+  pub fn local_addr(&self) -> io::Result<SocketAddr> {
+    self.listener.local_addr()
+  }
+
+  pub fn api(&self) -> &Api {
+    &self.api
+  }
+
+  pub fn set_customers(&mut self, path: impl Into<PathBuf>, staff_token: impl Into<String>) {
+    self.api.set_customers(path, staff_token);
+  }
+
+  pub fn set_policy(&mut self, policy: AgePolicy, audit: AuditLog) {
+    self.api.set_policy(policy, audit);
+  }
+
+  // answer requests until the process ends, each connection on its own thread
+  pub fn serve(&self) {
+    let open = Arc::new(AtomicUsize::new(0));
+    for stream in self.listener.incoming() {
+      match stream {
+        // only this thread adds to the count, so it can't go past the most between the check and
+        // the add. the 503 is small enough to go straight into the socket's buffer
+        Ok(mut stream) if open.load(Ordering::SeqCst) >= self.max_connections => {
+          let busy = Response::error(503, "too many connections at once, try again shortly");
+          if let Err(e) = stream.set_write_timeout(Some(IO_TIMEOUT)).and_then(|_| busy.write_to(&mut stream)) {
+            eprintln!("connection failed: {}", e);
+          }
+        }
+        Ok(stream) => {
+          open.fetch_add(1, Ordering::SeqCst);
+          let connection = OpenConnection(Arc::clone(&open));
+          let api = self.api.clone();
+          thread::spawn(move || {
+            let _connection = connection;
+            if let Err(e) = handle_connection(&api, stream) {
+              eprintln!("connection failed: {}", e);
+            }
+          });
+        }
+        Err(e) => eprintln!("couldn't accept a connection: {}", e),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn request(method: &str, path: &str, body: &str) -> Request {
+    Request {
+      method: method.to_string(),
+      path: path.to_string(),
+      authorization: None,
+      body: body.to_string(),
+    }
+  }
+
+  #[test]
+  fn places_looks_up_and_cancels_orders() {
+    let api = Api::new(PriceTable::standard());
+    let body = r#"{"items": [{"drink": "latte", "quantity": 2, "size": "large", "milk": "oat"}]}"#;
+    let placed = api.handle(&request("POST", "/orders", body));
+    assert_eq!(placed.status, 201);
+    assert_eq!(placed.body.get("id"), Some(&Json::Number(1)));
+    assert_eq!(placed.body.get("subtotal_cents"), Some(&Json::Number(1170)));
+
+    let found = api.handle(&request("GET", "/orders/1", ""));
+    assert_eq!(found.body.get("status"), Some(&Json::string("placed")));
+
+    assert_eq!(api.handle(&request("DELETE", "/orders/1", "")).status, 200);
+    assert_eq!(api.handle(&request("DELETE", "/orders/1", "")).status, 409);
+    assert_eq!(api.handle(&request("GET", "/orders/2", "")).status, 404);
+  }
+
+  #[test]
+  fn moves_orders_through_their_lifecycle() {
+    let api = Api::new(PriceTable::standard());
+    api.handle(&request("POST", "/orders", r#"{"items": [{"drink": "mocha"}], "actor": "kiosk"}"#));
+
+    let paid = api.handle(&request("PATCH", "/orders/1", r#"{"status": "paid"}"#));
+    assert_eq!(paid.status, 200);
+    let brewing = api.handle(&request("PATCH", "/orders/1", r#"{"status": "brewing", "actor": "alex"}"#));
+    let history = brewing.body.get("history").and_then(Json::as_array).unwrap();
+    let actors: Vec<&str> = history.iter().filter_map(|t| t.get("actor").and_then(Json::as_str)).collect();
+    assert_eq!(actors, vec!["kiosk", "api", "alex"]);
+
+    let cancel = api.handle(&request("DELETE", "/orders/1", ""));
+    assert_eq!(cancel.status, 409);
+    assert_eq!(
+      cancel.body.get("error"),
+      Some(&Json::string("order 1: a brewing order can't be cancelled (it can only become ready or refunded)"))
+    );
+    assert_eq!(api.handle(&request("PATCH", "/orders/1", r#"{"status": "gone"}"#)).status, 422);
+  }
+
+  #[test]
+  fn beer_needs_a_date_of_birth() {
+    let api = Api::new(PriceTable::standard());
+    let refused = api.handle(&request("POST", "/orders", r#"{"items": [{"drink": "beer"}]}"#));
+    assert_eq!(refused.status, 403);
+    assert_eq!(refused.body.get("error"), Some(&Json::string("age-restricted items need an ID check first")));
+    let underage = r#"{"items": [{"drink": "beer"}], "date_of_birth": "2020-01-01"}"#;
+    assert_eq!(api.handle(&request("POST", "/orders", underage)).status, 403);
+    let bad_date = r#"{"items": [{"drink": "beer"}], "date_of_birth": "last year"}"#;
+    assert_eq!(api.handle(&request("POST", "/orders", bad_date)).status, 422);
+
+    let body = r#"{"items": [{"drink": "beer"}], "date_of_birth": "1990-06-01", "actor": "sam"}"#;
+    assert_eq!(api.handle(&request("POST", "/orders", body)).status, 201);
+    // checked again when it's paid for, with the ID from when it was placed
+    assert_eq!(api.handle(&request("PATCH", "/orders/1", r#"{"status": "paid"}"#)).status, 200);
+    let audit = api.audit();
+    let audit = audit.lock().unwrap();
+    let outcomes: Vec<bool> = audit.entries().iter().map(|entry| entry.outcome.is_ok()).collect();
+    assert_eq!(outcomes, vec![false, false, true, true]);
+    assert_eq!(audit.entries()[2].checked_by.as_deref(), Some("sam"));
+  }
+
+  #[test]
+  fn rejects_bad_orders_with_a_reason() {
+    let api = Api::new(PriceTable::standard());
+    assert_eq!(api.handle(&request("POST", "/orders", "{")).status, 400);
+    let water_shots = api.handle(&request("POST", "/orders", r#"{"items": [{"drink": "water", "extra_shots": 1}]}"#));
+    assert_eq!(water_shots.status, 422);
+    assert_eq!(water_shots.body.get("error"), Some(&Json::string("item 1: Water can't have extra shots")));
+    assert_eq!(api.handle(&request("PUT", "/menu", "")).status, 405);
+  }
+
+  #[test]
+  fn greets_loyalty_members() {
+    let path = std::env::temp_dir().join(format!("test-package-api-customers-{}.csv", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut customers = CustomerBook::open(&path).unwrap();
+    customers.enroll("Sam", Some("555 010 2030"), Some("C-100")).unwrap();
+    let mut api = Api::new(PriceTable::standard());
+    assert_eq!(api.handle(&request("GET", "/customers/C-100", "")).status, 404);
+    api.set_customers(&path, "s3cret");
+    let staff = |path: &str, token: &str| Request {
+      authorization: Some(format!("Bearer {}", token)),
+      ..request("GET", path, "")
+    };
+
+    assert_eq!(api.handle(&request("GET", "/customers/C-100", "")).status, 401);
+    assert_eq!(api.handle(&staff("/customers/C-100", "guess")).status, 401);
+    let found = api.handle(&staff("/customers/C-100", "s3cret"));
+    assert_eq!(found.body.get("greeting"), Some(&Json::string("welcome, Sam!")));
+    assert_eq!(found.body.get("usual"), Some(&Json::Null));
+    assert_eq!(api.handle(&staff("/customers/5550102030", "s3cret")).status, 200);
+    // signed up at the till after the server started
+    customers.enroll("Alex", None, Some("C-200")).unwrap();
+    assert_eq!(api.handle(&staff("/customers/C-200", "s3cret")).status, 200);
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn reads_only_so_much_of_the_head() {
+    let request = read_request(&mut "GET /menu HTTP/1.1\r\nHost: localhost\r\n\r\n".as_bytes()).unwrap();
+    assert_eq!((request.method.as_str(), request.path.as_str()), ("GET", "/menu"));
+    let endless = format!("GET /menu HTTP/1.1\r\nX-Padding: {}", "a".repeat(MAX_HEADER_BYTES as usize));
+    assert!(matches!(read_request(&mut endless.as_bytes()), Err(ReadError::HeadersTooLarge)));
+    let timed_out = io::Error::from(io::ErrorKind::WouldBlock);
+    assert!(matches!(ReadError::from(timed_out), ReadError::TimedOut));
+  }
+
+  #[test]
+  fn serves_over_tcp() {
+    let server = Server::bind("127.0.0.1:0", PriceTable::standard()).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve());
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "GET /menu HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    let menu = Json::parse(body).unwrap();
+    assert_eq!(menu.get("drinks").and_then(Json::as_array).map(|d| d.len()), Some(Drinks::all().len()));
+  }
+
+  #[test]
+  fn turns_away_connections_past_the_most() {
+    let mut server = Server::bind("127.0.0.1:0", PriceTable::standard()).unwrap();
+    server.set_max_connections(1);
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve());
+
+    // says nothing, so it keeps the only connection open until it times out
+    let _idle = TcpStream::connect(addr).unwrap();
+    let mut turned_away = TcpStream::connect(addr).unwrap();
+    let mut response = String::new();
+    turned_away.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+  }
+}
@@ -0,0 +1,326 @@
+// what's in the back room, and what each drink uses up.
+// a Recipe lists the ingredients for one drink made a particular way, and the StockLedger keeps
+// count of what's on hand. fulfilling an order checks every line before touching any stock, so an
+// order either comes out of stock completely or not at all.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::config::{self, ConfigError};
+use crate::custom::{CustomDrink, Milk, Size, Syrup};
+use crate::order::Order;
+use crate::{CoffeeFlavor, Drinks};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Ingredient {
+  // single espresso shots
+  EspressoShot,
+  // milk, counted in ml
+  Milk(Milk),
+  // chocolate sauce for mochas, in ml
+  Chocolate,
+  // syrup, counted in pumps
+  Syrup(Syrup),
+  Cup,
+  SodaBottle,
+  BeerBottle,
+}
+impl Ingredient {
+  pub fn all() -> Vec<Ingredient> {
+    let mut all = vec![Ingredient::EspressoShot];
+    all.extend(Milk::ALL.iter().map(|milk| Ingredient::Milk(*milk)));
+    all.push(Ingredient::Chocolate);
+    all.extend(Syrup::ALL.iter().map(|syrup| Ingredient::Syrup(*syrup)));
+    all.extend([Ingredient::Cup, Ingredient::SodaBottle, Ingredient::BeerBottle]);
+    all
+  }
+
+  // the opposite of Display, ignoring case
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::all().into_iter().find(|ingredient| ingredient.to_string().eq_ignore_ascii_case(name.trim()))
+  }
+
+  pub fn unit(&self) -> &'static str {
+    match self {
+      Ingredient::EspressoShot => "shots",
+      Ingredient::Milk(_) | Ingredient::Chocolate => "ml",
+      Ingredient::Syrup(_) => "pumps",
+      Ingredient::Cup => "cups",
+      Ingredient::SodaBottle | Ingredient::BeerBottle => "bottles",
+    }
+  }
+}
+impl fmt::Display for Ingredient {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Ingredient::EspressoShot => write!(f, "espresso"),
+      Ingredient::Milk(milk) => write!(f, "{} milk", milk.name()),
+      Ingredient::Chocolate => write!(f, "chocolate"),
+      Ingredient::Syrup(syrup) => write!(f, "{} syrup", syrup.name()),
+      Ingredient::Cup => write!(f, "cups"),
+      Ingredient::SodaBottle => write!(f, "soda"),
+      Ingredient::BeerBottle => write!(f, "beer"),
+    }
+  }
+}
+
+// the ingredients (and how much of each) that go into one drink
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recipe {
+  amounts: BTreeMap<Ingredient, u32>,
+}
+impl Recipe {
+  pub fn for_drink(item: &CustomDrink) -> Self {
+    let mut recipe = Recipe::default();
+    recipe.add(Ingredient::Cup, 1);
+
+    // milk drinks get bigger with the cup, espresso drinks get a shot more in a large
+    let (milk_ml, shots) = match item.drink() {
+      Drinks::Coffee(CoffeeFlavor::Latte) => (240, 2),
+      Drinks::Coffee(CoffeeFlavor::Capuchino) => (150, 2),
+      Drinks::Coffee(CoffeeFlavor::Mocha) => (200, 2),
+      Drinks::Coffee(CoffeeFlavor::Americano) => (30, 2),
+      _ => (0, 0),
+    };
+    let (milk_ml, shots) = match item.size() {
+      Size::Small => (milk_ml * 3 / 4, shots.min(1)),
+      Size::Medium => (milk_ml, shots),
+      Size::Large => (milk_ml * 3 / 2, shots + 1),
+    };
+    recipe.add(Ingredient::EspressoShot, shots + u32::from(item.extra_shots()));
+    if let Some(milk) = item.milk() {
+      recipe.add(Ingredient::Milk(milk), milk_ml);
+    }
+    if item.drink() == Drinks::Coffee(CoffeeFlavor::Mocha) {
+      recipe.add(Ingredient::Chocolate, 30);
+    }
+    for syrup in item.syrups() {
+      recipe.add(Ingredient::Syrup(*syrup), 2);
+    }
+    match item.drink() {
+      Drinks::Soda => recipe.add(Ingredient::SodaBottle, 1),
+      Drinks::Beer => recipe.add(Ingredient::BeerBottle, 1),
+      _ => {}
+    }
+    recipe
+  }
+
+  pub fn add(&mut self, ingredient: Ingredient, amount: u32) {
+    if amount > 0 {
+      let total = self.amounts.entry(ingredient).or_insert(0);
+      *total = total.saturating_add(amount);
+    }
+  }
+
+  pub fn amount(&self, ingredient: &Ingredient) -> u32 {
+    self.amounts.get(ingredient).copied().unwrap_or(0)
+  }
+
+  pub fn ingredients(&self) -> impl Iterator<Item = (Ingredient, u32)> + '_ {
+    self.amounts.iter().map(|(ingredient, amount)| (*ingredient, *amount))
+  }
+
+  // the same recipe made `times` over. amounts too big to count stop at u32::MAX, which is still
+  // more than any stock on hand, so the stock check turns them down instead of wrapping around
+  pub fn times(&self, times: u32) -> Self {
+    let mut total = Recipe::default();
+    for (ingredient, amount) in self.ingredients() {
+      total.add(ingredient, amount.saturating_mul(times));
+    }
+    total
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StockLedger {
+  on_hand: BTreeMap<Ingredient, u32>,
+}
+impl StockLedger {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // stock past u32::MAX stops there, which is more than any till will run through
+  pub fn receive(&mut self, ingredient: Ingredient, amount: u32) {
+    let on_hand = self.on_hand.entry(ingredient).or_insert(0);
+    *on_hand = on_hand.saturating_add(amount);
+  }
+
+  // the same, but None (and nothing received) when there'd be more than can be counted. hands
+  // back how much there is now
+  pub fn checked_receive(&mut self, ingredient: Ingredient, amount: u32) -> Option<u32> {
+    let total = self.on_hand(&ingredient).checked_add(amount)?;
+    self.on_hand.insert(ingredient, total);
+    Some(total)
+  }
+
+  pub fn on_hand(&self, ingredient: &Ingredient) -> u32 {
+    self.on_hand.get(ingredient).copied().unwrap_or(0)
+  }
+
+  pub fn levels(&self) -> impl Iterator<Item = (Ingredient, u32)> + '_ {
+    self.on_hand.iter().map(|(ingredient, amount)| (*ingredient, *amount))
+  }
+
+  // a stock count, one ingredient per line, in the ingredient's own unit:
+  //
+  //   cups = 200
+  //   whole milk = 8000
+  //   vanilla syrup = 60
+  pub fn from_config(text: &str) -> Result<Self, ConfigError> {
+    let mut stock = Self::new();
+    for section in config::parse(text)? {
+      for (name, amount, line) in &section.entries {
+        let error = |message: String| ConfigError::new(*line, message);
+        let ingredient =
+          Ingredient::from_name(name).ok_or_else(|| error(format!("unknown ingredient \"{}\"", name)))?;
+        let amount = amount.parse().map_err(|_| error(format!("bad amount of {} \"{}\"", ingredient, amount)))?;
+        stock.checked_receive(ingredient, amount).ok_or_else(|| error(format!("too much {}", ingredient)))?;
+      }
+    }
+    Ok(stock)
+  }
+
+  // whether the whole order could be made from what's on hand, without taking any of it
+  pub fn check(&self, order: &Order) -> Result<(), StockError> {
+    self.needed_for(order).map(drop)
+  }
+
+  // take a whole order out of stock, or - if any line can't be made - nothing at all
+  pub fn fulfill(&mut self, order: &Order) -> Result<(), StockError> {
+    let needed = self.needed_for(order)?;
+    for (ingredient, amount) in needed.ingredients() {
+      let on_hand = self.on_hand.get_mut(&ingredient).expect("checked by needed_for");
+      *on_hand -= amount;
+    }
+    Ok(())
+  }
+
+  // how many of this drink could be made from what's on hand right now
+  pub fn can_make(&self, item: &CustomDrink) -> u32 {
+    Recipe::for_drink(item)
+      .ingredients()
+      .map(|(ingredient, amount)| self.on_hand(&ingredient) / amount)
+      .min()
+      .unwrap_or(0)
+  }
+
+  // everything the order uses, once it's known there's enough of all of it.
+  // lines are checked in order, and each line only counts as short once the lines before it have
+  // had their share, so the error points at the first line that can't be made
+  fn needed_for(&self, order: &Order) -> Result<Recipe, StockError> {
+    let mut needed = Recipe::default();
+    for (number, line) in order.lines().iter().enumerate() {
+      let recipe = Recipe::for_drink(&line.item).times(line.quantity);
+      for (ingredient, amount) in recipe.ingredients() {
+        let wanted = needed.amount(&ingredient).saturating_add(amount);
+        let available = self.on_hand(&ingredient);
+        if wanted > available {
+          return Err(StockError {
+            line: number,
+            item: line.item.clone(),
+            ingredient,
+            needed: wanted,
+            available,
+          });
+        }
+      }
+      for (ingredient, amount) in recipe.ingredients() {
+        needed.add(ingredient, amount);
+      }
+    }
+    Ok(needed)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StockError {
+  // index of the order line that couldn't be made
+  pub line: usize,
+  pub item: CustomDrink,
+  pub ingredient: Ingredient,
+  // how much of the ingredient the order needs up to and including this line
+  pub needed: u32,
+  pub available: u32,
+}
+impl fmt::Display for StockError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "can't make line {} ({}): out of {} (needs {} {}, only {} left)",
+      self.line + 1,
+      self.item,
+      self.ingredient,
+      self.needed,
+      self.ingredient.unit(),
+      self.available
+    )
+  }
+}
+impl std::error::Error for StockError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const LATTE: Drinks = Drinks::Coffee(CoffeeFlavor::Latte);
+
+  fn stocked() -> StockLedger {
+    let mut stock = StockLedger::new();
+    stock.receive(Ingredient::Cup, 10);
+    stock.receive(Ingredient::EspressoShot, 10);
+    stock.receive(Ingredient::Milk(Milk::Whole), 500);
+    stock.receive(Ingredient::BeerBottle, 1);
+    stock
+  }
+
+  #[test]
+  fn fulfilling_an_order_uses_up_its_recipes() {
+    let mut stock = stocked();
+    let mut order = Order::new();
+    order.add(LATTE, 2).unwrap();
+    order.add(Drinks::Beer, 1).unwrap();
+
+    stock.fulfill(&order).unwrap();
+    assert_eq!(stock.on_hand(&Ingredient::Cup), 7);
+    assert_eq!(stock.on_hand(&Ingredient::EspressoShot), 6);
+    assert_eq!(stock.on_hand(&Ingredient::Milk(Milk::Whole)), 20);
+    assert_eq!(stock.on_hand(&Ingredient::BeerBottle), 0);
+  }
+
+  #[test]
+  fn short_orders_leave_stock_untouched() {
+    let mut stock = stocked();
+    let mut order = Order::new();
+    order.add(Drinks::Beer, 1).unwrap();
+    order.add(LATTE, 3).unwrap();
+
+    let err = stock.fulfill(&order).unwrap_err();
+    assert_eq!(err.line, 1);
+    assert_eq!(err.ingredient, Ingredient::Milk(Milk::Whole));
+    assert_eq!((err.needed, err.available), (720, 500));
+    assert_eq!(stock, stocked());
+  }
+
+  #[test]
+  fn reads_a_stock_count() {
+    let stock = StockLedger::from_config("# monday\ncups = 10\nEspresso = 10\nwhole milk = 500\nbeer = 1\n").unwrap();
+    assert_eq!(stock, stocked());
+    let err = StockLedger::from_config("cups = 10\nsugar = 4\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 2: unknown ingredient \"sugar\"");
+    assert!(StockLedger::from_config("cups = lots\n").is_err());
+    // a count given twice adds up, as far as it can
+    let err = StockLedger::from_config("[stock]\ncups = 4294967295\ncups = 1\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 3: too much cups");
+    let mut stock = stocked();
+    stock.receive(Ingredient::Cup, u32::MAX);
+    assert_eq!(stock.on_hand(&Ingredient::Cup), u32::MAX);
+    assert_eq!(stock.checked_receive(Ingredient::Cup, 1), None);
+  }
+
+  #[test]
+  fn counts_how_many_can_be_made() {
+    assert_eq!(stocked().can_make(&CustomDrink::from(LATTE)), 2);
+    assert_eq!(stocked().can_make(&CustomDrink::from(Drinks::Soda)), 0);
+  }
+}
@@ -0,0 +1,398 @@
+// just enough JSON to hand data to and take data from other programs, written by hand so the
+// package keeps building without fetching any crates. numbers are integers only - money goes out
+// in cents anyway, and a fraction anywhere else would be a mistake on the other end.
+
+use std::fmt::{self, Write};
+
+// how deeply arrays and objects may nest. parsing recurses once per level, so without a limit a
+// request body of nothing but "[" would run the stack out
+pub const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Json {
+  Null,
+  Bool(bool),
+  Number(i64),
+  String(String),
+  Array(Vec<Json>),
+  // kept as a list so keys come out in the order they were added
+  Object(Vec<(String, Json)>),
+}
+impl Json {
+  pub fn string(s: impl Into<String>) -> Self {
+    Json::String(s.into())
+  }
+
+  // start an object and add fields to it with `field`
+  pub fn object() -> Self {
+    Json::Object(Vec::new())
+  }
+
+  // add a field to an object. panics if this isn't an object, which is always a bug
+  pub fn field(mut self, key: impl Into<String>, value: impl Into<Json>) -> Self {
+    match &mut self {
+      Json::Object(fields) => fields.push((key.into(), value.into())),
+      other => panic!("can't add a field to {}", other),
+    }
+    self
+  }
+
+  pub fn parse(text: &str) -> Result<Json, JsonError> {
+    let mut parser = Parser { chars: text.char_indices().peekable(), text };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+      None => Ok(value),
+      Some((at, _)) => Err(JsonError { at, message: "unexpected text after the value" }),
+    }
+  }
+
+  // a field of an object. None for missing fields and for anything that isn't an object
+  pub fn get(&self, key: &str) -> Option<&Json> {
+    match self {
+      Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Json::String(s) => Some(s),
+      _ => None,
+    }
+  }
+
+  pub fn as_i64(&self) -> Option<i64> {
+    match self {
+      Json::Number(n) => Some(*n),
+      _ => None,
+    }
+  }
+
+  pub fn as_array(&self) -> Option<&[Json]> {
+    match self {
+      Json::Array(items) => Some(items),
+      _ => None,
+    }
+  }
+
+  // indented two spaces per level, for people to read
+  pub fn pretty(&self) -> String {
+    let mut out = String::new();
+    self.write_pretty(&mut out, 0).expect("writing to a String can't fail");
+    out
+  }
+
+  fn write_pretty(&self, out: &mut String, depth: usize) -> fmt::Result {
+    let indent = |depth: usize| "  ".repeat(depth);
+    match self {
+      Json::Array(items) if !items.is_empty() => {
+        out.push_str("[\n");
+        for (i, item) in items.iter().enumerate() {
+          out.push_str(&indent(depth + 1));
+          item.write_pretty(out, depth + 1)?;
+          out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+        }
+        write!(out, "{}]", indent(depth))
+      }
+      Json::Object(fields) if !fields.is_empty() => {
+        out.push_str("{\n");
+        for (i, (key, value)) in fields.iter().enumerate() {
+          write!(out, "{}", indent(depth + 1))?;
+          write_string(out, key)?;
+          out.push_str(": ");
+          value.write_pretty(out, depth + 1)?;
+          out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+        }
+        write!(out, "{}}}", indent(depth))
+      }
+      other => write!(out, "{}", other),
+    }
+  }
+}
+
+// compact, all on one line
+impl fmt::Display for Json {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Json::Null => f.write_str("null"),
+      Json::Bool(b) => write!(f, "{}", b),
+      Json::Number(n) => write!(f, "{}", n),
+      Json::String(s) => write_string(f, s),
+      Json::Array(items) => {
+        f.write_char('[')?;
+        for (i, item) in items.iter().enumerate() {
+          if i > 0 {
+            f.write_char(',')?;
+          }
+          write!(f, "{}", item)?;
+        }
+        f.write_char(']')
+      }
+      Json::Object(fields) => {
+        f.write_char('{')?;
+        for (i, (key, value)) in fields.iter().enumerate() {
+          if i > 0 {
+            f.write_char(',')?;
+          }
+          write_string(f, key)?;
+          write!(f, ":{}", value)?;
+        }
+        f.write_char('}')
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+  // byte offset into the text
+  pub at: usize,
+  pub message: &'static str,
+}
+impl fmt::Display for JsonError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "bad JSON at byte {}: {}", self.at, self.message)
+  }
+}
+impl std::error::Error for JsonError {}
+
+struct Parser<'a> {
+  chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+  text: &'a str,
+}
+impl Parser<'_> {
+  fn error(&mut self, message: &'static str) -> JsonError {
+    let at = self.chars.peek().map_or(self.text.len(), |(at, _)| *at);
+    JsonError { at, message }
+  }
+
+  fn skip_whitespace(&mut self) {
+    while self.chars.next_if(|(_, c)| c.is_ascii_whitespace()).is_some() {}
+  }
+
+  fn expect(&mut self, wanted: char, message: &'static str) -> Result<(), JsonError> {
+    match self.chars.peek() {
+      Some((_, c)) if *c == wanted => {
+        self.chars.next();
+        Ok(())
+      }
+      _ => Err(self.error(message)),
+    }
+  }
+
+  fn keyword(&mut self, word: &'static str, value: Json) -> Result<Json, JsonError> {
+    for wanted in word.chars() {
+      self.expect(wanted, "unknown word")?;
+    }
+    Ok(value)
+  }
+
+  // `depth` is how many arrays and objects this value is inside
+  fn value(&mut self, depth: usize) -> Result<Json, JsonError> {
+    self.skip_whitespace();
+    match self.chars.peek().map(|(_, c)| *c) {
+      Some('n') => self.keyword("null", Json::Null),
+      Some('t') => self.keyword("true", Json::Bool(true)),
+      Some('f') => self.keyword("false", Json::Bool(false)),
+      Some('"') => self.string().map(Json::String),
+      Some('-' | '0'..='9') => self.number(),
+      Some('[' | '{') if depth >= MAX_DEPTH => Err(self.error("nested too deeply")),
+      Some('[') => self.array(depth + 1),
+      Some('{') => self.object(depth + 1),
+      Some(_) => Err(self.error("expected a value")),
+      None => Err(self.error("unexpected end of text")),
+    }
+  }
+
+  fn array(&mut self, depth: usize) -> Result<Json, JsonError> {
+    self.chars.next();
+    let mut items = Vec::new();
+    self.skip_whitespace();
+    if self.chars.next_if(|(_, c)| *c == ']').is_some() {
+      return Ok(Json::Array(items));
+    }
+    loop {
+      items.push(self.value(depth)?);
+      self.skip_whitespace();
+      match self.chars.next() {
+        Some((_, ',')) => {}
+        Some((_, ']')) => return Ok(Json::Array(items)),
+        _ => return Err(self.error("expected , or ] in array")),
+      }
+    }
+  }
+
+  fn object(&mut self, depth: usize) -> Result<Json, JsonError> {
+    self.chars.next();
+    let mut fields = Vec::new();
+    self.skip_whitespace();
+    if self.chars.next_if(|(_, c)| *c == '}').is_some() {
+      return Ok(Json::Object(fields));
+    }
+    loop {
+      self.skip_whitespace();
+      let key = self.string()?;
+      self.skip_whitespace();
+      self.expect(':', "expected : after object key")?;
+      fields.push((key, self.value(depth)?));
+      self.skip_whitespace();
+      match self.chars.next() {
+        Some((_, ',')) => {}
+        Some((_, '}')) => return Ok(Json::Object(fields)),
+        _ => return Err(self.error("expected , or } in object")),
+      }
+    }
+  }
+
+  fn number(&mut self) -> Result<Json, JsonError> {
+    let mut digits = String::new();
+    if self.chars.next_if(|(_, c)| *c == '-').is_some() {
+      digits.push('-');
+    }
+    while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_digit()) {
+      digits.push(c);
+    }
+    if let Some((_, '.' | 'e' | 'E')) = self.chars.peek() {
+      return Err(self.error("only whole numbers are supported"));
+    }
+    digits.parse().map(Json::Number).map_err(|_| self.error("bad number"))
+  }
+
+  fn string(&mut self) -> Result<String, JsonError> {
+    self.expect('"', "expected a string")?;
+    let mut out = String::new();
+    loop {
+      match self.chars.next() {
+        Some((_, '"')) => return Ok(out),
+        Some((_, '\\')) => match self.chars.next() {
+          Some((_, '"')) => out.push('"'),
+          Some((_, '\\')) => out.push('\\'),
+          Some((_, '/')) => out.push('/'),
+          Some((_, 'n')) => out.push('\n'),
+          Some((_, 'r')) => out.push('\r'),
+          Some((_, 't')) => out.push('\t'),
+          Some((_, 'b')) => out.push('\u{8}'),
+          Some((_, 'f')) => out.push('\u{c}'),
+          Some((_, 'u')) => {
+            let mut code = 0;
+            for _ in 0..4 {
+              let digit = self.chars.next().and_then(|(_, c)| c.to_digit(16));
+              code = code * 16 + digit.ok_or_else(|| self.error("bad \\u escape"))?;
+            }
+            // surrogate pairs aren't worth the trouble here
+            out.push(char::from_u32(code).ok_or_else(|| self.error("unsupported \\u escape"))?);
+          }
+          _ => return Err(self.error("bad escape")),
+        },
+        Some((_, c)) if u32::from(c) < 0x20 => return Err(self.error("control character in string")),
+        Some((_, c)) => out.push(c),
+        None => return Err(self.error("unterminated string")),
+      }
+    }
+  }
+}
+
+fn write_string(out: &mut impl Write, s: &str) -> fmt::Result {
+  out.write_char('"')?;
+  for c in s.chars() {
+    match c {
+      '"' => out.write_str("\\\"")?,
+      '\\' => out.write_str("\\\\")?,
+      '\n' => out.write_str("\\n")?,
+      '\r' => out.write_str("\\r")?,
+      '\t' => out.write_str("\\t")?,
+      c if u32::from(c) < 0x20 => write!(out, "\\u{:04x}", u32::from(c))?,
+      c => out.write_char(c)?,
+    }
+  }
+  out.write_char('"')
+}
+
+impl From<bool> for Json {
+  fn from(b: bool) -> Self {
+    Json::Bool(b)
+  }
+}
+impl From<i64> for Json {
+  fn from(n: i64) -> Self {
+    Json::Number(n)
+  }
+}
+impl From<u32> for Json {
+  fn from(n: u32) -> Self {
+    Json::Number(i64::from(n))
+  }
+}
+impl From<&str> for Json {
+  fn from(s: &str) -> Self {
+    Json::string(s)
+  }
+}
+impl From<String> for Json {
+  fn from(s: String) -> Self {
+    Json::String(s)
+  }
+}
+impl<T: Into<Json>> From<Vec<T>> for Json {
+  fn from(items: Vec<T>) -> Self {
+    Json::Array(items.into_iter().map(Into::into).collect())
+  }
+}
+impl<T: Into<Json>> From<Option<T>> for Json {
+  fn from(value: Option<T>) -> Self {
+    value.map_or(Json::Null, Into::into)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn writes_compact_json() {
+    let json = Json::object()
+      .field("name", "Latte \"large\"")
+      .field("quantity", 2u32)
+      .field("modifiers", vec!["oat milk"])
+      .field("note", Option::<String>::None);
+    assert_eq!(
+      json.to_string(),
+      r#"{"name":"Latte \"large\"","quantity":2,"modifiers":["oat milk"],"note":null}"#
+    );
+  }
+
+  #[test]
+  fn parses_what_it_writes() {
+    let json = Json::object()
+      .field("name", "tab\there \"quoted\" \u{e9}")
+      .field("n", -12i64)
+      .field("list", vec![Json::Null, Json::Bool(true), Json::object()]);
+    assert_eq!(Json::parse(&json.to_string()), Ok(json.clone()));
+    assert_eq!(Json::parse(&json.pretty()), Ok(json));
+  }
+
+  #[test]
+  fn parse_reports_where_it_went_wrong() {
+    assert_eq!(Json::parse("[1, 2").unwrap_err().message, "expected , or ] in array");
+    assert_eq!(Json::parse("{\"price\": 4.5}").unwrap_err().message, "only whole numbers are supported");
+    assert_eq!(Json::parse("{} x").unwrap_err().at, 3);
+    assert_eq!(Json::parse("\"\\u00e9\"").unwrap(), Json::string("\u{e9}"));
+  }
+
+  #[test]
+  fn turns_away_deep_nesting() {
+    let deepest = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+    assert!(Json::parse(&deepest).is_ok());
+    let too_deep = format!("{}{}", "[".repeat(MAX_DEPTH + 1), "]".repeat(MAX_DEPTH + 1));
+    assert_eq!(Json::parse(&too_deep).unwrap_err(), JsonError { at: MAX_DEPTH, message: "nested too deeply" });
+    // a body of nothing but brackets stops at the limit instead of overflowing the stack
+    assert_eq!(Json::parse(&"[{\"a\":".repeat(30_000)).unwrap_err().message, "nested too deeply");
+  }
+
+  #[test]
+  fn writes_pretty_json() {
+    let json = Json::object().field("lines", vec![1i64, 2]).field("empty", Vec::<Json>::new());
+    assert_eq!(json.pretty(), "{\n  \"lines\": [\n    1,\n    2\n  ],\n  \"empty\": []\n}");
+  }
+}
@@ -0,0 +1,704 @@
+// the sales ledger: every checked-out order appended to a CSV file, and read back for reports.
+//
+// the first row says what the file is and which version of the layout it uses, so a newer
+// build can still read old files. after that every row starts with its kind:
+//
+//   test-package-ledger,5
+//   order,<id>,<timestamp>,<subtotal cents>,<tax cents>,<total cents>
+//   line,<order id>,<quantity>,<drink>,<coffee flavor>,<size>,<modifiers>,<unit cents>,<total cents>
+//   produce,<order id>,<item>,<variety>,<units|grams>,<count or weight>,<cents each or per kg>,<total cents>
+//   discount,<order id>,<promotion>,<amount cents>
+//   payment,<order id>,<payer>,<amount cents>,<tip cents>
+//
+// the rows of an order come right after its order row. modifiers are joined with "; ". the
+// subtotal is before discounts, and tips are only ever on payment rows so they can't be mistaken
+// for revenue. only pickles have a variety, kept as it was typed so punctuation survives. version 1
+// files have no discount or payment rows, version 2 no payment rows and version 3 no produce rows.
+// version 4 produce rows have no variety column, and are still read in later files.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::custom::Size;
+use crate::date::{Date, Timestamp};
+use crate::felicity_farmstand::{Measure, ProduceLine, Vegetables};
+use crate::money::Money;
+use crate::pricing::Bill;
+use crate::promo::Discount;
+use crate::split::Share;
+use crate::{CoffeeFlavor, Drinks};
+
+const MAGIC: &str = "test-package-ledger";
+pub const LEDGER_VERSION: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaleLine {
+  pub quantity: u32,
+  pub drink: Drinks,
+  pub size: Size,
+  pub modifiers: Vec<String>,
+  pub unit_price: Money,
+  pub total: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaleRecord {
+  pub id: u64,
+  pub timestamp: Timestamp,
+  pub lines: Vec<SaleLine>,
+  pub produce: Vec<ProduceLine>,
+  pub discounts: Vec<Discount>,
+  // who paid what. empty for sales recorded before payers were
+  pub payments: Vec<Share>,
+  pub subtotal: Money,
+  pub tax: Money,
+  pub total: Money,
+}
+impl SaleRecord {
+  pub fn from_bill(id: u64, timestamp: Timestamp, bill: &Bill) -> Self {
+    let lines = bill
+      .lines
+      .iter()
+      .map(|line| SaleLine {
+        quantity: line.quantity,
+        drink: line.item.drink(),
+        size: line.item.size(),
+        modifiers: line.item.modifiers(),
+        unit_price: line.unit_price,
+        total: line.total,
+      })
+      .collect();
+    Self {
+      id,
+      timestamp,
+      lines,
+      produce: bill.produce.clone(),
+      discounts: bill.discounts.clone(),
+      payments: Vec::new(),
+      subtotal: bill.subtotal,
+      tax: bill.tax,
+      total: bill.total,
+    }
+  }
+
+  pub fn with_payments(mut self, payments: &[Share]) -> Self {
+    self.payments = payments.to_vec();
+    self
+  }
+
+  pub fn discount_total(&self) -> Money {
+    self.discounts.iter().map(|discount| discount.amount).sum()
+  }
+
+  pub fn tip_total(&self) -> Money {
+    self.payments.iter().map(|payment| payment.tip).sum()
+  }
+}
+
+pub struct Ledger {
+  path: PathBuf,
+  next_id: u64,
+  // the layout the file on disk says it uses
+  version: u32,
+}
+impl Ledger {
+  // open (or get ready to create) the ledger at `path`. an existing file is read through once to
+  // check it's a ledger we understand and to find the next order id
+  pub fn open(path: impl AsRef<Path>) -> Result<Self, LedgerError> {
+    let path = path.as_ref().to_path_buf();
+    let (version, next_id) = match File::open(&path) {
+      Ok(_) => {
+        let (version, sales) = read_records(&path)?;
+        (version.unwrap_or(LEDGER_VERSION), sales.iter().map(|sale| sale.id).max().unwrap_or(0) + 1)
+      }
+      Err(e) if e.kind() == io::ErrorKind::NotFound => (LEDGER_VERSION, 1),
+      Err(e) => return Err(LedgerError::Io(e)),
+    };
+    Ok(Self { path, next_id, version })
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  // write a sale and how it was paid to the end of the ledger, handing back the order id it was
+  // given
+  pub fn record(&mut self, timestamp: Timestamp, bill: &Bill, payments: &[Share]) -> Result<u64, LedgerError> {
+    let sale = SaleRecord::from_bill(self.next_id, timestamp, bill).with_payments(payments);
+    if self.version < LEDGER_VERSION {
+      self.upgrade()?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+    // a brand new (or empty) file needs its header before anything else
+    if file.metadata()?.len() == 0 {
+      writeln!(file, "{}", csv_row(&[MAGIC.to_string(), LEDGER_VERSION.to_string()]))?;
+    }
+    // build the whole order first so it lands in a single write
+    let mut rows = csv_row(&[
+      "order".to_string(),
+      sale.id.to_string(),
+      sale.timestamp.to_string(),
+      sale.subtotal.cents().to_string(),
+      sale.tax.cents().to_string(),
+      sale.total.cents().to_string(),
+    ]);
+    rows.push('\n');
+    for line in &sale.lines {
+      let (drink, flavor) = match line.drink {
+        Drinks::Coffee(flavor) => ("coffee", flavor.name()),
+        other => (other.name(), ""),
+      };
+      rows.push_str(&csv_row(&[
+        "line".to_string(),
+        sale.id.to_string(),
+        line.quantity.to_string(),
+        drink.to_lowercase(),
+        flavor.to_lowercase(),
+        line.size.name().to_string(),
+        line.modifiers.join("; "),
+        line.unit_price.cents().to_string(),
+        line.total.cents().to_string(),
+      ]));
+      rows.push('\n');
+    }
+    for line in &sale.produce {
+      let (measure, amount) = match line.measure {
+        Measure::Units(count) => ("units", count),
+        Measure::Grams(grams) => ("grams", grams),
+      };
+      let (item, variety) = match &line.item {
+        Vegetables::Pickle(variety) => (String::from("pickles"), variety.clone()),
+        other => (other.name(), String::new()),
+      };
+      rows.push_str(&csv_row(&[
+        "produce".to_string(),
+        sale.id.to_string(),
+        item,
+        variety,
+        measure.to_string(),
+        amount.to_string(),
+        line.rate.cents().to_string(),
+        line.total.cents().to_string(),
+      ]));
+      rows.push('\n');
+    }
+    for discount in &sale.discounts {
+      rows.push_str(&csv_row(&[
+        "discount".to_string(),
+        sale.id.to_string(),
+        discount.name.clone(),
+        discount.amount.cents().to_string(),
+      ]));
+      rows.push('\n');
+    }
+    for payment in &sale.payments {
+      rows.push_str(&csv_row(&[
+        "payment".to_string(),
+        sale.id.to_string(),
+        payment.payer.to_string(),
+        payment.amount.cents().to_string(),
+        payment.tip.cents().to_string(),
+      ]));
+      rows.push('\n');
+    }
+    file.write_all(rows.as_bytes())?;
+    self.next_id += 1;
+    Ok(sale.id)
+  }
+
+  // every older layout is a subset of the current one, so an old file only needs a new header
+  // before rows in the current layout can go on the end of it. written to a temporary file first,
+  // so a crash can't leave the ledger cut short
+  fn upgrade(&mut self) -> Result<(), LedgerError> {
+    let text = fs::read_to_string(&self.path)?;
+    let rest = text.split_once('\n').map_or("", |(_, rest)| rest);
+    let header = csv_row(&[MAGIC.to_string(), LEDGER_VERSION.to_string()]);
+    let temporary = self.path.with_extension("tmp");
+    fs::write(&temporary, format!("{}\n{}", header, rest))?;
+    fs::rename(&temporary, &self.path)?;
+    self.version = LEDGER_VERSION;
+    Ok(())
+  }
+
+  pub fn read_all(&self) -> Result<Vec<SaleRecord>, LedgerError> {
+    match File::open(&self.path) {
+      Ok(_) => read_records(&self.path).map(|(_, sales)| sales),
+      Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+      Err(e) => Err(LedgerError::Io(e)),
+    }
+  }
+
+  // every sale made on a day from `from` to `to`, both included
+  pub fn read_between(&self, from: Date, to: Date) -> Result<Vec<SaleRecord>, LedgerError> {
+    let mut sales = self.read_all()?;
+    sales.retain(|sale| (from..=to).contains(&sale.timestamp.date()));
+    Ok(sales)
+  }
+}
+
+// the version in the header (None for an empty file) and every sale in the file
+fn read_records(path: &Path) -> Result<(Option<u32>, Vec<SaleRecord>), LedgerError> {
+  let reader = BufReader::new(File::open(path)?);
+  let mut sales: Vec<SaleRecord> = Vec::new();
+  let mut version = None;
+  for (index, line) in reader.lines().enumerate() {
+    let line = line?;
+    let row = index + 1;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let fields = parse_csv_row(&line).ok_or(LedgerError::Malformed { row, reason: "unterminated quote" })?;
+    let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+
+    if version.is_none() {
+      version = Some(match fields.as_slice() {
+        [MAGIC, v] => match v.parse::<u32>() {
+          Ok(v) if (1..=LEDGER_VERSION).contains(&v) => v,
+          Ok(v) => return Err(LedgerError::UnsupportedVersion(v)),
+          Err(_) => return Err(LedgerError::Malformed { row, reason: "bad version number" }),
+        },
+        _ => return Err(LedgerError::NotALedger),
+      });
+      continue;
+    }
+
+    let malformed = |reason| LedgerError::Malformed { row, reason };
+    let number = |s: &str| s.parse::<i64>().map_err(|_| malformed("bad number"));
+    let money = |s: &str| number(s).map(Money::from_cents);
+    match fields.as_slice() {
+      ["order", id, timestamp, subtotal, tax, total] => sales.push(SaleRecord {
+        id: id.parse().map_err(|_| malformed("bad order id"))?,
+        timestamp: timestamp.parse().map_err(|_| malformed("bad timestamp"))?,
+        lines: Vec::new(),
+        produce: Vec::new(),
+        discounts: Vec::new(),
+        payments: Vec::new(),
+        subtotal: money(subtotal)?,
+        tax: money(tax)?,
+        total: money(total)?,
+      }),
+      ["line", id, quantity, drink, flavor, size, modifiers, unit_price, total] => {
+        let sale = match sales.last_mut() {
+          Some(sale) if sale.id.to_string() == *id => sale,
+          _ => return Err(malformed("line doesn't follow its order")),
+        };
+        let drink = match *drink {
+          "coffee" => Drinks::Coffee(flavor.parse::<CoffeeFlavor>().map_err(|_| malformed("unknown coffee"))?),
+          other => other.parse().map_err(|_| malformed("unknown drink"))?,
+        };
+        sale.lines.push(SaleLine {
+          quantity: quantity.parse().map_err(|_| malformed("bad quantity"))?,
+          drink,
+          size: Size::from_name(size).ok_or_else(|| malformed("unknown size"))?,
+          modifiers: modifiers.split("; ").filter(|m| !m.is_empty()).map(String::from).collect(),
+          unit_price: money(unit_price)?,
+          total: money(total)?,
+        });
+      }
+      ["produce", id, item, measure, amount, rate, total] if version >= Some(4) => {
+        let sale = match sales.last_mut() {
+          Some(sale) if sale.id.to_string() == *id => sale,
+          _ => return Err(malformed("produce doesn't follow its order")),
+        };
+        let amount = amount.parse().map_err(|_| malformed("bad amount"))?;
+        sale.produce.push(ProduceLine {
+          item: Vegetables::from_name(item).ok_or_else(|| malformed("unknown produce"))?,
+          measure: match *measure {
+            "units" => Measure::Units(amount),
+            "grams" => Measure::Grams(amount),
+            _ => return Err(malformed("produce is sold in units or grams")),
+          },
+          rate: money(rate)?,
+          total: money(total)?,
+        });
+      }
+      ["produce", id, item, variety, measure, amount, rate, total] if version >= Some(5) => {
+        let sale = match sales.last_mut() {
+          Some(sale) if sale.id.to_string() == *id => sale,
+          _ => return Err(malformed("produce doesn't follow its order")),
+        };
+        let amount = amount.parse().map_err(|_| malformed("bad amount"))?;
+        let item = match (Vegetables::from_name(item), variety.is_empty()) {
+          // from_name wants a variety, which is in its own column
+          (None, false) if item.eq_ignore_ascii_case("pickles") => Vegetables::pickle(variety),
+          (Some(item), true) => item,
+          _ => return Err(malformed("unknown produce")),
+        };
+        sale.produce.push(ProduceLine {
+          item,
+          measure: match *measure {
+            "units" => Measure::Units(amount),
+            "grams" => Measure::Grams(amount),
+            _ => return Err(malformed("produce is sold in units or grams")),
+          },
+          rate: money(rate)?,
+          total: money(total)?,
+        });
+      }
+      ["discount", id, name, amount] if version >= Some(2) => {
+        let sale = match sales.last_mut() {
+          Some(sale) if sale.id.to_string() == *id => sale,
+          _ => return Err(malformed("discount doesn't follow its order")),
+        };
+        sale.discounts.push(Discount {
+          name: name.to_string(),
+          amount: money(amount)?,
+        });
+      }
+      ["payment", id, payer, amount, tip] if version >= Some(3) => {
+        let sale = match sales.last_mut() {
+          Some(sale) if sale.id.to_string() == *id => sale,
+          _ => return Err(malformed("payment doesn't follow its order")),
+        };
+        sale.payments.push(Share {
+          payer: payer.parse().map_err(|_| malformed("bad payer"))?,
+          amount: money(amount)?,
+          tip: money(tip)?,
+        });
+      }
+      _ => return Err(malformed("unknown kind of row")),
+    }
+  }
+  Ok((version, sales))
+}
+
+// fields with commas, quotes or newlines are quoted, with quotes doubled
+pub(crate) fn csv_row(fields: &[String]) -> String {
+  let quoted: Vec<String> = fields
+    .iter()
+    .map(|field| {
+      if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+      } else {
+        field.clone()
+      }
+    })
+    .collect();
+  quoted.join(",")
+}
+
+// None if a quote is left open
+pub(crate) fn parse_csv_row(line: &str) -> Option<Vec<String>> {
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut in_quotes = false;
+  let mut chars = line.chars().peekable();
+  while let Some(c) = chars.next() {
+    match (c, in_quotes) {
+      ('"', true) if chars.peek() == Some(&'"') => {
+        field.push('"');
+        chars.next();
+      }
+      ('"', true) => in_quotes = false,
+      ('"', false) if field.is_empty() => in_quotes = true,
+      (',', false) => fields.push(std::mem::take(&mut field)),
+      (c, _) => field.push(c),
+    }
+  }
+  if in_quotes {
+    return None;
+  }
+  fields.push(field);
+  Some(fields)
+}
+
+#[derive(Debug)]
+pub enum LedgerError {
+  Io(io::Error),
+  // the first row isn't a ledger header
+  NotALedger,
+  // written by a newer version than this one knows how to read
+  UnsupportedVersion(u32),
+  // row numbers start at 1, counting the header
+  Malformed { row: usize, reason: &'static str },
+}
+impl fmt::Display for LedgerError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      LedgerError::Io(e) => write!(f, "couldn't use the ledger file: {}", e),
+      LedgerError::NotALedger => write!(f, "this file is not a sales ledger"),
+      LedgerError::UnsupportedVersion(v) => {
+        write!(f, "ledger version {} is newer than this program (reads up to {})", v, LEDGER_VERSION)
+      }
+      LedgerError::Malformed { row, reason } => write!(f, "ledger row {}: {}", row, reason),
+    }
+  }
+}
+impl std::error::Error for LedgerError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      LedgerError::Io(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+impl From<io::Error> for LedgerError {
+  fn from(e: io::Error) -> Self {
+    LedgerError::Io(e)
+  }
+}
+
+// what the sales in a ledger add up to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SalesReport {
+  pub from: Date,
+  pub to: Date,
+  pub orders: usize,
+  // after discounts, before tax
+  pub revenue: Money,
+  // the part of the revenue taken at the farm stand
+  pub produce: Money,
+  pub discounts: Money,
+  pub tax: Money,
+  // kept apart from revenue: they belong to the staff
+  pub tips: Money,
+  // units sold of each drink, with all coffees counted together
+  pub per_drink: BTreeMap<String, u32>,
+  pub per_coffee_flavor: BTreeMap<String, u32>,
+  // every drink (coffees by flavor) with units sold, best selling first
+  pub best_sellers: Vec<(Drinks, u32)>,
+}
+impl SalesReport {
+  pub fn new(from: Date, to: Date, sales: &[SaleRecord]) -> Self {
+    let sales: Vec<&SaleRecord> = sales
+      .iter()
+      .filter(|sale| (from..=to).contains(&sale.timestamp.date()))
+      .collect();
+    let mut per_drink = BTreeMap::new();
+    let mut per_coffee_flavor = BTreeMap::new();
+    let mut units: Vec<(Drinks, u32)> = Vec::new();
+    for line in sales.iter().flat_map(|sale| &sale.lines) {
+      let category = match line.drink {
+        Drinks::Coffee(flavor) => {
+          *per_coffee_flavor.entry(flavor.name().to_string()).or_insert(0) += line.quantity;
+          "Coffee"
+        }
+        other => other.name(),
+      };
+      *per_drink.entry(category.to_string()).or_insert(0) += line.quantity;
+      match units.iter_mut().find(|(drink, _)| *drink == line.drink) {
+        Some((_, count)) => *count += line.quantity,
+        None => units.push((line.drink, line.quantity)),
+      }
+    }
+    // most sold first, ties in menu order
+    let menu = Drinks::all();
+    let menu_position = |drink: &Drinks| menu.iter().position(|d| d == drink);
+    units.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(menu_position(a).cmp(&menu_position(b))));
+
+    Self {
+      from,
+      to,
+      orders: sales.len(),
+      revenue: sales.iter().map(|sale| sale.subtotal - sale.discount_total()).sum(),
+      produce: sales.iter().flat_map(|sale| &sale.produce).map(|line| line.total).sum(),
+      discounts: sales.iter().map(|sale| sale.discount_total()).sum(),
+      tax: sales.iter().map(|sale| sale.tax).sum(),
+      tips: sales.iter().map(|sale| sale.tip_total()).sum(),
+      per_drink,
+      per_coffee_flavor,
+      best_sellers: units,
+    }
+  }
+
+  // revenue per order, to the nearest cent. zero when nothing was sold
+  pub fn average_order_value(&self) -> Money {
+    if self.orders == 0 {
+      return Money::ZERO;
+    }
+    let orders = self.orders as i64;
+    Money::from_cents((self.revenue.cents() * 2 + orders) / (orders * 2))
+  }
+}
+impl fmt::Display for SalesReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.from == self.to {
+      writeln!(f, "sales for {}", self.from)?;
+    } else {
+      writeln!(f, "sales from {} to {}", self.from, self.to)?;
+    }
+    writeln!(f, "{:<22}{}", "orders:", self.orders)?;
+    writeln!(f, "{:<22}{}", "revenue (before tax):", self.revenue)?;
+    if !self.produce.is_zero() {
+      writeln!(f, "{:<22}{}", "  at the farm stand:", self.produce)?;
+    }
+    writeln!(f, "{:<22}{}", "discounts given:", self.discounts)?;
+    writeln!(f, "{:<22}{}", "tax collected:", self.tax)?;
+    writeln!(f, "{:<22}{}", "tips:", self.tips)?;
+    writeln!(f, "{:<22}{}", "average order:", self.average_order_value())?;
+    writeln!(f, "\nper drink:")?;
+    for (drink, count) in &self.per_drink {
+      writeln!(f, "  {:<12}{:>6}", drink, count)?;
+    }
+    if !self.per_coffee_flavor.is_empty() {
+      writeln!(f, "\nper coffee flavor:")?;
+      for (flavor, count) in &self.per_coffee_flavor {
+        writeln!(f, "  {:<12}{:>6}", flavor, count)?;
+      }
+    }
+    if !self.best_sellers.is_empty() {
+      writeln!(f, "\nbest sellers:")?;
+      for (rank, (drink, count)) in self.best_sellers.iter().take(3).enumerate() {
+        writeln!(f, "  {}. {} ({})", rank + 1, drink, count)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::custom::{CustomDrink, Milk};
+  use crate::felicity_farmstand::{Farmstand, Pricing};
+  use crate::order::Order;
+  use crate::pricing::PriceTable;
+  use crate::promo::{Occasion, Promotions};
+  use crate::split::{split, Split, Tip};
+  use std::env;
+  use std::fs;
+
+  fn temp_ledger(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("test-package-{}-{}.csv", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+  }
+
+  fn at(day: u32, hour: u32) -> Timestamp {
+    Timestamp::from_date_time(Date::new(2024, 5, day).unwrap(), hour, 0, 0)
+  }
+
+  #[test]
+  fn sales_survive_a_round_trip() {
+    let path = temp_ledger("round-trip");
+    let latte = CustomDrink::builder(Drinks::Coffee(CoffeeFlavor::Capuchino)).milk(Milk::Oat).build().unwrap();
+    let mut order = Order::new();
+    order.add(latte, 2).unwrap();
+    order.add(Drinks::Beer, 1).unwrap();
+    let mut stand = Farmstand::standard();
+    order.add_produce(stand.weigh(&Vegetables::Asparagus, 380).unwrap()).unwrap();
+    order.add_produce(stand.sell(&Vegetables::pickle("spicy garlic"), 1).unwrap()).unwrap();
+    let bread_and_butter = Vegetables::pickle("Bread & Butter");
+    stand.set_price(bread_and_butter.clone(), Pricing::PerUnit(Money::from_cents(800)));
+    order.add_produce(stand.sell(&bread_and_butter, 1).unwrap()).unwrap();
+    let bill = PriceTable::standard().price(&order).unwrap();
+
+    let mut ledger = Ledger::open(&path).unwrap();
+    assert_eq!(ledger.record(at(1, 9), &bill, &[]).unwrap(), 1);
+    // reopening picks up where the file left off
+    let mut ledger = Ledger::open(&path).unwrap();
+    assert_eq!(ledger.record(at(2, 9), &bill, &[]).unwrap(), 2);
+
+    let sales = ledger.read_all().unwrap();
+    assert_eq!(sales, vec![SaleRecord::from_bill(1, at(1, 9), &bill), SaleRecord::from_bill(2, at(2, 9), &bill)]);
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("test-package-ledger,5\n"));
+    assert!(text.contains("produce,1,pickles,bread & butter,units,1,800,800\n"));
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn keeps_discounts_and_reads_version_1_files() {
+    let path = temp_ledger("discounts");
+    let promotions =
+      Promotions::from_config("[soda deal]\nkind = nth_item\nitems = soda\nnth = 1\namount_off = 0.25").unwrap();
+    let mut order = Order::new();
+    order.add(Drinks::Soda, 2).unwrap();
+    let bill = PriceTable::standard().price_with_promotions(&order, &promotions, &Occasion::at(at(1, 9))).unwrap();
+    let mut ledger = Ledger::open(&path).unwrap();
+    ledger.record(at(1, 9), &bill, &[]).unwrap();
+    let sales = ledger.read_all().unwrap();
+    assert_eq!(sales[0].discount_total(), Money::from_cents(50));
+    let report = SalesReport::new(at(1, 9).date(), at(1, 9).date(), &sales);
+    assert_eq!(report.revenue, Money::from_cents(400));
+    assert_eq!(report.discounts, Money::from_cents(50));
+
+    // written before discounts existed: same rows, older header
+    let version_1 = "test-package-ledger,1\norder,1,2024-05-01T09:00:00Z,225,19,244\nline,1,1,soda,,medium,,225,225\n";
+    fs::write(&path, version_1).unwrap();
+    let mut ledger = Ledger::open(&path).unwrap();
+    assert_eq!(ledger.read_all().unwrap()[0].total, Money::from_cents(244));
+    // adding to it brings the header up to date, so the new rows can be read back
+    assert_eq!(ledger.record(at(1, 10), &bill, &[]).unwrap(), 2);
+    assert_eq!(ledger.read_all().unwrap()[1].discounts, bill.discounts);
+    assert!(!path.with_extension("tmp").exists());
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn reads_produce_written_before_varieties_had_a_column() {
+    let path = temp_ledger("version-4");
+    let version_4 = "test-package-ledger,4\norder,1,2024-05-01T09:00:00Z,800,0,800\n\
+produce,1,Pickles (dill),units,1,800,800\n";
+    fs::write(&path, version_4).unwrap();
+    let mut ledger = Ledger::open(&path).unwrap();
+    assert_eq!(ledger.read_all().unwrap()[0].produce[0].item, Vegetables::pickle("dill"));
+    // the old row is still read once the file has been brought up to date
+    let mut order = Order::new();
+    order.add(Drinks::Soda, 1).unwrap();
+    ledger.record(at(1, 10), &PriceTable::standard().price(&order).unwrap(), &[]).unwrap();
+    assert_eq!(ledger.read_all().unwrap()[0].produce[0].item, Vegetables::pickle("dill"));
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn tips_are_kept_apart_from_revenue() {
+    let path = temp_ledger("tips");
+    let mut order = Order::new();
+    order.add(Drinks::Coffee(CoffeeFlavor::Mocha), 2).unwrap();
+    let bill = PriceTable::standard().price(&order).unwrap();
+    let shares = split(&bill, &Split::Evenly(2), Some(Tip::Amount(Money::from_cents(150)))).unwrap();
+    let mut ledger = Ledger::open(&path).unwrap();
+    ledger.record(at(1, 9), &bill, &shares).unwrap();
+
+    let sales = ledger.read_all().unwrap();
+    assert_eq!(sales[0].payments, shares);
+    let report = SalesReport::new(at(1, 9).date(), at(1, 9).date(), &sales);
+    assert_eq!(report.revenue, Money::from_cents(950));
+    assert_eq!(report.tips, Money::from_cents(150));
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn refuses_files_it_cant_read() {
+    let path = temp_ledger("future");
+    fs::write(&path, "test-package-ledger,99\n").unwrap();
+    assert!(matches!(Ledger::open(&path), Err(LedgerError::UnsupportedVersion(99))));
+    fs::write(&path, "name,price\n").unwrap();
+    assert!(matches!(Ledger::open(&path), Err(LedgerError::NotALedger)));
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn csv_quoting_round_trips() {
+    let fields = vec!["plain".to_string(), "a, b".to_string(), "say \"hi\"".to_string(), String::new()];
+    assert_eq!(parse_csv_row(&csv_row(&fields)), Some(fields));
+  }
+
+  #[test]
+  fn report_summarizes_a_date_range() {
+    let table = PriceTable::standard();
+    let bill_of = |drink: Drinks, quantity| {
+      let mut order = Order::new();
+      order.add(drink, quantity).unwrap();
+      table.price(&order).unwrap()
+    };
+    let sales = vec![
+      SaleRecord::from_bill(1, at(1, 9), &bill_of(Drinks::Coffee(CoffeeFlavor::Latte), 2)),
+      SaleRecord::from_bill(2, at(1, 10), &bill_of(Drinks::Coffee(CoffeeFlavor::Mocha), 1)),
+      SaleRecord::from_bill(3, at(2, 9), &bill_of(Drinks::Water, 3)),
+      SaleRecord::from_bill(4, at(5, 9), &bill_of(Drinks::Beer, 1)),
+    ];
+    let report = SalesReport::new(Date::new(2024, 5, 1).unwrap(), Date::new(2024, 5, 2).unwrap(), &sales);
+    assert_eq!(report.orders, 3);
+    assert_eq!(report.revenue, Money::from_cents(900 + 475 + 300));
+    assert_eq!(report.average_order_value(), Money::from_cents(558));
+    assert_eq!(report.per_drink.get("Coffee"), Some(&3));
+    assert_eq!(report.per_coffee_flavor.get("Latte"), Some(&2));
+    assert_eq!(report.best_sellers[0], (Drinks::Water, 3));
+    assert_eq!(report.best_sellers[1], (Drinks::Coffee(CoffeeFlavor::Latte), 2));
+  }
+}
@@ -1,59 +1,183 @@
 // this is the library crate root for this package. it can define some public API(?)
 
-#[derive(Debug)]
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CoffeeFlavor {
   Mocha,
   Latte,
   Capuchino,
   Americano,
 }
+impl CoffeeFlavor {
+  // every flavor we brew, in the order it shows up on the menu
+  pub const ALL: [CoffeeFlavor; 4] = [
+    CoffeeFlavor::Mocha,
+    CoffeeFlavor::Latte,
+    CoffeeFlavor::Capuchino,
+    CoffeeFlavor::Americano,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      CoffeeFlavor::Mocha => "Mocha",
+      CoffeeFlavor::Latte => "Latte",
+      CoffeeFlavor::Capuchino => "Cappuccino",
+      CoffeeFlavor::Americano => "Americano",
+    }
+  }
+}
 
-#[derive(Debug)]
+impl fmt::Display for CoffeeFlavor {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Drinks {
   Water,
   Coffee(CoffeeFlavor),
   Soda,
   Beer,
 }
+impl Drinks {
+  // the full catalog: every drink we sell, with one entry per coffee flavor.
+  // this is the one place that knows "what we sell" - menus should be built from it
+  pub fn all() -> Vec<Drinks> {
+    let mut drinks = vec![Drinks::Water];
+    drinks.extend(CoffeeFlavor::ALL.iter().map(|flavor| Drinks::Coffee(*flavor)));
+    drinks.push(Drinks::Soda);
+    drinks.push(Drinks::Beer);
+    drinks
+  }
 
-#[derive(Debug)]
-pub struct MyRectangle {
-  width: u32,
-  height: u32,
-}
-impl MyRectangle {
-  fn area(&self) -> u32 {
-    self.width * self.height
-  }
-  pub fn print_info(&self) {
-    let width = self.width;
-    let height = self.height;
-    println!("rectangle with width: {width} and height {height}");
-  }
-  pub fn new(width: u32, height: u32) -> Self {
-    Self {
-      width,
-      height,
+  pub fn name(&self) -> &'static str {
+    match self {
+      Drinks::Water => "Water",
+      Drinks::Coffee(flavor) => flavor.name(),
+      Drinks::Soda => "Soda",
+      Drinks::Beer => "Beer",
     }
   }
 }
 
-// declare an inline module (replacing the semicolon with curly brackets)
-mod felicity_farmstand {
-  enum Vegetables {
-    Asparagus,
-    Squash,
-    Beets,
-    Pickle(String),
+// shows up as "Latte (coffee)" for coffees and just the name for everything else
+impl fmt::Display for Drinks {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Drinks::Coffee(flavor) => write!(f, "{} (coffee)", flavor),
+      other => f.write_str(other.name()),
+    }
   }
 }
 
+// parsing drinks out of freehand text lives in its own module
+pub mod parse;
+pub use parse::ParseDrinkError;
+
+// taking orders and working out what they cost
+pub mod custom;
+pub mod inventory;
+pub mod money;
+pub mod nutrition;
+pub mod order;
+pub mod pricing;
+pub mod promo;
+pub use custom::{CustomDrink, CustomDrinkBuilder, CustomizeError};
+pub use money::Money;
+pub use nutrition::{Allergen, MenuQuery, Nutrition};
+pub use order::{Order, OrderLine};
+pub use pricing::{Bill, PriceTable};
+pub use promo::Promotions;
+
+// receipts, and the dates and JSON they need
+pub mod date;
+pub mod json;
+pub mod receipt;
+pub use receipt::Receipt;
+
+// every sale written down, and the reports made from them
+pub mod ledger;
+
+// where each order is, from placed to picked up
+pub mod lifecycle;
+pub use lifecycle::{Lifecycle, OrderStatus};
+
+// the order book over HTTP, for the kiosk and handhelds
+pub mod http;
+
+// settings files, and the age checks they configure
+pub mod config;
+pub mod policy;
+
+// sharing a bill between payers, with tips
+pub mod split;
+
+// regulars, and the punch card that keeps them coming back
+pub mod loyalty;
+
+// the command loop behind the till binary
+pub mod till;
+
+// baristas working through the queue of orders on their own threads
+pub mod barista;
+
+// the rectangle from the book lives in the shared geometry crate now, along with the copies the
+// other tutorials used to keep. the old name stays for the code that uses it
+pub use geometry::{Rectangle, RectangleError};
+pub type MyRectangle = Rectangle;
+
+// declare a module 'felicity_farmstand' which would be found in either of:
+// src/felicity_farmstand.rs
+// src/felicity_farmstand/mod.rs
+// it started out inline (with curly brackets instead of the semicolon) and grew into the produce
+// sold at the farm stand next door, rung up on the same till as the drinks
+pub mod felicity_farmstand;
+pub use felicity_farmstand::{Farmstand, Vegetables};
+
+// one cart for everything we sell - drinks, produce and boards cut to size - on one checkout
+pub mod cart;
+pub use cart::{Cart, Sellable};
+
 // declare a module 'chris_cataclysm' which would be found in either of:
 // src/chris_cataclysm.rs
 // src/chris_cataclysm/mod.rs
-mod chris_cataclysm;
+// it holds the one error every module's own errors convert into
+pub mod chris_cataclysm;
+pub use chris_cataclysm::{Context, Error};
 
 // declare a module 'owen_oblivion' which would be found in either of:
 // src/owen_oblivion.rs
 // src/owen_oblivion/mod.rs
-mod owen_oblivion;
+pub mod owen_oblivion;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn catalog_lists_every_coffee_flavor() {
+    let drinks = Drinks::all();
+    for flavor in CoffeeFlavor::ALL {
+      assert!(drinks.contains(&Drinks::Coffee(flavor)));
+    }
+    assert_eq!(drinks.len(), 3 + CoffeeFlavor::ALL.len());
+  }
+
+  #[test]
+  fn coffees_display_with_their_category() {
+    assert_eq!(Drinks::Coffee(CoffeeFlavor::Latte).to_string(), "Latte (coffee)");
+    assert_eq!(Drinks::Water.to_string(), "Water");
+  }
+
+  #[test]
+  fn catalog_names_are_unique() {
+    let drinks = Drinks::all();
+    for (i, a) in drinks.iter().enumerate() {
+      for b in &drinks[i + 1..] {
+        assert_ne!(a.name(), b.name());
+      }
+    }
+  }
+}
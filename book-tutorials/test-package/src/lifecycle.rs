@@ -0,0 +1,239 @@
+// where an order is in its life, and the only ways it can move on:
+//
+//   Placed -> Paid -> Brewing -> Ready -> PickedUp
+//
+// an order can only be Cancelled before it's paid for. once it has been paid the money has to go
+// back, so it's Refunded instead - whether or not it was picked up. every move is stamped with
+// when it happened and who did it, so the history of an order can always be told.
+
+use std::fmt;
+
+use crate::custom::find_by_name;
+use crate::date::Timestamp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderStatus {
+  Placed,
+  Paid,
+  Brewing,
+  Ready,
+  PickedUp,
+  Cancelled,
+  Refunded,
+}
+impl OrderStatus {
+  pub const ALL: [OrderStatus; 7] = [
+    OrderStatus::Placed,
+    OrderStatus::Paid,
+    OrderStatus::Brewing,
+    OrderStatus::Ready,
+    OrderStatus::PickedUp,
+    OrderStatus::Cancelled,
+    OrderStatus::Refunded,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      OrderStatus::Placed => "placed",
+      OrderStatus::Paid => "paid",
+      OrderStatus::Brewing => "brewing",
+      OrderStatus::Ready => "ready",
+      OrderStatus::PickedUp => "picked up",
+      OrderStatus::Cancelled => "cancelled",
+      OrderStatus::Refunded => "refunded",
+    }
+  }
+
+  // "picked_up" and "picked-up" are fine too
+  pub fn from_name(name: &str) -> Option<Self> {
+    find_by_name(&Self::ALL, &name.replace(['_', '-'], " "), Self::name)
+  }
+
+  // nothing more can happen to the order
+  pub fn is_final(&self) -> bool {
+    self.next_statuses().is_empty()
+  }
+
+  // every status an order in this one is allowed to move to
+  pub fn next_statuses(&self) -> &'static [OrderStatus] {
+    match self {
+      OrderStatus::Placed => &[OrderStatus::Paid, OrderStatus::Cancelled],
+      OrderStatus::Paid => &[OrderStatus::Brewing, OrderStatus::Refunded],
+      OrderStatus::Brewing => &[OrderStatus::Ready, OrderStatus::Refunded],
+      OrderStatus::Ready => &[OrderStatus::PickedUp, OrderStatus::Refunded],
+      OrderStatus::PickedUp => &[OrderStatus::Refunded],
+      OrderStatus::Cancelled | OrderStatus::Refunded => &[],
+    }
+  }
+
+  pub fn can_become(&self, next: OrderStatus) -> bool {
+    self.next_statuses().contains(&next)
+  }
+}
+impl fmt::Display for OrderStatus {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+  // None for the very first status, which the order was created in
+  pub from: Option<OrderStatus>,
+  pub to: OrderStatus,
+  pub at: Timestamp,
+  pub actor: String,
+}
+
+// the status of one order and how it got there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lifecycle {
+  history: Vec<Transition>,
+}
+impl Lifecycle {
+  // a freshly placed order
+  pub fn placed(at: Timestamp, actor: impl Into<String>) -> Self {
+    Self {
+      history: vec![Transition {
+        from: None,
+        to: OrderStatus::Placed,
+        at,
+        actor: actor.into(),
+      }],
+    }
+  }
+
+  pub fn status(&self) -> OrderStatus {
+    self.history.last().expect("a lifecycle always has its first status").to
+  }
+
+  pub fn history(&self) -> &[Transition] {
+    &self.history
+  }
+
+  // when the order last moved
+  pub fn updated_at(&self) -> Timestamp {
+    self.history.last().expect("a lifecycle always has its first status").at
+  }
+
+  pub fn advance(&mut self, to: OrderStatus, at: Timestamp, actor: impl Into<String>) -> Result<(), TransitionError> {
+    let from = self.status();
+    if !from.can_become(to) {
+      return Err(TransitionError::Illegal { from, to });
+    }
+    if at < self.updated_at() {
+      return Err(TransitionError::Backdated { from, to });
+    }
+    self.history.push(Transition {
+      from: Some(from),
+      to,
+      at,
+      actor: actor.into(),
+    });
+    Ok(())
+  }
+
+  pub fn pay(&mut self, at: Timestamp, actor: impl Into<String>) -> Result<(), TransitionError> {
+    self.advance(OrderStatus::Paid, at, actor)
+  }
+
+  pub fn start_brewing(&mut self, at: Timestamp, actor: impl Into<String>) -> Result<(), TransitionError> {
+    self.advance(OrderStatus::Brewing, at, actor)
+  }
+
+  pub fn mark_ready(&mut self, at: Timestamp, actor: impl Into<String>) -> Result<(), TransitionError> {
+    self.advance(OrderStatus::Ready, at, actor)
+  }
+
+  pub fn pick_up(&mut self, at: Timestamp, actor: impl Into<String>) -> Result<(), TransitionError> {
+    self.advance(OrderStatus::PickedUp, at, actor)
+  }
+
+  pub fn cancel(&mut self, at: Timestamp, actor: impl Into<String>) -> Result<(), TransitionError> {
+    self.advance(OrderStatus::Cancelled, at, actor)
+  }
+
+  pub fn refund(&mut self, at: Timestamp, actor: impl Into<String>) -> Result<(), TransitionError> {
+    self.advance(OrderStatus::Refunded, at, actor)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionError {
+  // a move the state machine doesn't have
+  Illegal { from: OrderStatus, to: OrderStatus },
+  // an allowed move, stamped before the order's last one
+  Backdated { from: OrderStatus, to: OrderStatus },
+}
+impl fmt::Display for TransitionError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      TransitionError::Illegal { from, to } => {
+        write!(f, "a {} order can't be {}", from, to)?;
+        if from.is_final() {
+          write!(f, " (nothing more can happen to it)")
+        } else {
+          let next: Vec<&str> = from.next_statuses().iter().map(|s| s.name()).collect();
+          write!(f, " (it can only become {})", next.join(" or "))
+        }
+      }
+      TransitionError::Backdated { from, to } => {
+        write!(f, "can't mark a {} order {} with a time before it was {}", from, to, from)
+      }
+    }
+  }
+}
+impl std::error::Error for TransitionError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn at(minute: i64) -> Timestamp {
+    Timestamp::from_unix_seconds(1_714_550_400 + minute * 60)
+  }
+
+  #[test]
+  fn follows_the_happy_path_and_records_who_did_what() {
+    let mut order = Lifecycle::placed(at(0), "kiosk");
+    order.pay(at(1), "kiosk").unwrap();
+    order.start_brewing(at(2), "alex").unwrap();
+    order.mark_ready(at(5), "alex").unwrap();
+    order.pick_up(at(9), "sam").unwrap();
+
+    assert_eq!(order.status(), OrderStatus::PickedUp);
+    let actors: Vec<&str> = order.history().iter().map(|t| t.actor.as_str()).collect();
+    assert_eq!(actors, vec!["kiosk", "kiosk", "alex", "alex", "sam"]);
+    assert_eq!(order.history()[3].from, Some(OrderStatus::Brewing));
+  }
+
+  #[test]
+  fn refuses_illegal_moves_with_a_reason() {
+    let mut order = Lifecycle::placed(at(0), "kiosk");
+    let err = order.start_brewing(at(1), "alex").unwrap_err();
+    assert_eq!(err, TransitionError::Illegal { from: OrderStatus::Placed, to: OrderStatus::Brewing });
+    assert_eq!(err.to_string(), "a placed order can't be brewing (it can only become paid or cancelled)");
+
+    order.cancel(at(1), "kiosk").unwrap();
+    let err = order.refund(at(2), "sam").unwrap_err();
+    assert_eq!(err.to_string(), "a cancelled order can't be refunded (nothing more can happen to it)");
+    assert_eq!(order.history().len(), 2);
+  }
+
+  #[test]
+  fn only_unpaid_orders_can_be_cancelled() {
+    let mut order = Lifecycle::placed(at(0), "kiosk");
+    order.pay(at(1), "kiosk").unwrap();
+    let err = order.cancel(at(2), "kiosk").unwrap_err();
+    assert_eq!(err.to_string(), "a paid order can't be cancelled (it can only become brewing or refunded)");
+    order.refund(at(2), "sam").unwrap();
+  }
+
+  #[test]
+  fn transitions_cant_go_back_in_time() {
+    let mut order = Lifecycle::placed(at(5), "kiosk");
+    let err = order.pay(at(4), "kiosk").unwrap_err();
+    assert_eq!(err, TransitionError::Backdated { from: OrderStatus::Placed, to: OrderStatus::Paid });
+    assert_eq!(err.to_string(), "can't mark a placed order paid with a time before it was placed");
+  }
+}
@@ -0,0 +1,410 @@
+// customer profiles and the loyalty punch card: every coffee bought is a punch, and every
+// `punches_per_reward` punches earn a free coffee.
+//
+// profiles live in a small CSV file (see ledger.rs for the quoting), rewritten whole on every
+// save since it only ever holds one row per customer and one per visit:
+//
+//   test-package-customers,1
+//   customer,<id>,<name>,<phone>,<card>,<favorite drink>,<punches>,<free coffees>
+//   visit,<customer id>,<timestamp>,<drinks>
+//
+// a visit's drinks are written as "latte:2; water:1", and come right after their customer.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::date::Timestamp;
+use crate::ledger::{csv_row, parse_csv_row};
+use crate::order::Order;
+use crate::Drinks;
+
+const MAGIC: &str = "test-package-customers";
+const VERSION: u32 = 1;
+
+// buy nine, the tenth is on the house
+pub const DEFAULT_PUNCHES_PER_REWARD: u32 = 9;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Visit {
+  pub at: Timestamp,
+  // every drink bought, with how many
+  pub drinks: Vec<(Drinks, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Customer {
+  pub id: u64,
+  pub name: String,
+  // digits only, so "(555) 010-2030" and "555 010 2030" are the same number
+  pub phone: Option<String>,
+  pub card: Option<String>,
+  // what they order most. None until they've bought something
+  pub favorite: Option<Drinks>,
+  pub visits: Vec<Visit>,
+  // towards the next free coffee
+  pub punches: u32,
+  // earned and not yet used
+  pub free_coffees: u32,
+}
+impl Customer {
+  pub fn greeting(&self) -> String {
+    match (self.visits.is_empty(), self.favorite) {
+      (true, _) => format!("welcome, {}!", self.name),
+      (false, Some(drink)) => format!("welcome back, {}, your usual {}?", self.name, drink.name()),
+      (false, None) => format!("welcome back, {}!", self.name),
+    }
+  }
+
+  // how many of each drink they've ever bought, most first (ties in the order first bought)
+  pub fn tally(&self) -> Vec<(Drinks, u32)> {
+    let mut tally: Vec<(Drinks, u32)> = Vec::new();
+    for (drink, quantity) in self.visits.iter().flat_map(|visit| &visit.drinks) {
+      match tally.iter_mut().find(|(d, _)| d == drink) {
+        Some((_, count)) => *count += quantity,
+        None => tally.push((*drink, *quantity)),
+      }
+    }
+    tally.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    tally
+  }
+}
+
+pub struct CustomerBook {
+  // None keeps everything in memory
+  path: Option<PathBuf>,
+  customers: Vec<Customer>,
+  punches_per_reward: u32,
+}
+impl CustomerBook {
+  pub fn in_memory() -> Self {
+    Self {
+      path: None,
+      customers: Vec::new(),
+      punches_per_reward: DEFAULT_PUNCHES_PER_REWARD,
+    }
+  }
+
+  // the profiles stored at `path`. a file that isn't there yet is an empty book
+  pub fn open(path: impl AsRef<Path>) -> Result<Self, LoyaltyError> {
+    let path = path.as_ref().to_path_buf();
+    let customers = match fs::read_to_string(&path) {
+      Ok(text) => parse_customers(&text)?,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+      Err(e) => return Err(LoyaltyError::Io(e)),
+    };
+    Ok(Self {
+      path: Some(path),
+      customers,
+      punches_per_reward: DEFAULT_PUNCHES_PER_REWARD,
+    })
+  }
+
+  pub fn set_punches_per_reward(&mut self, punches: u32) {
+    self.punches_per_reward = punches.max(1);
+  }
+
+  pub fn customers(&self) -> &[Customer] {
+    &self.customers
+  }
+
+  pub fn get(&self, id: u64) -> Option<&Customer> {
+    self.customers.iter().find(|customer| customer.id == id)
+  }
+
+  pub fn find_by_phone(&self, phone: &str) -> Option<&Customer> {
+    let phone = normalize_phone(phone)?;
+    self.customers.iter().find(|customer| customer.phone.as_deref() == Some(phone.as_str()))
+  }
+
+  pub fn find_by_card(&self, card: &str) -> Option<&Customer> {
+    let card = card.trim();
+    self.customers.iter().find(|customer| customer.card.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(card)))
+  }
+
+  // whatever the customer read out: a card number first, then a phone number
+  pub fn lookup(&self, number: &str) -> Option<&Customer> {
+    self.find_by_card(number).or_else(|| self.find_by_phone(number))
+  }
+
+  // sign somebody up, handing back their id. phone numbers and cards can't be shared
+  pub fn enroll(&mut self, name: &str, phone: Option<&str>, card: Option<&str>) -> Result<u64, LoyaltyError> {
+    let phone = match phone {
+      Some(phone) => Some(normalize_phone(phone).ok_or_else(|| LoyaltyError::BadPhone(phone.to_string()))?),
+      None => None,
+    };
+    if let Some(phone) = &phone {
+      if self.find_by_phone(phone).is_some() {
+        return Err(LoyaltyError::AlreadyEnrolled(phone.clone()));
+      }
+    }
+    if let Some(card) = card {
+      if self.find_by_card(card).is_some() {
+        return Err(LoyaltyError::AlreadyEnrolled(card.to_string()));
+      }
+    }
+    let id = self.customers.iter().map(|customer| customer.id).max().unwrap_or(0) + 1;
+    self.customers.push(Customer {
+      id,
+      name: name.trim().to_string(),
+      phone,
+      card: card.map(|card| card.trim().to_string()),
+      favorite: None,
+      visits: Vec::new(),
+      punches: 0,
+      free_coffees: 0,
+    });
+    self.save()?;
+    Ok(id)
+  }
+
+  // a checked-out order: punches for every coffee on it except the `redeemed` free ones, and a
+  // new favorite if it's changed. hands back how many free coffees the order earned
+  pub fn record_visit(&mut self, id: u64, at: Timestamp, order: &Order, redeemed: u32) -> Result<u32, LoyaltyError> {
+    let punches_per_reward = self.punches_per_reward;
+    let customer = self.customer_mut(id)?;
+    let mut drinks: Vec<(Drinks, u32)> = Vec::new();
+    for line in order.lines() {
+      match drinks.iter_mut().find(|(drink, _)| *drink == line.item.drink()) {
+        Some((_, count)) => *count += line.quantity,
+        None => drinks.push((line.item.drink(), line.quantity)),
+      }
+    }
+    let coffees: u32 = drinks.iter().filter(|(drink, _)| matches!(drink, Drinks::Coffee(_))).map(|(_, n)| n).sum();
+    customer.visits.push(Visit { at, drinks });
+    customer.punches += coffees.saturating_sub(redeemed);
+    let earned = customer.punches / punches_per_reward;
+    customer.punches %= punches_per_reward;
+    customer.free_coffees += earned;
+    // only a strictly bigger count takes over, so a tie doesn't flip the usual back and forth
+    let tally = customer.tally();
+    let count_of = |drink: Option<Drinks>| tally.iter().find(|(d, _)| Some(*d) == drink).map_or(0, |(_, n)| *n);
+    if let Some((top, count)) = tally.first() {
+      if *count > count_of(customer.favorite) {
+        customer.favorite = Some(*top);
+      }
+    }
+    self.save()?;
+    Ok(earned)
+  }
+
+  // use up one free coffee
+  pub fn redeem(&mut self, id: u64) -> Result<(), LoyaltyError> {
+    let customer = self.customer_mut(id)?;
+    if customer.free_coffees == 0 {
+      return Err(LoyaltyError::NoFreeCoffees(customer.name.clone()));
+    }
+    customer.free_coffees -= 1;
+    self.save()
+  }
+
+  fn customer_mut(&mut self, id: u64) -> Result<&mut Customer, LoyaltyError> {
+    self.customers.iter_mut().find(|customer| customer.id == id).ok_or(LoyaltyError::UnknownCustomer(id))
+  }
+
+  // written to a temporary file first, so a crash can't leave half a book behind
+  fn save(&self) -> Result<(), LoyaltyError> {
+    let path = match &self.path {
+      Some(path) => path,
+      None => return Ok(()),
+    };
+    let mut text = csv_row(&[MAGIC.to_string(), VERSION.to_string()]);
+    text.push('\n');
+    for customer in &self.customers {
+      text.push_str(&csv_row(&[
+        "customer".to_string(),
+        customer.id.to_string(),
+        customer.name.clone(),
+        customer.phone.clone().unwrap_or_default(),
+        customer.card.clone().unwrap_or_default(),
+        customer.favorite.map(|drink| drink.name().to_lowercase()).unwrap_or_default(),
+        customer.punches.to_string(),
+        customer.free_coffees.to_string(),
+      ]));
+      text.push('\n');
+      for visit in &customer.visits {
+        let drinks: Vec<String> =
+          visit.drinks.iter().map(|(drink, n)| format!("{}:{}", drink.name().to_lowercase(), n)).collect();
+        text.push_str(&csv_row(&[
+          "visit".to_string(),
+          customer.id.to_string(),
+          visit.at.to_string(),
+          drinks.join("; "),
+        ]));
+        text.push('\n');
+      }
+    }
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, text)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+  }
+}
+
+// digits only. None if there aren't enough of them to be a phone number
+fn normalize_phone(phone: &str) -> Option<String> {
+  let digits: String = phone.chars().filter(char::is_ascii_digit).collect();
+  if digits.len() >= 7 && phone.chars().all(|c| c.is_ascii_digit() || " +-().".contains(c)) {
+    Some(digits)
+  } else {
+    None
+  }
+}
+
+fn parse_customers(text: &str) -> Result<Vec<Customer>, LoyaltyError> {
+  let mut customers: Vec<Customer> = Vec::new();
+  let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+  match lines.next().and_then(|(_, header)| parse_csv_row(header)) {
+    Some(fields) if fields.len() == 2 && fields[0] == MAGIC => {
+      if fields[1] != VERSION.to_string() {
+        return Err(LoyaltyError::NotACustomerFile);
+      }
+    }
+    None => return Ok(customers),
+    _ => return Err(LoyaltyError::NotACustomerFile),
+  }
+  for (index, line) in lines {
+    let row = index + 1;
+    let malformed = |reason| LoyaltyError::Malformed { row, reason };
+    let fields = parse_csv_row(line).ok_or(malformed("unterminated quote"))?;
+    let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+    let count = |s: &str| s.parse::<u32>().map_err(|_| malformed("bad number"));
+    let optional = |s: &str| if s.is_empty() { None } else { Some(s.to_string()) };
+    match fields.as_slice() {
+      ["customer", id, name, phone, card, favorite, punches, free_coffees] => customers.push(Customer {
+        id: id.parse().map_err(|_| malformed("bad customer id"))?,
+        name: name.to_string(),
+        phone: optional(phone),
+        card: optional(card),
+        favorite: match *favorite {
+          "" => None,
+          drink => Some(drink.parse().map_err(|_| malformed("unknown drink"))?),
+        },
+        visits: Vec::new(),
+        punches: count(punches)?,
+        free_coffees: count(free_coffees)?,
+      }),
+      ["visit", id, at, drinks] => {
+        let customer = match customers.last_mut() {
+          Some(customer) if customer.id.to_string() == *id => customer,
+          _ => return Err(malformed("visit doesn't follow its customer")),
+        };
+        let mut visit = Visit {
+          at: at.parse().map_err(|_| malformed("bad timestamp"))?,
+          drinks: Vec::new(),
+        };
+        for entry in drinks.split("; ").filter(|entry| !entry.is_empty()) {
+          let (drink, quantity) = entry.rsplit_once(':').ok_or(malformed("bad drink in visit"))?;
+          visit.drinks.push((drink.parse().map_err(|_| malformed("unknown drink"))?, count(quantity)?));
+        }
+        customer.visits.push(visit);
+      }
+      _ => return Err(malformed("unknown kind of row")),
+    }
+  }
+  Ok(customers)
+}
+
+#[derive(Debug)]
+pub enum LoyaltyError {
+  Io(io::Error),
+  NotACustomerFile,
+  // row numbers start at 1, counting the header
+  Malformed { row: usize, reason: &'static str },
+  UnknownCustomer(u64),
+  BadPhone(String),
+  // the phone number or card is somebody else's
+  AlreadyEnrolled(String),
+  // the customer's name
+  NoFreeCoffees(String),
+}
+impl fmt::Display for LoyaltyError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      LoyaltyError::Io(e) => write!(f, "couldn't use the customer file: {}", e),
+      LoyaltyError::NotACustomerFile => write!(f, "this file is not a customer file this program can read"),
+      LoyaltyError::Malformed { row, reason } => write!(f, "customer file row {}: {}", row, reason),
+      LoyaltyError::UnknownCustomer(id) => write!(f, "there is no customer {}", id),
+      LoyaltyError::BadPhone(phone) => write!(f, "\"{}\" is not a phone number", phone),
+      LoyaltyError::AlreadyEnrolled(number) => write!(f, "{} already belongs to a customer", number),
+      LoyaltyError::NoFreeCoffees(name) => write!(f, "{} has no free coffees yet", name),
+    }
+  }
+}
+impl std::error::Error for LoyaltyError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      LoyaltyError::Io(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+impl From<io::Error> for LoyaltyError {
+  fn from(e: io::Error) -> Self {
+    LoyaltyError::Io(e)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::date::Date;
+  use crate::CoffeeFlavor;
+  use std::env;
+
+  const MOCHA: Drinks = Drinks::Coffee(CoffeeFlavor::Mocha);
+
+  fn at(day: u32) -> Timestamp {
+    Timestamp::from_date_time(Date::new(2024, 5, day).unwrap(), 9, 0, 0)
+  }
+
+  fn order(drinks: &[(Drinks, u32)]) -> Order {
+    let mut order = Order::new();
+    for (drink, quantity) in drinks {
+      order.add(*drink, *quantity).unwrap();
+    }
+    order
+  }
+
+  #[test]
+  fn every_ninth_coffee_earns_a_free_one() {
+    let mut book = CustomerBook::in_memory();
+    let id = book.enroll("Sam", Some("(555) 010-2030"), None).unwrap();
+    assert_eq!(book.record_visit(id, at(1), &order(&[(MOCHA, 5), (Drinks::Water, 3)]), 0).unwrap(), 0);
+    assert_eq!(book.record_visit(id, at(2), &order(&[(MOCHA, 5)]), 0).unwrap(), 1);
+    let sam = book.get(id).unwrap();
+    assert_eq!((sam.punches, sam.free_coffees), (1, 1));
+
+    book.redeem(id).unwrap();
+    assert_eq!(book.redeem(id).unwrap_err().to_string(), "Sam has no free coffees yet");
+    // the free one doesn't count towards the next
+    book.record_visit(id, at(3), &order(&[(MOCHA, 2)]), 1).unwrap();
+    assert_eq!(book.get(id).unwrap().punches, 2);
+  }
+
+  #[test]
+  fn greets_regulars_with_their_usual() {
+    let mut book = CustomerBook::in_memory();
+    let id = book.enroll("Sam", Some("555 010 2030"), Some("C-100")).unwrap();
+    assert_eq!(book.lookup("c-100").unwrap().greeting(), "welcome, Sam!");
+    book.record_visit(id, at(1), &order(&[(MOCHA, 2), (Drinks::Water, 1)]), 0).unwrap();
+    book.record_visit(id, at(2), &order(&[(Drinks::Water, 1)]), 0).unwrap();
+    // two waters ties two mochas, and a tie doesn't change the usual
+    assert_eq!(book.lookup("555-010-2030").unwrap().greeting(), "welcome back, Sam, your usual Mocha?");
+    assert!(matches!(book.enroll("Alex", Some("5550102030"), None), Err(LoyaltyError::AlreadyEnrolled(_))));
+  }
+
+  #[test]
+  fn profiles_survive_a_round_trip() {
+    let path = env::temp_dir().join(format!("test-package-customers-{}.csv", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut book = CustomerBook::open(&path).unwrap();
+    let id = book.enroll("Sam, the regular", Some("555 010 2030"), None).unwrap();
+    book.record_visit(id, at(1), &order(&[(MOCHA, 2), (Drinks::Beer, 1)]), 0).unwrap();
+
+    let reopened = CustomerBook::open(&path).unwrap();
+    assert_eq!(reopened.customers(), book.customers());
+    fs::remove_file(&path).unwrap();
+  }
+}
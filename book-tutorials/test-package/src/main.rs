@@ -1,17 +1,43 @@
-use test_package::MyRectangle;
+use std::env;
+
+use test_package::{Drinks, Error, MyRectangle, Order, PriceTable};
 
 fn main() {
     println!("Hello, world from the default binary crate of this package!");
 
     // try som functionality from the default library crate
     let rect1 = MyRectangle::new(3, 4);
-    rect1.print_info();
+    println!("rectangle with width: {} and height {}", rect1.width(), rect1.height());
 
     println!("Hello, and welcome to the test-package cafe!");
     println!("The available drinks are: ");
-    let drink = test_package::Drinks::Coffee(test_package::CoffeeFlavor::Latte);
-    // for name in  {
-    //   println!("{}", name);
-    // }
-    println!("{:?}", drink);
+    let prices = PriceTable::standard();
+    for drink in Drinks::all() {
+        match prices.price_of(&drink) {
+            Some(price) => println!("{} - {}", drink, price),
+            None => println!("{}", drink),
+        }
+    }
+
+    // each command line argument is one drink, so "latte latte water" is two lattes and a water.
+    // with no arguments we ring up a single latte, like we always have
+    let mut order = Order::new();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        args.push(String::from("latte"));
+    }
+    // a drink we don't know exits 2, and one we can't ring up 3, like the other binaries
+    for arg in &args {
+        let drink = arg.parse::<Drinks>().unwrap_or_else(|e| Error::from(e).exit());
+        order.add(drink, 1).unwrap_or_else(|e| Error::from(e).exit());
+    }
+
+    let bill = prices.price(&order).unwrap_or_else(|e| Error::from(e).exit());
+    println!("\nYour order:");
+    for line in &bill.lines {
+        println!("{} x {} @ {} = {}", line.quantity, line.item, line.unit_price, line.total);
+    }
+    println!("subtotal: {}", bill.subtotal);
+    println!("tax: {}", bill.tax);
+    println!("total: {}", bill.total);
 }
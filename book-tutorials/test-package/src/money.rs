@@ -0,0 +1,199 @@
+// a fixed-point amount of money, counted in whole cents.
+// everything that touches prices goes through this type so that nothing is ever rounded twice
+// or stored as a float - percentages are applied with integer math and rounded exactly once.
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money {
+  cents: i64,
+}
+impl Money {
+  pub const ZERO: Money = Money { cents: 0 };
+  // the most an amount typed in or read from a file can be, either way from zero: a billion
+  // dollars. far more than the cafe will ever see, and far enough from what an i64 can count that
+  // adding such amounts up can't overflow
+  pub const MAX_PARSED: Money = Money { cents: 100_000_000_000 };
+
+  pub const fn from_cents(cents: i64) -> Self {
+    Self { cents }
+  }
+
+  pub const fn cents(&self) -> i64 {
+    self.cents
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.cents == 0
+  }
+
+  // None when the total is too big to count in cents
+  pub fn checked_times(&self, quantity: u32) -> Option<Self> {
+    self.cents.checked_mul(i64::from(quantity)).map(Self::from_cents)
+  }
+
+  pub fn checked_add(&self, other: Money) -> Option<Self> {
+    self.cents.checked_add(other.cents).map(Self::from_cents)
+  }
+
+  pub fn checked_sub(&self, other: Money) -> Option<Self> {
+    self.cents.checked_sub(other.cents).map(Self::from_cents)
+  }
+
+  // a share of this amount given in basis points (1/100th of a percent, so 825 is 8.25%),
+  // rounded half away from zero to the nearest cent. None when the share is too big to count
+  pub fn checked_basis_points(&self, basis_points: u32) -> Option<Self> {
+    // i128 so that even the largest amount times the largest rate can't overflow on the way
+    let scaled = i128::from(self.cents) * i128::from(basis_points);
+    let rounded = (scaled.abs() + 5_000) / 10_000;
+    i64::try_from(rounded * scaled.signum()).ok().map(Self::from_cents)
+  }
+
+  // the same, for rates of up to 100% (which can never overflow)
+  pub fn basis_points(&self, basis_points: u32) -> Self {
+    self.checked_basis_points(basis_points).expect("a share too big to count in cents")
+  }
+}
+
+impl fmt::Display for Money {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let sign = if self.cents < 0 { "-" } else { "" };
+    let cents = self.cents.unsigned_abs();
+    let text = format!("{}${}.{:02}", sign, cents / 100, cents % 100);
+    // go through pad so callers can right-align amounts in columns
+    f.pad(&text)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMoneyError {
+  input: String,
+  // an amount, but more than MAX_PARSED
+  too_large: bool,
+}
+impl fmt::Display for ParseMoneyError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.too_large {
+      write!(f, "\"{}\" is more than the most an amount can be ({})", self.input, Money::MAX_PARSED)
+    } else {
+      write!(f, "\"{}\" is not an amount of money (expected something like 4.50)", self.input)
+    }
+  }
+}
+impl std::error::Error for ParseMoneyError {}
+
+// accepts "4", "4.5", "4.50" and "$4.50", with an optional leading minus sign, up to MAX_PARSED
+impl FromStr for Money {
+  type Err = ParseMoneyError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let error = || ParseMoneyError { input: s.to_string(), too_large: false };
+    let trimmed = s.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+      Some(rest) => (true, rest),
+      None => (false, trimmed),
+    };
+    let unsigned = unsigned.strip_prefix('$').unwrap_or(unsigned);
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let all_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if whole.is_empty() || !all_digits(whole) || fraction.len() > 2 || !all_digits(fraction) {
+      return Err(error());
+    }
+    let too_large = || ParseMoneyError { input: s.to_string(), too_large: true };
+    // all digits, so the only way these can fail is by being too big
+    let dollars: i64 = whole.parse().map_err(|_| too_large())?;
+    let cents: i64 = format!("{:0<2}", fraction).parse().map_err(|_| error())?;
+    let total = dollars.checked_mul(100).and_then(|c| c.checked_add(cents)).ok_or_else(too_large)?;
+    if total > Self::MAX_PARSED.cents {
+      return Err(too_large());
+    }
+    Ok(Self::from_cents(if negative { -total } else { total }))
+  }
+}
+
+impl Add for Money {
+  type Output = Money;
+
+  fn add(self, other: Money) -> Money {
+    Money::from_cents(self.cents + other.cents)
+  }
+}
+impl AddAssign for Money {
+  fn add_assign(&mut self, other: Money) {
+    self.cents += other.cents;
+  }
+}
+impl Sub for Money {
+  type Output = Money;
+
+  fn sub(self, other: Money) -> Money {
+    Money::from_cents(self.cents - other.cents)
+  }
+}
+impl SubAssign for Money {
+  fn sub_assign(&mut self, other: Money) {
+    self.cents -= other.cents;
+  }
+}
+impl Neg for Money {
+  type Output = Money;
+
+  fn neg(self) -> Money {
+    Money::from_cents(-self.cents)
+  }
+}
+impl Sum for Money {
+  fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+    iter.fold(Money::ZERO, Add::add)
+  }
+}
+impl<'a> Sum<&'a Money> for Money {
+  fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+    iter.copied().sum()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn displays_dollars_and_cents() {
+    assert_eq!(Money::from_cents(450).to_string(), "$4.50");
+    assert_eq!(Money::from_cents(-5).to_string(), "-$0.05");
+    assert_eq!(format!("{:>7}", Money::from_cents(99)), "  $0.99");
+  }
+
+  #[test]
+  fn parses_common_spellings() {
+    assert_eq!("4.5".parse(), Ok(Money::from_cents(450)));
+    assert_eq!("$12".parse(), Ok(Money::from_cents(1200)));
+    assert_eq!("-0.25".parse(), Ok(Money::from_cents(-25)));
+    assert!("4.505".parse::<Money>().is_err());
+    assert!("four".parse::<Money>().is_err());
+  }
+
+  #[test]
+  fn amounts_past_the_most_are_turned_away() {
+    assert_eq!("-1000000000".parse(), Ok(-Money::MAX_PARSED));
+    let err = "1000000000.01".parse::<Money>().unwrap_err();
+    assert_eq!(err.to_string(), "\"1000000000.01\" is more than the most an amount can be ($1000000000.00)");
+    assert!("92233720368547758.07".parse::<Money>().is_err());
+    assert!("99999999999999999999".parse::<Money>().is_err());
+  }
+
+  #[test]
+  fn basis_points_round_half_away_from_zero() {
+    // 8.25% of $4.50 is 37.125 cents
+    assert_eq!(Money::from_cents(450).basis_points(825), Money::from_cents(37));
+    // 10% of $0.05 is half a cent
+    assert_eq!(Money::from_cents(5).basis_points(1_000), Money::from_cents(1));
+    assert_eq!(Money::from_cents(-5).basis_points(1_000), Money::from_cents(-1));
+    // all of the most an i64 can count is fine, but any more than all of it isn't
+    assert_eq!(Money::from_cents(i64::MAX).basis_points(10_000), Money::from_cents(i64::MAX));
+    assert_eq!(Money::from_cents(i64::MAX).checked_basis_points(10_001), None);
+  }
+}
@@ -0,0 +1,260 @@
+// what's in a drink for the people drinking it: allergens, caffeine and calories.
+//
+// nothing here is written down per drink. each ingredient knows its own numbers, and a drink's
+// label is added up from the same Recipe the stock room uses - so a large oat latte with an extra
+// shot and hazelnut syrup gets an honest answer without anyone keeping a table of every way a
+// drink can be made. the numbers are typical values, good enough to answer "is there dairy in
+// this" and "how much caffeine is that", not a lab report.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::custom::{find_by_name, CustomDrink, Milk, Syrup};
+use crate::inventory::{Ingredient, Recipe};
+use crate::{CoffeeFlavor, Drinks};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Allergen {
+  Dairy,
+  Soy,
+  Nuts,
+}
+impl Allergen {
+  pub const ALL: [Allergen; 3] = [Allergen::Dairy, Allergen::Soy, Allergen::Nuts];
+
+  // "nut" is fine too
+  pub fn from_name(name: &str) -> Option<Self> {
+    if name.trim().eq_ignore_ascii_case("nut") {
+      return Some(Allergen::Nuts);
+    }
+    find_by_name(&Self::ALL, name, Self::name)
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Allergen::Dairy => "dairy",
+      Allergen::Soy => "soy",
+      Allergen::Nuts => "nuts",
+    }
+  }
+}
+impl fmt::Display for Allergen {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Nutrition {
+  pub caffeine_mg: u32,
+  pub calories: u32,
+  pub allergens: BTreeSet<Allergen>,
+}
+impl Nutrition {
+  // one drink made exactly this way
+  pub fn of(item: &CustomDrink) -> Self {
+    Recipe::for_drink(item)
+      .ingredients()
+      .map(|(ingredient, amount)| Self::of_ingredient(ingredient, amount))
+      .fold(Self::default(), |total, part| total + part)
+  }
+
+  // a drink the way it comes, a medium with the usual milk
+  pub fn of_drink(drink: Drinks) -> Self {
+    Self::of(&CustomDrink::from(drink))
+  }
+
+  pub fn of_flavor(flavor: CoffeeFlavor) -> Self {
+    Self::of_drink(Drinks::Coffee(flavor))
+  }
+
+  // `amount` of an ingredient, counted the way the stock room counts it (ml of milk, pumps of
+  // syrup, ...). values are per `per` of those, rounded once the amount is known
+  pub fn of_ingredient(ingredient: Ingredient, amount: u32) -> Self {
+    let (per, caffeine_mg, calories, allergens): (u32, u32, u32, &[Allergen]) = match ingredient {
+      Ingredient::EspressoShot => (1, 64, 1, &[]),
+      Ingredient::Milk(milk) => (100, 0, milk_calories(milk), milk_allergens(milk)),
+      // milk chocolate sauce, with soy lecithin in it
+      Ingredient::Chocolate => (30, 6, 90, &[Allergen::Dairy, Allergen::Soy]),
+      Ingredient::Syrup(syrup) => (1, 0, 20, syrup_allergens(syrup)),
+      Ingredient::Cup => (1, 0, 0, &[]),
+      Ingredient::SodaBottle => (1, 34, 140, &[]),
+      Ingredient::BeerBottle => (1, 0, 150, &[]),
+    };
+    let scale = |value: u32| (value * amount + per / 2) / per;
+    Self {
+      caffeine_mg: scale(caffeine_mg),
+      calories: scale(calories),
+      allergens: if amount > 0 { allergens.iter().copied().collect() } else { BTreeSet::new() },
+    }
+  }
+
+  pub fn contains(&self, allergen: Allergen) -> bool {
+    self.allergens.contains(&allergen)
+  }
+}
+impl std::ops::Add for Nutrition {
+  type Output = Nutrition;
+
+  fn add(mut self, other: Nutrition) -> Nutrition {
+    self.caffeine_mg += other.caffeine_mg;
+    self.calories += other.calories;
+    self.allergens.extend(other.allergens);
+    self
+  }
+}
+// the label on the menu: "128 mg caffeine, 156 kcal, contains dairy"
+impl fmt::Display for Nutrition {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.caffeine_mg == 0 {
+      write!(f, "no caffeine")?;
+    } else {
+      write!(f, "{} mg caffeine", self.caffeine_mg)?;
+    }
+    write!(f, ", {} kcal", self.calories)?;
+    if !self.allergens.is_empty() {
+      let names: Vec<&str> = self.allergens.iter().map(|a| a.name()).collect();
+      write!(f, ", contains {}", names.join(", "))?;
+    }
+    Ok(())
+  }
+}
+
+// per 100 ml
+fn milk_calories(milk: Milk) -> u32 {
+  match milk {
+    Milk::Whole => 64,
+    Milk::Skim => 35,
+    Milk::Oat => 50,
+    Milk::Soy => 42,
+    Milk::Almond => 16,
+  }
+}
+
+fn milk_allergens(milk: Milk) -> &'static [Allergen] {
+  match milk {
+    Milk::Whole | Milk::Skim => &[Allergen::Dairy],
+    Milk::Soy => &[Allergen::Soy],
+    Milk::Almond => &[Allergen::Nuts],
+    Milk::Oat => &[],
+  }
+}
+
+// hazelnut syrup is flavoring more than nut, but it's labeled so nobody has to wonder
+fn syrup_allergens(syrup: Syrup) -> &'static [Allergen] {
+  match syrup {
+    Syrup::Hazelnut => &[Allergen::Nuts],
+    Syrup::Vanilla | Syrup::Caramel => &[],
+  }
+}
+
+// a question about the menu, like "no dairy and under 100 mg caffeine":
+//
+//   MenuQuery::new().without(Allergen::Dairy).caffeine_under(100).drinks()
+//
+// limits are strict ("under"), and a query with nothing set matches everything
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MenuQuery {
+  without: BTreeSet<Allergen>,
+  caffeine_under: Option<u32>,
+  calories_under: Option<u32>,
+}
+impl MenuQuery {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn without(mut self, allergen: Allergen) -> Self {
+    self.without.insert(allergen);
+    self
+  }
+
+  pub fn caffeine_under(mut self, mg: u32) -> Self {
+    self.caffeine_under = Some(mg);
+    self
+  }
+
+  pub fn calories_under(mut self, kcal: u32) -> Self {
+    self.calories_under = Some(kcal);
+    self
+  }
+
+  pub fn matches(&self, nutrition: &Nutrition) -> bool {
+    self.without.is_disjoint(&nutrition.allergens)
+      && self.caffeine_under.is_none_or(|limit| nutrition.caffeine_mg < limit)
+      && self.calories_under.is_none_or(|limit| nutrition.calories < limit)
+  }
+
+  // every drink on the menu that fits, the way it comes
+  pub fn drinks(&self) -> Vec<Drinks> {
+    Drinks::all().into_iter().filter(|drink| self.matches(&Nutrition::of_drink(*drink))).collect()
+  }
+
+  // the drinks that fit once the milk is changed, when the usual milk is what rules them out -
+  // "no dairy" still gets a latte, made with oat milk
+  pub fn with_other_milk(&self) -> Vec<CustomDrink> {
+    let mut found = Vec::new();
+    for drink in Drinks::all() {
+      let usual = CustomDrink::from(drink);
+      if usual.milk().is_none() || self.matches(&Nutrition::of(&usual)) {
+        continue;
+      }
+      for milk in Milk::ALL {
+        if let Ok(item) = CustomDrink::builder(drink).milk(milk).build() {
+          if self.matches(&Nutrition::of(&item)) {
+            found.push(item);
+          }
+        }
+      }
+    }
+    found
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::custom::Size;
+
+  const LATTE: Drinks = Drinks::Coffee(CoffeeFlavor::Latte);
+
+  #[test]
+  fn labels_add_up_from_the_recipe() {
+    // two shots and 240 ml of whole milk
+    let latte = Nutrition::of_drink(LATTE);
+    assert_eq!((latte.caffeine_mg, latte.calories), (128, 156));
+    assert_eq!(latte.to_string(), "128 mg caffeine, 156 kcal, contains dairy");
+    assert_eq!(Nutrition::of_drink(Drinks::Water).to_string(), "no caffeine, 0 kcal");
+
+    let mocha = Nutrition::of_flavor(CoffeeFlavor::Mocha);
+    assert_eq!(mocha.allergens.iter().copied().collect::<Vec<_>>(), vec![Allergen::Dairy, Allergen::Soy]);
+  }
+
+  #[test]
+  fn customizations_change_the_label() {
+    let item = CustomDrink::builder(LATTE)
+      .size(Size::Large)
+      .milk(Milk::Almond)
+      .extra_shots(1)
+      .syrup(Syrup::Vanilla)
+      .build()
+      .unwrap();
+    let label = Nutrition::of(&item);
+    // four shots, 360 ml of almond milk and two pumps of syrup
+    assert_eq!(label.caffeine_mg, 256);
+    assert_eq!(label.calories, 4 + 58 + 40);
+    assert!(label.contains(Allergen::Nuts) && !label.contains(Allergen::Dairy));
+  }
+
+  #[test]
+  fn finds_drinks_without_dairy_under_a_caffeine_limit() {
+    let query = MenuQuery::new().without(Allergen::Dairy).caffeine_under(100);
+    assert_eq!(query.drinks(), vec![Drinks::Water, Drinks::Soda, Drinks::Beer]);
+
+    let no_dairy = MenuQuery::new().without(Allergen::Dairy);
+    assert!(no_dairy.drinks().contains(&Drinks::Coffee(CoffeeFlavor::Americano)));
+    let lattes: Vec<Option<Milk>> =
+      no_dairy.with_other_milk().iter().filter(|item| item.drink() == LATTE).map(|item| item.milk()).collect();
+    assert_eq!(lattes, vec![Some(Milk::Oat), Some(Milk::Soy), Some(Milk::Almond)]);
+  }
+}
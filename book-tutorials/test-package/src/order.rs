@@ -0,0 +1,134 @@
+// an order is just the list of what the customer asked for. what it costs is worked out by a
+// PriceTable (see pricing.rs) so the same order can be priced against different menus.
+// produce from the farm stand is the exception: it's priced when it's counted or weighed, so it
+// rides along already priced (see felicity_farmstand.rs).
+
+use std::fmt;
+
+use crate::custom::CustomDrink;
+use crate::felicity_farmstand::{Measure, ProduceLine};
+
+// the most of one thing a single line can hold. nobody orders a thousand lattes, and keeping
+// counts this small means the totals worked out from them can't overflow
+pub const MAX_QUANTITY: u32 = 999;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderLine {
+  pub item: CustomDrink,
+  pub quantity: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Order {
+  lines: Vec<OrderLine>,
+  produce: Vec<ProduceLine>,
+}
+impl Order {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // adding a drink that is already on the order (made exactly the same way) bumps its quantity
+  // instead of adding a line. plain Drinks can be added too. adding zero of something is a no-op,
+  // and nothing changes when the line would go over MAX_QUANTITY
+  pub fn add(&mut self, item: impl Into<CustomDrink>, quantity: u32) -> Result<(), QuantityError> {
+    let item = item.into();
+    if quantity == 0 {
+      return Ok(());
+    }
+    let existing = self.lines.iter().position(|line| line.item == item);
+    let already = existing.map_or(0, |index| self.lines[index].quantity);
+    let total = capped(already, quantity).ok_or_else(|| QuantityError::new(item.to_string(), already, quantity))?;
+    match existing {
+      Some(index) => self.lines[index].quantity = total,
+      None => self.lines.push(OrderLine { item, quantity }),
+    }
+    Ok(())
+  }
+
+  // remove a whole line by its position on the order, handing it back if it existed
+  pub fn remove(&mut self, index: usize) -> Option<OrderLine> {
+    if index < self.lines.len() {
+      Some(self.lines.remove(index))
+    } else {
+      None
+    }
+  }
+
+  // counted produce that's already on the order bumps its count, up to MAX_QUANTITY. each
+  // weighing is its own line
+  pub fn add_produce(&mut self, line: ProduceLine) -> Result<(), QuantityError> {
+    if let Measure::Units(count) = line.measure {
+      let existing = self
+        .produce
+        .iter_mut()
+        .find(|existing| existing.item == line.item && matches!(existing.measure, Measure::Units(_)));
+      let already = existing.as_ref().map_or(0, |existing| existing.quantity());
+      let total = capped(already, count).ok_or_else(|| QuantityError::new(line.item.to_string(), already, count))?;
+      if let Some(existing) = existing {
+        existing.measure = Measure::Units(total);
+        existing.total += line.total;
+        return Ok(());
+      }
+    }
+    self.produce.push(line);
+    Ok(())
+  }
+
+  pub fn remove_produce(&mut self, index: usize) -> Option<ProduceLine> {
+    if index < self.produce.len() {
+      Some(self.produce.remove(index))
+    } else {
+      None
+    }
+  }
+
+  // the drinks. produce is kept apart, in produce()
+  pub fn lines(&self) -> &[OrderLine] {
+    &self.lines
+  }
+
+  pub fn produce(&self) -> &[ProduceLine] {
+    &self.produce
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.lines.is_empty() && self.produce.is_empty()
+  }
+
+  // how many items in total, counting quantities. a weighed line counts once. stops at u32::MAX
+  // rather than overflowing, which would take millions of lines
+  pub fn item_count(&self) -> u32 {
+    let drinks = self.lines.iter().map(|line| line.quantity);
+    let produce = self.produce.iter().map(ProduceLine::quantity);
+    drinks.chain(produce).fold(0, u32::saturating_add)
+  }
+}
+
+fn capped(already: u32, more: u32) -> Option<u32> {
+  already.checked_add(more).filter(|total| *total <= MAX_QUANTITY)
+}
+
+// a line that would end up holding more than MAX_QUANTITY
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuantityError {
+  pub item: String,
+  // what the line already had, and what was being added to it
+  pub already: u32,
+  pub adding: u32,
+}
+impl QuantityError {
+  fn new(item: String, already: u32, adding: u32) -> Self {
+    Self { item, already, adding }
+  }
+}
+impl fmt::Display for QuantityError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "can't add {} x {}: at most {} of one thing go on an order", self.adding, self.item, MAX_QUANTITY)?;
+    if self.already > 0 {
+      write!(f, " and there are already {}", self.already)?;
+    }
+    Ok(())
+  }
+}
+impl std::error::Error for QuantityError {}
@@ -1,16 +1,103 @@
 // module definition (declaration?) for OwenOblivion
+//
+// where stock goes to die: the expiry subsystem. everything that spoils - milk, produce, pickles -
+// comes in as a lot with the day it was received and how long it keeps. stock is used oldest
+// first, lots that pass their date are written off instead of used, and the write-offs add up to
+// a waste report so it's plain what spoilage is costing.
+//
+//   owen_inline      what spoils, and how long each thing keeps
+//   owen_submod_one  lots, first in first out, and writing off what's expired
+//   owen_submod_two  the waste report
+
+use std::fmt;
+
+use crate::custom::Milk;
+use crate::felicity_farmstand::Vegetables;
 
 // an inline submodule for this module
-mod owen_inline {
+pub mod owen_inline {
+  use super::{Perishable, Unit};
+  use crate::custom::Milk;
+  use crate::felicity_farmstand::Vegetables;
+
+  // what it's counted in, the way the farm stand sells it
+  pub fn unit_of(item: &Perishable) -> Unit {
+    match item {
+      Perishable::Milk(_) => Unit::Milliliters,
+      Perishable::Produce(Vegetables::Asparagus | Vegetables::Beets) => Unit::Grams,
+      Perishable::Produce(Vegetables::Squash | Vegetables::Pickle(_)) => Unit::Pieces,
+    }
+  }
 
+  // how many days something keeps from the day it's received. it's written off on the day it
+  // reaches this age
+  pub fn shelf_life_days(item: &Perishable) -> u32 {
+    match item {
+      // unopened, in the fridge
+      Perishable::Milk(Milk::Whole | Milk::Skim) => 10,
+      Perishable::Milk(Milk::Oat | Milk::Soy | Milk::Almond) => 21,
+      Perishable::Produce(Vegetables::Asparagus) => 4,
+      Perishable::Produce(Vegetables::Beets) => 14,
+      Perishable::Produce(Vegetables::Squash) => 60,
+      // opened jars go back in the fridge, but a jar on the shelf keeps a year
+      Perishable::Produce(Vegetables::Pickle(_)) => 365,
+    }
+  }
 }
 
 // declare a submodule of 'owen_oblivion' named 'owen_submod_one' which would be found in either of:
 // src/owen_oblivion/owen_submod_one.rs
 // src/owen_oblivion/owen_submod_one/mod.rs
-mod owen_submod_one;
+pub mod owen_submod_one;
 
 // declare a submodule of 'owen_oblivion' named 'owen_submod_two' which would be found in either of:
 // src/owen_oblivion/owen_submod_two.rs
 // src/owen_oblivion/owen_submod_two/mod.rs
-mod owen_submod_two;
+pub mod owen_submod_two;
+
+pub use owen_inline::{shelf_life_days, unit_of};
+pub use owen_submod_one::{ExpiryError, Lot, PerishableStock, WriteOff};
+pub use owen_submod_two::WasteReport;
+
+// anything that spoils. milk is counted in ml, and produce the way it's sold: grams for what's
+// weighed, pieces (or jars) for what's counted
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Perishable {
+  Milk(Milk),
+  Produce(Vegetables),
+}
+impl Perishable {
+  // the way it's written by Display: "oat milk", or whatever the farm stand calls it
+  pub fn from_name(name: &str) -> Option<Self> {
+    let name = name.trim();
+    let milk = name.strip_suffix(" milk").and_then(Milk::from_name).map(Perishable::Milk);
+    milk.or_else(|| Vegetables::from_name(name).map(Perishable::Produce))
+  }
+}
+// "oat milk", or the produce's own name
+impl fmt::Display for Perishable {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Perishable::Milk(milk) => write!(f, "{} milk", milk.name()),
+      Perishable::Produce(item) => write!(f, "{}", item),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+  Milliliters,
+  Grams,
+  // jars, or whole vegetables
+  Pieces,
+}
+// what goes after an amount: " ml", " g", or nothing for a count
+impl fmt::Display for Unit {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Unit::Milliliters => write!(f, " ml"),
+      Unit::Grams => write!(f, " g"),
+      Unit::Pieces => Ok(()),
+    }
+  }
+}
@@ -0,0 +1,358 @@
+// lots of perishable stock, used oldest first.
+//
+// every delivery is its own lot, remembered with what it cost so that what's thrown away can be
+// put in dollars. taking stock always writes off whatever has expired by then first, so an
+// expired lot is never used, and a request either comes out of stock completely or not at all.
+//
+// the lots on hand when the till opens come from a config file, one section for each delivery:
+//
+//   [whole milk]
+//   quantity = 4000
+//   cost = 6.00
+//   received = 2024-05-01
+//   # optional, for a delivery that keeps more or less than its kind usually does
+//   shelf_life_days = 7
+
+use std::fmt;
+
+use super::{shelf_life_days, unit_of, Perishable, Unit};
+use crate::config::{self, ConfigError};
+use crate::date::Date;
+use crate::felicity_farmstand::Measure;
+use crate::inventory::{Ingredient, Recipe};
+use crate::money::Money;
+use crate::order::Order;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lot {
+  pub id: u64,
+  pub item: Perishable,
+  pub received: Date,
+  // the first day it's no good
+  pub expires: Date,
+  pub received_quantity: u32,
+  pub remaining: u32,
+  // what the quantities are counted in
+  pub unit: Unit,
+  // what the whole lot cost
+  pub cost: Money,
+}
+impl Lot {
+  pub fn is_expired(&self, date: Date) -> bool {
+    date >= self.expires
+  }
+
+  // what `quantity` of this lot cost, to the nearest cent
+  pub fn value_of(&self, quantity: u32) -> Money {
+    if self.received_quantity == 0 {
+      return Money::ZERO;
+    }
+    let cents = i128::from(self.cost.cents()) * i128::from(quantity);
+    let received = i128::from(self.received_quantity);
+    Money::from_cents(((cents * 2 + received) / (received * 2)) as i64)
+  }
+}
+
+// stock thrown away because it expired
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOff {
+  pub lot: u64,
+  pub item: Perishable,
+  pub quantity: u32,
+  pub unit: Unit,
+  pub value: Money,
+  pub on: Date,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PerishableStock {
+  // in the order they were received
+  lots: Vec<Lot>,
+  next_id: u64,
+  write_offs: Vec<WriteOff>,
+}
+impl PerishableStock {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // a delivery that keeps as long as its kind usually does. hands back the lot's id
+  pub fn receive(&mut self, item: Perishable, quantity: u32, cost: Money, received: Date) -> u64 {
+    let days = shelf_life_days(&item);
+    self.receive_with_shelf_life(item, quantity, cost, received, days)
+  }
+
+  // a delivery with its own shelf life, e.g. milk that came in with a short date
+  pub fn receive_with_shelf_life(
+    &mut self,
+    item: Perishable,
+    quantity: u32,
+    cost: Money,
+    received: Date,
+    days: u32,
+  ) -> u64 {
+    self.next_id += 1;
+    let unit = unit_of(&item);
+    self.lots.push(Lot {
+      id: self.next_id,
+      item,
+      received,
+      expires: received.add_days(i64::from(days)),
+      received_quantity: quantity,
+      remaining: quantity,
+      unit,
+      cost,
+    });
+    self.next_id
+  }
+
+  pub fn from_config(text: &str) -> Result<Self, ConfigError> {
+    let mut stock = Self::new();
+    for section in config::parse(text)? {
+      let error = |message: String| ConfigError::new(section.line, message);
+      let item =
+        Perishable::from_name(&section.name).ok_or_else(|| error(format!("\"{}\" doesn't spoil", section.name)))?;
+      let value = |key: &str| section.get(key).ok_or_else(|| error(format!("{} has no {}", item, key)));
+      let bad = |key: &str, value: &str| {
+        ConfigError::new(section.line_of(key), format!("bad {} for {} \"{}\"", key, item, value))
+      };
+      let quantity = value("quantity")?;
+      let quantity = quantity.parse().map_err(|_| bad("quantity", quantity))?;
+      let cost = value("cost")?;
+      let cost = cost.parse().map_err(|_| bad("cost", cost))?;
+      let received = value("received")?;
+      let received = received.parse().map_err(|_| bad("received", received))?;
+      let days = match section.get("shelf_life_days") {
+        Some(days) => days.parse().map_err(|_| bad("shelf_life_days", days))?,
+        None => shelf_life_days(&item),
+      };
+      stock.receive_with_shelf_life(item, quantity, cost, received, days);
+    }
+    Ok(stock)
+  }
+
+  // every lot with something left in it, oldest first. expired lots stay here until they're
+  // written off
+  pub fn lots(&self) -> &[Lot] {
+    &self.lots
+  }
+
+  pub fn write_offs(&self) -> &[WriteOff] {
+    &self.write_offs
+  }
+
+  // how much is still good on `date`. each lot fits a u32, but a few of them together may not
+  pub fn on_hand(&self, item: &Perishable, date: Date) -> u64 {
+    self.usable(item, date).map(|lot| u64::from(lot.remaining)).sum()
+  }
+
+  // lots that are still good but won't be in `days` days: use these first, or mark them down
+  pub fn expiring_within(&self, date: Date, days: u32) -> Vec<&Lot> {
+    let soon = date.add_days(i64::from(days));
+    self.lots.iter().filter(|lot| !lot.is_expired(date) && lot.is_expired(soon)).collect()
+  }
+
+  // throw out everything that's expired by `date`, handing back what went
+  pub fn write_off_expired(&mut self, date: Date) -> Vec<WriteOff> {
+    let mut written_off = Vec::new();
+    self.lots.retain(|lot| {
+      if !lot.is_expired(date) {
+        return true;
+      }
+      written_off.push(WriteOff {
+        lot: lot.id,
+        item: lot.item.clone(),
+        quantity: lot.remaining,
+        unit: lot.unit,
+        value: lot.value_of(lot.remaining),
+        on: date,
+      });
+      false
+    });
+    self.write_offs.extend(written_off.iter().cloned());
+    written_off
+  }
+
+  // take `quantity` of something, oldest lot first. hands back how much came out of each lot
+  pub fn consume(&mut self, item: &Perishable, quantity: u32, date: Date) -> Result<Vec<(u64, u32)>, ExpiryError> {
+    self.write_off_expired(date);
+    let available = self.on_hand(item, date);
+    if u64::from(quantity) > available {
+      return Err(ExpiryError { item: item.clone(), needed: quantity, available });
+    }
+    Ok(self.take(item, quantity))
+  }
+
+  // whether there's enough in date on `date` for the whole order, without taking any of it
+  pub fn check(&self, order: &Order, date: Date) -> Result<(), ExpiryError> {
+    self.needed_for(order, date).map(drop)
+  }
+
+  // the milk and produce that go into an order, all of it or none of it
+  pub fn fulfill(&mut self, order: &Order, date: Date) -> Result<(), ExpiryError> {
+    self.write_off_expired(date);
+    for (item, amount) in self.needed_for(order, date)? {
+      self.take(&item, amount);
+    }
+    Ok(())
+  }
+
+  // everything perishable the order uses, once it's known there's enough of all of it
+  fn needed_for(&self, order: &Order, date: Date) -> Result<Vec<(Perishable, u32)>, ExpiryError> {
+    let mut needed: Vec<(Perishable, u32)> = Vec::new();
+    let mut need = |item: Perishable, amount: u32| match needed.iter_mut().find(|(wanted, _)| *wanted == item) {
+      Some((_, total)) => *total = total.saturating_add(amount),
+      None => needed.push((item, amount)),
+    };
+    for line in order.lines() {
+      for (ingredient, amount) in Recipe::for_drink(&line.item).times(line.quantity).ingredients() {
+        if let Ingredient::Milk(milk) = ingredient {
+          need(Perishable::Milk(milk), amount);
+        }
+      }
+    }
+    for line in order.produce() {
+      let amount = match line.measure {
+        Measure::Units(count) => count,
+        Measure::Grams(grams) => grams,
+      };
+      need(Perishable::Produce(line.item.clone()), amount);
+    }
+    for (item, amount) in &needed {
+      let available = self.on_hand(item, date);
+      if u64::from(*amount) > available {
+        return Err(ExpiryError { item: item.clone(), needed: *amount, available });
+      }
+    }
+    Ok(needed)
+  }
+
+  fn usable<'a>(&'a self, item: &'a Perishable, date: Date) -> impl Iterator<Item = &'a Lot> {
+    self.lots.iter().filter(move |lot| lot.item == *item && !lot.is_expired(date))
+  }
+
+  // only called once there's known to be enough, with everything expired already gone
+  fn take(&mut self, item: &Perishable, quantity: u32) -> Vec<(u64, u32)> {
+    let mut left = quantity;
+    let mut taken = Vec::new();
+    for lot in self.lots.iter_mut().filter(|lot| lot.item == *item) {
+      if left == 0 {
+        break;
+      }
+      let amount = left.min(lot.remaining);
+      lot.remaining -= amount;
+      left -= amount;
+      taken.push((lot.id, amount));
+    }
+    self.lots.retain(|lot| lot.remaining > 0);
+    taken
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiryError {
+  pub item: Perishable,
+  pub needed: u32,
+  // what's left that's still good
+  pub available: u64,
+}
+impl fmt::Display for ExpiryError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "not enough {} in date: {} needed, {} left", self.item, self.needed, self.available)
+  }
+}
+impl std::error::Error for ExpiryError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::custom::Milk;
+  use crate::felicity_farmstand::{Farmstand, Vegetables};
+  use crate::owen_oblivion::WasteReport;
+  use crate::{CoffeeFlavor, Drinks};
+
+  fn day(day: u32) -> Date {
+    Date::new(2024, 5, day).unwrap()
+  }
+
+  const WHOLE: Perishable = Perishable::Milk(Milk::Whole);
+
+  #[test]
+  fn oldest_stock_goes_first() {
+    let mut stock = PerishableStock::new();
+    let first = stock.receive(WHOLE, 2_000, Money::from_cents(300), day(1));
+    let second = stock.receive(WHOLE, 2_000, Money::from_cents(300), day(3));
+    assert_eq!(stock.consume(&WHOLE, 2_500, day(4)), Ok(vec![(first, 2_000), (second, 500)]));
+    assert_eq!(stock.on_hand(&WHOLE, day(4)), 1_500);
+    assert_eq!(stock.lots().len(), 1);
+  }
+
+  #[test]
+  fn lots_add_up_past_what_one_can_hold() {
+    let mut stock = PerishableStock::new();
+    for _ in 0..3 {
+      stock.receive(WHOLE, u32::MAX, Money::from_cents(100), day(1));
+    }
+    assert_eq!(stock.on_hand(&WHOLE, day(2)), 3 * u64::from(u32::MAX));
+    assert_eq!(stock.consume(&WHOLE, u32::MAX, day(2)).unwrap().len(), 1);
+    stock.write_off_expired(day(30));
+    let report = WasteReport::new(day(30), day(30), stock.write_offs());
+    assert_eq!(report.per_item[0].1, 2 * u64::from(u32::MAX));
+  }
+
+  #[test]
+  fn expired_lots_are_written_off_not_used() {
+    let mut stock = PerishableStock::new();
+    // whole milk keeps 10 days, so this is no good from the 11th
+    let old = stock.receive(WHOLE, 1_000, Money::from_cents(200), day(1));
+    stock.receive(WHOLE, 1_000, Money::from_cents(200), day(9));
+    assert_eq!(stock.expiring_within(day(8), 3).iter().map(|lot| lot.id).collect::<Vec<_>>(), vec![old]);
+
+    let err = stock.consume(&WHOLE, 1_500, day(11)).unwrap_err();
+    assert_eq!(err.to_string(), "not enough whole milk in date: 1500 needed, 1000 left");
+    let written_off = stock.write_offs();
+    assert_eq!(written_off.len(), 1);
+    assert_eq!((written_off[0].lot, written_off[0].quantity), (old, 1_000));
+    assert_eq!(written_off[0].value, Money::from_cents(200));
+  }
+
+  #[test]
+  fn reads_the_lots_on_hand() {
+    let text = "[whole milk]\nquantity = 4000\ncost = 6.00\nreceived = 2024-05-01\n\n\
+      [dill pickles]\nquantity = 12\ncost = 48.00\nreceived = 2024-05-02\nshelf_life_days = 30\n";
+    let stock = PerishableStock::from_config(text).unwrap();
+    let lots = stock.lots();
+    assert_eq!((&lots[0].item, lots[0].remaining, lots[0].expires), (&WHOLE, 4_000, day(11)));
+    assert_eq!(lots[1].item, Perishable::Produce(Vegetables::pickle("dill")));
+    assert_eq!((lots[1].cost, lots[1].expires), (Money::from_cents(4_800), Date::new(2024, 6, 1).unwrap()));
+
+    let err = PerishableStock::from_config("[cups]\nquantity = 10").unwrap_err();
+    assert_eq!(err.to_string(), "line 1: \"cups\" doesn't spoil");
+    let err = PerishableStock::from_config("[oat milk]\nquantity = 10\ncost = 2.00").unwrap_err();
+    assert_eq!(err.to_string(), "line 1: oat milk has no received");
+    let err = PerishableStock::from_config("[oat milk]\nquantity = 10\ncost = 2.00\nreceived = soon").unwrap_err();
+    assert_eq!(err.to_string(), "line 4: bad received for oat milk \"soon\"");
+  }
+
+  #[test]
+  fn orders_take_their_milk_and_produce() {
+    let stand = Farmstand::standard();
+    let mut order = Order::new();
+    order.add(Drinks::Coffee(CoffeeFlavor::Latte), 2).unwrap();
+    order.add_produce(stand.weigh(&Vegetables::Beets, 400).unwrap()).unwrap();
+    let beets = Perishable::Produce(Vegetables::Beets);
+
+    let mut stock = PerishableStock::new();
+    stock.receive(WHOLE, 1_000, Money::from_cents(200), day(1));
+    stock.receive(beets.clone(), 300, Money::from_cents(90), day(1));
+    let err = stock.fulfill(&order, day(2)).unwrap_err();
+    assert_eq!(err.item, beets);
+    // nothing came out, since the beets were short
+    assert_eq!(stock.on_hand(&WHOLE, day(2)), 1_000);
+
+    stock.receive(beets.clone(), 5_000, Money::from_cents(1_500), day(2));
+    stock.fulfill(&order, day(2)).unwrap();
+    assert_eq!(stock.on_hand(&WHOLE, day(2)), 1_000 - 480);
+    assert_eq!(stock.on_hand(&beets, day(2)), 4_900);
+  }
+}
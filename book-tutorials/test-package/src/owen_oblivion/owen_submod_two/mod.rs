@@ -0,0 +1,95 @@
+// the waste report: what spoiled over a stretch of days, and what it cost.
+
+use std::fmt;
+
+use super::owen_submod_one::WriteOff;
+use super::{Perishable, Unit};
+use crate::date::Date;
+use crate::money::Money;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasteReport {
+  pub from: Date,
+  pub to: Date,
+  // what was thrown out of each thing and what it's counted in, costliest first (ties by name)
+  pub per_item: Vec<(Perishable, u64, Unit, Money)>,
+  pub lots: usize,
+  pub total: Money,
+}
+impl WasteReport {
+  // the write-offs from `from` to `to`, both included
+  pub fn new(from: Date, to: Date, write_offs: &[WriteOff]) -> Self {
+    let write_offs: Vec<&WriteOff> = write_offs.iter().filter(|w| (from..=to).contains(&w.on)).collect();
+    let mut per_item: Vec<(Perishable, u64, Unit, Money)> = Vec::new();
+    for write_off in &write_offs {
+      match per_item.iter_mut().find(|(item, _, unit, _)| *item == write_off.item && *unit == write_off.unit) {
+        Some((_, quantity, _, value)) => {
+          *quantity += u64::from(write_off.quantity);
+          *value += write_off.value;
+        }
+        None => {
+          let quantity = u64::from(write_off.quantity);
+          per_item.push((write_off.item.clone(), quantity, write_off.unit, write_off.value));
+        }
+      }
+    }
+    per_item.sort_by(|(a, _, _, a_value), (b, _, _, b_value)| b_value.cmp(a_value).then(a.cmp(b)));
+    Self {
+      from,
+      to,
+      lots: write_offs.len(),
+      total: per_item.iter().map(|(_, _, _, value)| *value).sum(),
+      per_item,
+    }
+  }
+}
+impl fmt::Display for WasteReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.from == self.to {
+      writeln!(f, "waste for {}", self.from)?;
+    } else {
+      writeln!(f, "waste from {} to {}", self.from, self.to)?;
+    }
+    writeln!(f, "{:<22}{}", "lots written off:", self.lots)?;
+    writeln!(f, "{:<22}{}", "cost of waste:", self.total)?;
+    if !self.per_item.is_empty() {
+      writeln!(f, "\nper item:")?;
+      for (item, quantity, unit, value) in &self.per_item {
+        writeln!(f, "  {:<28}{:>8}{:<3}{:>10}", item.to_string(), quantity, unit.to_string(), value.to_string())?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::custom::Milk;
+  use crate::felicity_farmstand::Vegetables;
+  use crate::owen_oblivion::PerishableStock;
+
+  #[test]
+  fn adds_up_what_spoiled_in_the_range() {
+    let day = |day| Date::new(2024, 5, day).unwrap();
+    let mut stock = PerishableStock::new();
+    stock.receive(Perishable::Milk(Milk::Oat), 1_000, Money::from_cents(250), day(1));
+    stock.receive(Perishable::Produce(Vegetables::Asparagus), 2_000, Money::from_cents(1_400), day(1));
+    stock.receive(Perishable::Produce(Vegetables::Asparagus), 1_000, Money::from_cents(700), day(3));
+    // asparagus keeps 4 days
+    stock.write_off_expired(day(5));
+    stock.write_off_expired(day(7));
+    // oat milk keeps 21
+    stock.write_off_expired(day(30));
+
+    let report = WasteReport::new(day(1), day(10), stock.write_offs());
+    assert_eq!(report.lots, 2);
+    assert_eq!(report.total, Money::from_cents(2_100));
+    let asparagus = Perishable::Produce(Vegetables::Asparagus);
+    assert_eq!(report.per_item, vec![(asparagus, 3_000, Unit::Grams, Money::from_cents(2_100))]);
+    // weighed, so it's grams and not 3000 pieces
+    assert!(report.to_string().contains("  Asparagus                       3000 g     $21.00\n"));
+    assert!(report.to_string().starts_with("waste from 2024-05-01 to 2024-05-10\nlots written off:     2\n"));
+    assert_eq!(WasteReport::new(day(1), day(31), stock.write_offs()).total, Money::from_cents(2_350));
+  }
+}
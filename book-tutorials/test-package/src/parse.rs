@@ -0,0 +1,220 @@
+// parsing drinks from whatever staff type into the till.
+// names are matched case-insensitively, a handful of aliases are accepted, and small typos are
+// forgiven as long as they only resemble one drink. anything else becomes a ParseDrinkError that
+// carries the closest matches so the till can say "did you mean ...?"
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{CoffeeFlavor, Drinks};
+
+// every spelling we accept on purpose, already normalized (see normalize below)
+const ALIASES: &[(&str, Drinks)] = &[
+  ("water", Drinks::Water),
+  ("still water", Drinks::Water),
+  ("tap water", Drinks::Water),
+  ("h2o", Drinks::Water),
+  ("soda", Drinks::Soda),
+  ("pop", Drinks::Soda),
+  ("soft drink", Drinks::Soda),
+  ("beer", Drinks::Beer),
+  ("lager", Drinks::Beer),
+  ("ale", Drinks::Beer),
+  ("mocha", Drinks::Coffee(CoffeeFlavor::Mocha)),
+  ("cafe mocha", Drinks::Coffee(CoffeeFlavor::Mocha)),
+  ("mochaccino", Drinks::Coffee(CoffeeFlavor::Mocha)),
+  ("latte", Drinks::Coffee(CoffeeFlavor::Latte)),
+  ("cafe latte", Drinks::Coffee(CoffeeFlavor::Latte)),
+  ("caffe latte", Drinks::Coffee(CoffeeFlavor::Latte)),
+  ("cappuccino", Drinks::Coffee(CoffeeFlavor::Capuchino)),
+  ("capuchino", Drinks::Coffee(CoffeeFlavor::Capuchino)),
+  ("capp", Drinks::Coffee(CoffeeFlavor::Capuchino)),
+  ("americano", Drinks::Coffee(CoffeeFlavor::Americano)),
+  ("caffe americano", Drinks::Coffee(CoffeeFlavor::Americano)),
+  ("long black", Drinks::Coffee(CoffeeFlavor::Americano)),
+];
+
+// the most suggestions an error will carry
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDrinkError {
+  input: String,
+  suggestions: Vec<Drinks>,
+}
+impl ParseDrinkError {
+  // the text that failed to parse, exactly as it was given
+  pub fn input(&self) -> &str {
+    &self.input
+  }
+
+  // the closest drinks to the input, best match first. may be empty
+  pub fn suggestions(&self) -> &[Drinks] {
+    &self.suggestions
+  }
+}
+impl fmt::Display for ParseDrinkError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "unknown drink \"{}\"", self.input)?;
+    if !self.suggestions.is_empty() {
+      let names: Vec<String> = self.suggestions.iter().map(|d| d.to_string()).collect();
+      write!(f, "; did you mean {}?", names.join(", "))?;
+    }
+    Ok(())
+  }
+}
+impl Error for ParseDrinkError {}
+
+impl FromStr for Drinks {
+  type Err = ParseDrinkError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    parse_drink(s, |_| true)
+  }
+}
+
+impl FromStr for CoffeeFlavor {
+  type Err = ParseDrinkError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match parse_drink(s, |drink| matches!(drink, Drinks::Coffee(_)))? {
+      Drinks::Coffee(flavor) => Ok(flavor),
+      _ => unreachable!("only coffees are considered"),
+    }
+  }
+}
+
+// lowercase, turn punctuation into spaces, collapse runs of whitespace and drop a "coffee" word
+// that only restates the category ("latte coffee", "Latte (coffee)")
+fn normalize(s: &str) -> String {
+  let cleaned: String = s
+    .chars()
+    .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' })
+    .collect();
+  let words: Vec<&str> = cleaned.split_whitespace().collect();
+  let without_coffee: Vec<&str> = words.iter().copied().filter(|w| *w != "coffee").collect();
+  if without_coffee.is_empty() {
+    words.join(" ")
+  } else {
+    without_coffee.join(" ")
+  }
+}
+
+// how many single character edits (insert, delete, substitute) turn a into b
+fn edit_distance(a: &str, b: &str) -> usize {
+  let a: Vec<char> = a.chars().collect();
+  let b: Vec<char> = b.chars().collect();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+  for (i, ca) in a.iter().enumerate() {
+    let mut current = vec![i + 1; b.len() + 1];
+    for (j, cb) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(ca != cb);
+      current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+    }
+    previous = current;
+  }
+  previous[b.len()]
+}
+
+// how far off a spelling may be and still count as a typo rather than a different word
+fn typo_allowance(len: usize) -> usize {
+  match len {
+    0..=3 => 0,
+    4..=6 => 1,
+    _ => 2,
+  }
+}
+
+fn parse_drink(s: &str, allowed: impl Fn(&Drinks) -> bool) -> Result<Drinks, ParseDrinkError> {
+  let wanted = normalize(s);
+  let error = |suggestions| ParseDrinkError { input: s.to_string(), suggestions };
+
+  // the closest alias for each drink, as (distance, drink)
+  let mut closest: Vec<(usize, Drinks)> = Vec::new();
+  for (alias, drink) in ALIASES.iter().filter(|(_, drink)| allowed(drink)) {
+    let distance = edit_distance(&wanted, alias);
+    match closest.iter_mut().find(|(_, d)| d == drink) {
+      Some(entry) => entry.0 = entry.0.min(distance),
+      None => closest.push((distance, *drink)),
+    }
+  }
+  closest.sort_by_key(|(distance, _)| *distance);
+
+  match closest.as_slice() {
+    [] => Err(error(Vec::new())),
+    [(0, drink), ..] => Ok(*drink),
+    // a typo is only forgiven when it doesn't look just as much like another drink
+    [(distance, drink), rest @ ..]
+      if *distance <= typo_allowance(wanted.len())
+        && rest.first().is_none_or(|(next, _)| next > distance) =>
+    {
+      Ok(*drink)
+    }
+    _ => {
+      // "coffee" on its own is too vague to pick one, so suggest every coffee
+      let suggestions: Vec<Drinks> = if wanted == "coffee" {
+        CoffeeFlavor::ALL.iter().map(|flavor| Drinks::Coffee(*flavor)).filter(|d| allowed(d)).collect()
+      } else {
+        let limit = wanted.chars().count().max(3) / 2 + 1;
+        closest
+          .iter()
+          .filter(|(distance, _)| *distance <= limit)
+          .take(MAX_SUGGESTIONS)
+          .map(|(_, drink)| *drink)
+          .collect()
+      };
+      Err(error(suggestions))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn names_are_case_insensitive() {
+    assert_eq!("LATTE".parse::<Drinks>(), Ok(Drinks::Coffee(CoffeeFlavor::Latte)));
+    assert_eq!("  Water ".parse::<Drinks>(), Ok(Drinks::Water));
+  }
+
+  #[test]
+  fn display_names_round_trip() {
+    for drink in Drinks::all() {
+      assert_eq!(drink.to_string().parse::<Drinks>(), Ok(drink));
+    }
+  }
+
+  #[test]
+  fn aliases_and_spellings_of_cappuccino() {
+    let cappuccino = Ok(CoffeeFlavor::Capuchino);
+    assert_eq!("cappuccino".parse::<CoffeeFlavor>(), cappuccino);
+    assert_eq!("Capuchino".parse::<CoffeeFlavor>(), cappuccino);
+    assert_eq!("capucino".parse::<CoffeeFlavor>(), cappuccino);
+  }
+
+  #[test]
+  fn small_typos_are_forgiven() {
+    assert_eq!("amercano".parse::<Drinks>(), Ok(Drinks::Coffee(CoffeeFlavor::Americano)));
+    assert_eq!("lattee".parse::<Drinks>(), Ok(Drinks::Coffee(CoffeeFlavor::Latte)));
+  }
+
+  #[test]
+  fn unknown_drinks_suggest_close_matches() {
+    let err = "moca latt".parse::<Drinks>().unwrap_err();
+    assert_eq!(err.input(), "moca latt");
+    assert!(err.suggestions().contains(&Drinks::Coffee(CoffeeFlavor::Latte)));
+
+    let err = "coffee".parse::<Drinks>().unwrap_err();
+    assert_eq!(err.suggestions().len(), CoffeeFlavor::ALL.len());
+
+    let err = "tea".parse::<Drinks>().unwrap_err();
+    assert!(err.to_string().starts_with("unknown drink \"tea\""));
+  }
+
+  #[test]
+  fn coffee_flavors_reject_other_drinks() {
+    assert!("beer".parse::<CoffeeFlavor>().is_err());
+  }
+}
@@ -0,0 +1,365 @@
+// rules for selling age-restricted drinks (beer).
+// an order with anything restricted on it can only be checked out after somebody has looked at
+// the customer's ID and entered their date of birth, and only during the hours the jurisdiction
+// allows. every check - passed or refused - goes into the audit trail.
+//
+// jurisdictions are read from a config file (see config.rs):
+//
+//   [us-ca]
+//   minimum_age = 21
+//   sale_hours = 06:00-02:00
+//   utc_offset = -08:00
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::config::{self, ConfigError};
+use crate::date::{Date, Timestamp};
+use crate::ledger::csv_row;
+use crate::order::Order;
+use crate::Drinks;
+
+pub fn is_age_restricted(drink: &Drinks) -> bool {
+  matches!(drink, Drinks::Beer)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgePolicy {
+  pub jurisdiction: String,
+  pub minimum_age: u32,
+  // local time restricted drinks may be sold, as (from, until) in minutes after midnight.
+  // until may come before from for hours that run past midnight. None is any time
+  pub sale_hours: Option<(u32, u32)>,
+  // local time is UTC plus this
+  pub utc_offset_minutes: i32,
+}
+impl AgePolicy {
+  // sold at any time of day, on UTC
+  pub fn new(jurisdiction: impl Into<String>, minimum_age: u32) -> Self {
+    Self {
+      jurisdiction: jurisdiction.into(),
+      minimum_age,
+      sale_hours: None,
+      utc_offset_minutes: 0,
+    }
+  }
+
+  // the rules for `jurisdiction` out of a config file
+  pub fn from_config(text: &str, jurisdiction: &str) -> Result<Self, ConfigError> {
+    let sections = config::parse(text)?;
+    let section = sections
+      .iter()
+      .find(|section| section.name == jurisdiction)
+      .ok_or_else(|| ConfigError::new(0, format!("no rules for jurisdiction \"{}\"", jurisdiction)))?;
+    // on the key's own line, or the header's when it's missing
+    let error = |key: &str, message: String| ConfigError::new(section.line_of(key), message);
+
+    let minimum_age = section
+      .get("minimum_age")
+      .ok_or_else(|| error("minimum_age", String::from("minimum_age is required")))?;
+    let mut policy = AgePolicy::new(
+      jurisdiction,
+      minimum_age.parse().map_err(|_| error("minimum_age", format!("bad minimum_age \"{}\"", minimum_age)))?,
+    );
+    if let Some(hours) = section.get("sale_hours") {
+      let hours = parse_hours(hours)
+        .ok_or_else(|| error("sale_hours", format!("bad sale_hours \"{}\" (expected HH:MM-HH:MM)", hours)))?;
+      policy.sale_hours = Some(hours);
+    }
+    if let Some(offset) = section.get("utc_offset") {
+      policy.utc_offset_minutes = parse_utc_offset(offset)
+        .ok_or_else(|| error("utc_offset", format!("bad utc_offset \"{}\"", offset)))?;
+    }
+    Ok(policy)
+  }
+
+  // how old someone born on `date_of_birth` is on `date`, in whole years
+  pub fn age_on(date_of_birth: Date, date: Date) -> u32 {
+    let years = date.year() - date_of_birth.year();
+    let had_birthday = (date.month(), date.day()) >= (date_of_birth.month(), date_of_birth.day());
+    (years - i32::from(!had_birthday)).max(0) as u32
+  }
+
+  // may this order be sold at `at`, given the ID that was checked (if any)?
+  pub fn check(&self, order: &Order, id: Option<&IdCheck>, at: Timestamp) -> Result<(), PolicyViolation> {
+    if !order.lines().iter().any(|line| is_age_restricted(&line.item.drink())) {
+      return Ok(());
+    }
+    let local = at.add_seconds(i64::from(self.utc_offset_minutes) * 60);
+    if let Some((from, until)) = self.sale_hours {
+      let (hour, minute, _) = local.time_of_day();
+      let now = hour * 60 + minute;
+      if !within_hours(now, (from, until)) {
+        return Err(PolicyViolation::OutsideSaleHours { local_minutes: now, hours: (from, until) });
+      }
+    }
+    let id = id.ok_or(PolicyViolation::IdRequired)?;
+    let age = AgePolicy::age_on(id.date_of_birth, local.date());
+    if age < self.minimum_age {
+      return Err(PolicyViolation::Underage { age, minimum_age: self.minimum_age });
+    }
+    Ok(())
+  }
+
+  // check, and write the outcome to the audit trail. orders with nothing restricted on them
+  // aren't checked, so they aren't logged either. a check that can't be logged doesn't pass
+  pub fn authorize(
+    &self,
+    order: &Order,
+    id: Option<&IdCheck>,
+    at: Timestamp,
+    audit: &mut AuditLog,
+  ) -> Result<(), PolicyViolation> {
+    let restricted: u32 = order
+      .lines()
+      .iter()
+      .filter(|line| is_age_restricted(&line.item.drink()))
+      .map(|line| line.quantity)
+      .sum();
+    if restricted == 0 {
+      return Ok(());
+    }
+    let outcome = self.check(order, id, at);
+    let entry = AuditEntry {
+      at,
+      jurisdiction: self.jurisdiction.clone(),
+      checked_by: id.map(|id| id.checked_by.clone()),
+      date_of_birth: id.map(|id| id.date_of_birth),
+      restricted_items: restricted,
+      outcome: outcome.clone(),
+    };
+    audit
+      .record(entry)
+      .map_err(|e| PolicyViolation::AuditUnavailable(e.to_string()))?;
+    outcome
+  }
+}
+
+// "HH:MM-HH:MM" as (from, until) in minutes after midnight
+pub(crate) fn parse_hours(s: &str) -> Option<(u32, u32)> {
+  let (from, until) = s.split_once('-')?;
+  Some((parse_clock(from)? as u32, parse_clock(until)? as u32))
+}
+
+// "-08:00", "+05:30" or "05:30", in minutes
+pub(crate) fn parse_utc_offset(s: &str) -> Option<i32> {
+  let (sign, clock) = match s.strip_prefix('-') {
+    Some(clock) => (-1, clock),
+    None => (1, s.strip_prefix('+').unwrap_or(s)),
+  };
+  Some(sign * parse_clock(clock)?)
+}
+
+// is `minutes` after midnight inside the hours? until may come before from for hours that run
+// past midnight
+pub(crate) fn within_hours(minutes: u32, (from, until): (u32, u32)) -> bool {
+  if from <= until {
+    (from..until).contains(&minutes)
+  } else {
+    minutes >= from || minutes < until
+  }
+}
+
+// "HH:MM" as minutes after midnight, up to 24:00
+fn parse_clock(s: &str) -> Option<i32> {
+  let (hour, minute) = s.trim().split_once(':')?;
+  let (hour, minute): (i32, i32) = (hour.parse().ok()?, minute.parse().ok()?);
+  if (0..=24).contains(&hour) && (0..60).contains(&minute) && hour * 60 + minute <= 24 * 60 {
+    Some(hour * 60 + minute)
+  } else {
+    None
+  }
+}
+
+// a member of staff looked at an ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdCheck {
+  pub date_of_birth: Date,
+  pub checked_by: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+  IdRequired,
+  Underage { age: u32, minimum_age: u32 },
+  // local time and the allowed hours, in minutes after midnight
+  OutsideSaleHours { local_minutes: u32, hours: (u32, u32) },
+  // the audit trail couldn't be written, so the check can't be proven
+  AuditUnavailable(String),
+}
+impl fmt::Display for PolicyViolation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let clock = |minutes: u32| format!("{:02}:{:02}", minutes / 60, minutes % 60);
+    match self {
+      PolicyViolation::IdRequired => write!(f, "age-restricted items need an ID check first"),
+      PolicyViolation::Underage { age, minimum_age } => {
+        write!(f, "customer is {} and must be at least {}", age, minimum_age)
+      }
+      PolicyViolation::OutsideSaleHours { local_minutes, hours } => write!(
+        f,
+        "age-restricted items can only be sold from {} to {} (it is {})",
+        clock(hours.0),
+        clock(hours.1),
+        clock(*local_minutes)
+      ),
+      PolicyViolation::AuditUnavailable(e) => write!(f, "the ID check couldn't be logged: {}", e),
+    }
+  }
+}
+impl std::error::Error for PolicyViolation {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+  pub at: Timestamp,
+  pub jurisdiction: String,
+  // None when nobody checked an ID
+  pub checked_by: Option<String>,
+  pub date_of_birth: Option<Date>,
+  pub restricted_items: u32,
+  pub outcome: Result<(), PolicyViolation>,
+}
+
+// every ID check, kept in memory and (when given a path) appended to a CSV file as it happens:
+// timestamp,jurisdiction,checked by,date of birth,restricted items,outcome
+#[derive(Debug, Default)]
+pub struct AuditLog {
+  entries: Vec<AuditEntry>,
+  path: Option<PathBuf>,
+}
+impl AuditLog {
+  // kept in memory only
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_file(path: impl Into<PathBuf>) -> Self {
+    Self {
+      entries: Vec::new(),
+      path: Some(path.into()),
+    }
+  }
+
+  pub fn entries(&self) -> &[AuditEntry] {
+    &self.entries
+  }
+
+  pub fn record(&mut self, entry: AuditEntry) -> io::Result<()> {
+    if let Some(path) = &self.path {
+      let outcome = match &entry.outcome {
+        Ok(()) => String::from("approved"),
+        Err(violation) => format!("refused: {}", violation),
+      };
+      let row = csv_row(&[
+        entry.at.to_string(),
+        entry.jurisdiction.clone(),
+        entry.checked_by.clone().unwrap_or_default(),
+        entry.date_of_birth.map(|date| date.to_string()).unwrap_or_default(),
+        entry.restricted_items.to_string(),
+        outcome,
+      ]);
+      let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+      writeln!(file, "{}", row)?;
+    }
+    self.entries.push(entry);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::CoffeeFlavor;
+
+  const CONFIG: &str = "
+# late licence, pacific time
+[us-ca]
+minimum_age = 21
+sale_hours = 06:00-02:00
+utc_offset = -08:00
+
+[uk]
+minimum_age = 18
+";
+
+  fn date(s: &str) -> Date {
+    s.parse().unwrap()
+  }
+
+  fn utc(s: &str) -> Timestamp {
+    s.parse().unwrap()
+  }
+
+  fn id(date_of_birth: &str) -> IdCheck {
+    IdCheck { date_of_birth: date(date_of_birth), checked_by: String::from("sam") }
+  }
+
+  fn beer_order() -> Order {
+    let mut order = Order::new();
+    order.add(Drinks::Beer, 2).unwrap();
+    order
+  }
+
+  #[test]
+  fn reads_jurisdictions_from_config() {
+    let policy = AgePolicy::from_config(CONFIG, "us-ca").unwrap();
+    assert_eq!(policy.minimum_age, 21);
+    assert_eq!(policy.sale_hours, Some((6 * 60, 2 * 60)));
+    assert_eq!(policy.utc_offset_minutes, -8 * 60);
+    assert_eq!(AgePolicy::from_config(CONFIG, "uk").unwrap(), AgePolicy::new("uk", 18));
+    assert!(AgePolicy::from_config(CONFIG, "mars").is_err());
+    let err = AgePolicy::from_config("[uk]\nminimum_age = 18\nsale_hours = 10:00-late\n", "uk").unwrap_err();
+    assert_eq!(err.to_string(), "line 3: bad sale_hours \"10:00-late\" (expected HH:MM-HH:MM)");
+  }
+
+  #[test]
+  fn age_counts_whole_years() {
+    assert_eq!(AgePolicy::age_on(date("2003-05-02"), date("2024-05-01")), 20);
+    assert_eq!(AgePolicy::age_on(date("2003-05-01"), date("2024-05-01")), 21);
+  }
+
+  #[test]
+  fn only_restricted_orders_need_an_id() {
+    let policy = AgePolicy::new("uk", 18);
+    let mut coffee = Order::new();
+    coffee.add(Drinks::Coffee(CoffeeFlavor::Latte), 1).unwrap();
+    let at = utc("2024-05-01T12:00:00Z");
+    assert_eq!(policy.check(&coffee, None, at), Ok(()));
+    assert_eq!(policy.check(&beer_order(), None, at), Err(PolicyViolation::IdRequired));
+    assert_eq!(policy.check(&beer_order(), Some(&id("2006-05-01")), at), Ok(()));
+    assert_eq!(
+      policy.check(&beer_order(), Some(&id("2006-05-02")), at),
+      Err(PolicyViolation::Underage { age: 17, minimum_age: 18 })
+    );
+  }
+
+  #[test]
+  fn sale_hours_are_local_and_can_cross_midnight() {
+    let policy = AgePolicy::from_config(CONFIG, "us-ca").unwrap();
+    let adult = id("1990-01-01");
+    // 09:00 UTC is 01:00 in california - still open
+    assert_eq!(policy.check(&beer_order(), Some(&adult), utc("2024-05-01T09:00:00Z")), Ok(()));
+    // 11:00 UTC is 03:00 - closed
+    assert!(matches!(
+      policy.check(&beer_order(), Some(&adult), utc("2024-05-01T11:00:00Z")),
+      Err(PolicyViolation::OutsideSaleHours { local_minutes: 180, .. })
+    ));
+  }
+
+  #[test]
+  fn every_check_is_audited() {
+    let policy = AgePolicy::new("uk", 18);
+    let mut audit = AuditLog::new();
+    let at = utc("2024-05-01T12:00:00Z");
+    assert!(policy.authorize(&beer_order(), None, at, &mut audit).is_err());
+    assert!(policy.authorize(&beer_order(), Some(&id("1990-01-01")), at, &mut audit).is_ok());
+    assert!(policy.authorize(&Order::new(), None, at, &mut audit).is_ok());
+
+    let entries = audit.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].outcome, Err(PolicyViolation::IdRequired));
+    assert_eq!(entries[1].checked_by.as_deref(), Some("sam"));
+    assert_eq!(entries[1].restricted_items, 2);
+  }
+}
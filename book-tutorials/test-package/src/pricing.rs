@@ -0,0 +1,363 @@
+// prices for everything on the menu, and the arithmetic that turns an Order into a bill.
+// tax is charged after discounts, once on the whole of what's taxed at each rate (not per line or
+// per category) so the rounding can't add up.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::cart::Sellable;
+use crate::custom::{CustomDrink, Size};
+use crate::felicity_farmstand::ProduceLine;
+use crate::money::Money;
+use crate::order::Order;
+use crate::promo::{Discount, Occasion, Promotions};
+use crate::{CoffeeFlavor, Drinks};
+
+// what customizations add to (or take off) a drink's base price
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModifierPrices {
+  pub small: Money,
+  pub large: Money,
+  // oat, soy or almond instead of dairy
+  pub alternative_milk: Money,
+  pub extra_shot: Money,
+  pub syrup: Money,
+}
+impl ModifierPrices {
+  // customizations are free unless told otherwise
+  pub fn free() -> Self {
+    Self {
+      small: Money::ZERO,
+      large: Money::ZERO,
+      alternative_milk: Money::ZERO,
+      extra_shot: Money::ZERO,
+      syrup: Money::ZERO,
+    }
+  }
+
+  pub fn standard() -> Self {
+    Self {
+      small: Money::from_cents(-50),
+      large: Money::from_cents(75),
+      alternative_milk: Money::from_cents(60),
+      extra_shot: Money::from_cents(80),
+      syrup: Money::from_cents(50),
+    }
+  }
+}
+
+// what kind of sale something is, as far as tax goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TaxCategory {
+  // made to be eaten or drunk here: drinks
+  Prepared,
+  // food to take home and make: produce, pickles
+  Grocery,
+  // everything else
+  General,
+}
+impl TaxCategory {
+  pub fn name(&self) -> &'static str {
+    match self {
+      TaxCategory::Prepared => "prepared food",
+      TaxCategory::Grocery => "groceries",
+      TaxCategory::General => "general",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceTable {
+  // keyed by the full drink, so each coffee flavor carries its own price
+  prices: HashMap<Drinks, Money>,
+  modifiers: ModifierPrices,
+  // sales tax in basis points, e.g. 825 is 8.25%
+  tax_basis_points: u32,
+  // groceries are taxed like everything else unless they're given a rate of their own
+  grocery_tax_basis_points: Option<u32>,
+}
+impl PriceTable {
+  // an empty table with no tax and free customizations.
+  // drinks must be given a price before they can be sold
+  pub fn new() -> Self {
+    Self {
+      prices: HashMap::new(),
+      modifiers: ModifierPrices::free(),
+      tax_basis_points: 0,
+      grocery_tax_basis_points: None,
+    }
+  }
+
+  // the prices printed on the board behind the counter
+  pub fn standard() -> Self {
+    let mut table = Self::new();
+    table.set_price(Drinks::Water, Money::from_cents(100));
+    table.set_price(Drinks::Coffee(CoffeeFlavor::Mocha), Money::from_cents(475));
+    table.set_price(Drinks::Coffee(CoffeeFlavor::Latte), Money::from_cents(450));
+    table.set_price(Drinks::Coffee(CoffeeFlavor::Capuchino), Money::from_cents(425));
+    table.set_price(Drinks::Coffee(CoffeeFlavor::Americano), Money::from_cents(350));
+    table.set_price(Drinks::Soda, Money::from_cents(225));
+    table.set_price(Drinks::Beer, Money::from_cents(600));
+    table.set_modifier_prices(ModifierPrices::standard());
+    table.set_tax_basis_points(825);
+    table
+  }
+
+  pub fn set_price(&mut self, drink: Drinks, price: Money) {
+    self.prices.insert(drink, price);
+  }
+
+  pub fn price_of(&self, drink: &Drinks) -> Option<Money> {
+    self.prices.get(drink).copied()
+  }
+
+  pub fn set_modifier_prices(&mut self, modifiers: ModifierPrices) {
+    self.modifiers = modifiers;
+  }
+
+  pub fn modifier_prices(&self) -> &ModifierPrices {
+    &self.modifiers
+  }
+
+  // the price of one of this drink, made this way
+  pub fn unit_price(&self, item: &CustomDrink) -> Option<Money> {
+    let mut price = self.price_of(&item.drink())?;
+    match item.size() {
+      Size::Small => price += self.modifiers.small,
+      Size::Medium => {}
+      Size::Large => price += self.modifiers.large,
+    }
+    if item.milk().is_some_and(|milk| !milk.is_dairy()) {
+      price += self.modifiers.alternative_milk;
+    }
+    price += self.modifiers.extra_shot.checked_times(u32::from(item.extra_shots()))?;
+    price += self.modifiers.syrup.checked_times(item.syrups().len() as u32)?;
+    Some(price)
+  }
+
+  pub fn set_tax_basis_points(&mut self, basis_points: u32) {
+    self.tax_basis_points = basis_points;
+  }
+
+  pub fn tax_basis_points(&self) -> u32 {
+    self.tax_basis_points
+  }
+
+  // where groceries are taxed less (or not at all) than the general rate
+  pub fn set_grocery_tax_basis_points(&mut self, basis_points: u32) {
+    self.grocery_tax_basis_points = Some(basis_points);
+  }
+
+  pub fn tax_rate(&self, category: TaxCategory) -> u32 {
+    match category {
+      TaxCategory::Grocery => self.grocery_tax_basis_points.unwrap_or(self.tax_basis_points),
+      TaxCategory::Prepared | TaxCategory::General => self.tax_basis_points,
+    }
+  }
+
+  // the tax on a sale, given what each line came to and its tax category. bills and carts are both
+  // taxed here, and amounts charged the same rate are added up first
+  pub fn tax_on(&self, taxable: impl IntoIterator<Item = (TaxCategory, Money)>) -> Money {
+    let mut by_rate: Vec<(u32, Money)> = Vec::new();
+    for (category, amount) in taxable {
+      let rate = self.tax_rate(category);
+      match by_rate.iter_mut().find(|(each, _)| *each == rate) {
+        Some((_, subtotal)) => *subtotal += amount,
+        None => by_rate.push((rate, amount)),
+      }
+    }
+    by_rate.iter().map(|(rate, amount)| amount.basis_points(*rate)).sum()
+  }
+
+  pub fn price(&self, order: &Order) -> Result<Bill, PricingError> {
+    Ok(self.bill(self.bill_lines(order)?, order.produce().to_vec(), Vec::new()))
+  }
+
+  // price the order and take off whatever the promotions give it
+  pub fn price_with_promotions(
+    &self,
+    order: &Order,
+    promotions: &Promotions,
+    occasion: &Occasion,
+  ) -> Result<Bill, PricingError> {
+    let lines = self.bill_lines(order)?;
+    let discounts = promotions.discounts(&lines, occasion);
+    Ok(self.bill(lines, order.produce().to_vec(), discounts))
+  }
+
+  fn bill_lines(&self, order: &Order) -> Result<Vec<BillLine>, PricingError> {
+    let mut lines = Vec::new();
+    for line in order.lines() {
+      let unit_price = self
+        .unit_price(&line.item)
+        .ok_or(PricingError::Unpriced(line.item.drink()))?;
+      lines.push(BillLine {
+        item: line.item.clone(),
+        quantity: line.quantity,
+        unit_price,
+        total: unit_price
+          .checked_times(line.quantity)
+          .ok_or(PricingError::TooLarge(line.item.drink()))?,
+      });
+    }
+    Ok(lines)
+  }
+
+  // every line is taxed as the Sellable it is. promotions only ever come off drinks, so they only
+  // lower the tax on prepared food
+  fn bill(&self, lines: Vec<BillLine>, produce: Vec<ProduceLine>, discounts: Vec<Discount>) -> Bill {
+    let subtotal: Money = lines.iter().map(|line| line.total).chain(produce.iter().map(|line| line.total)).sum();
+    let discount: Money = discounts.iter().map(|discount| discount.amount).sum();
+    let taxable = lines
+      .iter()
+      .map(|line| (line.item.tax_category(), line.total))
+      .chain(produce.iter().map(|line| (line.item.tax_category(), line.total)))
+      .chain([(TaxCategory::Prepared, -discount)]);
+    let tax = self.tax_on(taxable);
+    Bill {
+      lines,
+      produce,
+      discounts,
+      subtotal,
+      tax,
+      total: subtotal - discount + tax,
+    }
+  }
+}
+impl Default for PriceTable {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BillLine {
+  pub item: CustomDrink,
+  pub quantity: u32,
+  pub unit_price: Money,
+  pub total: Money,
+}
+
+// a priced order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bill {
+  pub lines: Vec<BillLine>,
+  // from the farm stand, priced when it was counted or weighed. promotions only look at drinks
+  pub produce: Vec<ProduceLine>,
+  // promotions, in the order they were applied
+  pub discounts: Vec<Discount>,
+  // before discounts
+  pub subtotal: Money,
+  pub tax: Money,
+  pub total: Money,
+}
+impl Bill {
+  pub fn discount_total(&self) -> Money {
+    self.discounts.iter().map(|discount| discount.amount).sum()
+  }
+
+  // what every line came to, drinks first and then produce, in the order they're numbered on the
+  // cart and the receipt
+  pub fn line_totals(&self) -> Vec<Money> {
+    let drinks = self.lines.iter().map(|line| line.total);
+    drinks.chain(self.produce.iter().map(|line| line.total)).collect()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PricingError {
+  // the drink is on the order but not in the price table
+  Unpriced(Drinks),
+  // the line's total doesn't fit in the till
+  TooLarge(Drinks),
+}
+impl fmt::Display for PricingError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PricingError::Unpriced(drink) => write!(f, "{} has no price", drink),
+      PricingError::TooLarge(drink) => write!(f, "the total for {} is too large to ring up", drink),
+    }
+  }
+}
+impl std::error::Error for PricingError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::custom::Milk;
+  use crate::felicity_farmstand::{Farmstand, Vegetables};
+
+  #[test]
+  fn bill_adds_up_lines_and_tax() {
+    let mut order = Order::new();
+    order.add(Drinks::Coffee(CoffeeFlavor::Latte), 2).unwrap();
+    order.add(Drinks::Water, 1).unwrap();
+
+    let bill = PriceTable::standard().price(&order).unwrap();
+    assert_eq!(bill.subtotal, Money::from_cents(1_000));
+    assert_eq!(bill.tax, Money::from_cents(83));
+    assert_eq!(bill.total, Money::from_cents(1_083));
+  }
+
+  #[test]
+  fn tax_is_rounded_once_on_the_subtotal() {
+    // 8.25% of 6 cents is just under half a cent, which rounds down to nothing on every line.
+    // charged on the 18 cent subtotal it comes to 1.485 cents, so one cent of tax is owed
+    let mut table = PriceTable::new();
+    table.set_tax_basis_points(825);
+    let mut order = Order::new();
+    for drink in [Drinks::Water, Drinks::Soda, Drinks::Beer] {
+      table.set_price(drink, Money::from_cents(6));
+      order.add(drink, 1).unwrap();
+    }
+    let bill = table.price(&order).unwrap();
+    assert_eq!(bill.tax, Money::from_cents(1));
+  }
+
+  #[test]
+  fn groceries_are_taxed_like_everything_else_unless_given_a_rate() {
+    let mut order = Order::new();
+    order.add(Drinks::Coffee(CoffeeFlavor::Latte), 1).unwrap();
+    order.add_produce(Farmstand::standard().weigh(&Vegetables::Beets, 412).unwrap()).unwrap();
+    let mut table = PriceTable::standard();
+    // 8.25% of 4.50 + 2.47 is 57.5 cents, where taxing them apart would come to 37 + 20
+    assert_eq!(table.price(&order).unwrap().tax, Money::from_cents(58));
+    table.set_grocery_tax_basis_points(0);
+    assert_eq!(table.price(&order).unwrap().tax, Money::from_cents(37));
+  }
+
+  #[test]
+  fn customizations_change_the_unit_price() {
+    let latte = CustomDrink::builder(Drinks::Coffee(CoffeeFlavor::Latte))
+      .size(Size::Large)
+      .milk(Milk::Oat)
+      .extra_shots(2)
+      .build()
+      .unwrap();
+    // 4.50 + 0.75 large + 0.60 oat + 2 * 0.80 shots
+    assert_eq!(PriceTable::standard().unit_price(&latte), Some(Money::from_cents(745)));
+  }
+
+  #[test]
+  fn unpriced_drinks_are_an_error() {
+    let mut order = Order::new();
+    order.add(Drinks::Beer, 1).unwrap();
+    assert_eq!(PriceTable::new().price(&order), Err(PricingError::Unpriced(Drinks::Beer)));
+  }
+
+  #[test]
+  fn tax_is_charged_after_discounts() {
+    let promotions =
+      Promotions::from_config("[latte deal]\nkind = nth_item\nitems = latte\nnth = 2\npercent_off = 50").unwrap();
+    let mut order = Order::new();
+    order.add(Drinks::Coffee(CoffeeFlavor::Latte), 2).unwrap();
+    let occasion = Occasion::at(crate::date::Timestamp::from_unix_seconds(0));
+
+    let bill = PriceTable::standard().price_with_promotions(&order, &promotions, &occasion).unwrap();
+    assert_eq!(bill.subtotal, Money::from_cents(900));
+    assert_eq!(bill.discount_total(), Money::from_cents(225));
+    // 8.25% of 6.75
+    assert_eq!(bill.tax, Money::from_cents(56));
+    assert_eq!(bill.total, Money::from_cents(731));
+  }
+}
@@ -0,0 +1,554 @@
+// promotions: discounts that marketing can change from a config file (see config.rs) without a
+// code edit. each section is one rule, and its name is what the customer sees on the receipt:
+//
+//   [second latte half price]
+//   kind = nth_item
+//   items = latte
+//   nth = 2
+//   percent_off = 50
+//
+//   [coffee and a soda]
+//   kind = combo
+//   items = coffee, soda
+//   amount_off = 1.00
+//
+//   [save10]
+//   kind = coupon
+//   code = SAVE10
+//   percent_off = 10
+//   minimum = 10.00
+//
+//   [happy hour]
+//   kind = happy_hour
+//   items = beer, soda
+//   hours = 16:00-18:00
+//   utc_offset = -08:00
+//   percent_off = 25
+//
+// items name drinks the way the till does, plus "coffee" for any coffee and "any" for anything.
+// a combo is one of each of its items, so "coffee plus pastry" is just "items = coffee, pastry"
+// once pastries are on the menu.
+//
+// every rule can also set:
+//
+//   priority = 10    rules are tried highest first (default 0, ties in file order)
+//   stacks = false   the rule only applies to an order nothing else has discounted, and once it
+//                    applies nothing else does (default true)
+//
+// a drink only ever gets one item discount (nth_item, combo or happy_hour) - whichever rule
+// reaches it first. coupons come off whatever is left of the order after that, and free coffees
+// from the loyalty card (see loyalty.rs) come last, whatever the rules did.
+
+use std::fmt;
+
+use crate::config::{self, ConfigError, Section};
+use crate::date::Timestamp;
+use crate::money::Money;
+use crate::policy::{parse_hours, parse_utc_offset, within_hours};
+use crate::pricing::BillLine;
+use crate::Drinks;
+
+// which drinks a rule is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+  Drink(Drinks),
+  AnyCoffee,
+  Anything,
+}
+impl Target {
+  pub fn matches(&self, drink: &Drinks) -> bool {
+    match self {
+      Target::Drink(target) => target == drink,
+      Target::AnyCoffee => matches!(drink, Drinks::Coffee(_)),
+      Target::Anything => true,
+    }
+  }
+}
+impl fmt::Display for Target {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Target::Drink(drink) => write!(f, "{}", drink),
+      Target::AnyCoffee => f.write_str("any coffee"),
+      Target::Anything => f.write_str("anything"),
+    }
+  }
+}
+
+// how much comes off whatever a rule discounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reward {
+  // e.g. 2500 is 25% off
+  PercentOff { basis_points: u32 },
+  AmountOff(Money),
+}
+impl Reward {
+  // the discount on `price`, which is never more than the price itself
+  pub fn off(&self, price: Money) -> Money {
+    let off = match self {
+      Reward::PercentOff { basis_points } => price.basis_points(*basis_points),
+      Reward::AmountOff(amount) => *amount,
+    };
+    if off.cents() > price.cents() {
+      price
+    } else {
+      off
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleKind {
+  // every nth matching drink gets the reward, cheapest drinks first
+  NthItem { items: Target, nth: u32 },
+  // one of each target, together, gets the reward
+  Combo { items: Vec<Target> },
+  // matching drinks get the reward between these local hours (minutes after midnight)
+  HappyHour { items: Vec<Target>, hours: (u32, u32), utc_offset_minutes: i32 },
+  // the order gets the reward when the code is given and the subtotal reaches the minimum
+  Coupon { code: String, minimum: Money },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+  // printed on the receipt
+  pub name: String,
+  pub kind: RuleKind,
+  pub reward: Reward,
+  pub priority: i32,
+  pub stacks: bool,
+}
+
+// one applied rule, as a line on the bill
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discount {
+  pub name: String,
+  // how much came off, as a positive amount
+  pub amount: Money,
+}
+
+// what a rule needs to know about the sale besides what's on it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occasion {
+  pub at: Timestamp,
+  // coupon codes the customer handed over
+  pub coupons: Vec<String>,
+  // loyalty rewards being used on this order
+  pub free_coffees: u32,
+}
+impl Occasion {
+  pub fn at(at: Timestamp) -> Self {
+    Self {
+      at,
+      coupons: Vec::new(),
+      free_coffees: 0,
+    }
+  }
+}
+
+// the receipt line for a loyalty reward
+pub const FREE_COFFEE: &str = "free coffee (loyalty card)";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Promotions {
+  rules: Vec<Rule>,
+}
+impl Promotions {
+  // no promotions at all
+  pub fn new() -> Self {
+    Self { rules: Vec::new() }
+  }
+
+  pub fn from_config(text: &str) -> Result<Self, ConfigError> {
+    let mut promotions = Self::new();
+    for section in config::parse(text)? {
+      promotions.add(rule_from_section(&section)?);
+    }
+    Ok(promotions)
+  }
+
+  pub fn add(&mut self, rule: Rule) {
+    self.rules.push(rule);
+  }
+
+  pub fn rules(&self) -> &[Rule] {
+    &self.rules
+  }
+
+  pub fn has_coupon(&self, code: &str) -> bool {
+    self.rules.iter().any(|rule| match &rule.kind {
+      RuleKind::Coupon { code: rule_code, .. } => rule_code.eq_ignore_ascii_case(code),
+      _ => false,
+    })
+  }
+
+  // every discount the priced lines get, in the order the rules were applied
+  pub fn discounts(&self, lines: &[BillLine], occasion: &Occasion) -> Vec<Discount> {
+    // rules share a line between them by taking drinks off its count
+    let mut pool: Vec<Unclaimed> = lines
+      .iter()
+      .map(|line| Unclaimed {
+        drink: line.item.drink(),
+        price: line.unit_price,
+        left: line.quantity,
+      })
+      .collect();
+    let subtotal: Money = lines.iter().map(|line| line.total).sum();
+
+    let mut rules: Vec<&Rule> = self.rules.iter().collect();
+    // stable, so equal priorities keep their file order
+    rules.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+
+    let mut discounts: Vec<Discount> = Vec::new();
+    for rule in rules {
+      if !discounts.is_empty() && !rule.stacks {
+        continue;
+      }
+      let so_far: Money = discounts.iter().map(|d| d.amount).sum();
+      let (amount, claimed) = rule.evaluate(&pool, subtotal - so_far, occasion);
+      if amount.is_zero() {
+        continue;
+      }
+      for (index, count) in claimed {
+        pool[index].left -= count;
+      }
+      discounts.push(Discount {
+        name: rule.name.clone(),
+        amount,
+      });
+      if !rule.stacks {
+        break;
+      }
+    }
+    // the dearest coffees nothing else has discounted, as long as there's anything left to take off
+    for _ in 0..occasion.free_coffees {
+      let coffee = (0..pool.len())
+        .filter(|&i| pool[i].left > 0 && matches!(pool[i].drink, Drinks::Coffee(_)))
+        .max_by_key(|&i| (pool[i].price.cents(), std::cmp::Reverse(i)));
+      let left = subtotal - discounts.iter().map(|d| d.amount).sum();
+      let index = match coffee {
+        Some(index) if !left.is_zero() => index,
+        _ => break,
+      };
+      pool[index].left -= 1;
+      discounts.push(Discount {
+        name: FREE_COFFEE.to_string(),
+        amount: pool[index].price.min(left),
+      });
+    }
+    discounts
+  }
+}
+
+// what's left of one bill line for item discounts
+struct Unclaimed {
+  drink: Drinks,
+  price: Money,
+  // drinks on the line no rule has discounted yet
+  left: u32,
+}
+
+// `count` drinks' worth of a discount. never more than the lines they came off, which were
+// already priced, so this can't overflow
+fn times(amount: Money, count: u32) -> Money {
+  amount.checked_times(count).expect("no more than the bill's own lines")
+}
+
+impl Rule {
+  // how much this rule takes off, and how many drinks it used up from each line. `remaining` is
+  // what's left of the order after the rules before it
+  fn evaluate(&self, pool: &[Unclaimed], remaining: Money, occasion: &Occasion) -> (Money, Vec<(usize, u32)>) {
+    let available = |target: &Target| -> Vec<usize> {
+      (0..pool.len()).filter(|&i| pool[i].left > 0 && target.matches(&pool[i].drink)).collect()
+    };
+    match &self.kind {
+      RuleKind::NthItem { items, nth } => {
+        let nth = (*nth).max(1);
+        let mut matching = available(items);
+        // dearest first, so the nth drink of each group is the cheapest one in it
+        matching.sort_by_key(|&i| std::cmp::Reverse(pool[i].price.cents()));
+        let total: u32 = matching.iter().map(|&i| pool[i].left).sum();
+        let grouped = total - total % nth;
+        let (mut amount, mut used, mut position) = (Money::ZERO, Vec::new(), 0);
+        for i in matching {
+          let count = pool[i].left.min(grouped - position);
+          if count == 0 {
+            break;
+          }
+          // the drinks at positions nth, 2nth, ... (counting from 1) end a group
+          let rewarded = (position + count) / nth - position / nth;
+          amount += times(self.reward.off(pool[i].price), rewarded);
+          used.push((i, count));
+          position += count;
+        }
+        (amount, used)
+      }
+      RuleKind::Combo { items } => {
+        let mut left: Vec<u32> = pool.iter().map(|line| line.left).collect();
+        let mut used: Vec<(usize, u32)> = Vec::new();
+        let mut amount = Money::ZERO;
+        while !items.is_empty() {
+          // one combo: the dearest drink that fits each target, so the reward goes as far as it can
+          let mut after = left.clone();
+          let mut combo = Vec::new();
+          for target in items {
+            let pick = (0..pool.len())
+              .filter(|&i| after[i] > 0 && target.matches(&pool[i].drink))
+              .max_by_key(|&i| (pool[i].price.cents(), std::cmp::Reverse(i)));
+            match pick {
+              Some(i) => {
+                after[i] -= 1;
+                combo.push(i);
+              }
+              None => return (amount, used),
+            }
+          }
+          // the same combo comes up again until one of its lines runs short, so take that many at once
+          let uses = |i: usize| combo.iter().filter(|&&j| j == i).count() as u32;
+          let repeats = combo.iter().map(|&i| left[i] / uses(i)).min().expect("one drink per target");
+          for &i in &combo {
+            left[i] -= repeats;
+            used.push((i, repeats));
+          }
+          amount += times(self.reward.off(combo.iter().map(|&i| pool[i].price).sum()), repeats);
+        }
+        (amount, used)
+      }
+      RuleKind::HappyHour { items, hours, utc_offset_minutes } => {
+        let local = occasion.at.add_seconds(i64::from(*utc_offset_minutes) * 60);
+        let (hour, minute, _) = local.time_of_day();
+        if !within_hours(hour * 60 + minute, *hours) {
+          return (Money::ZERO, Vec::new());
+        }
+        let used: Vec<(usize, u32)> = (0..pool.len())
+          .filter(|&i| pool[i].left > 0 && items.iter().any(|target| target.matches(&pool[i].drink)))
+          .map(|i| (i, pool[i].left))
+          .collect();
+        let amount = used.iter().map(|&(i, count)| times(self.reward.off(pool[i].price), count)).sum();
+        (amount, used)
+      }
+      RuleKind::Coupon { code, minimum } => {
+        let given = occasion.coupons.iter().any(|c| c.eq_ignore_ascii_case(code));
+        if !given || remaining.cents() < minimum.cents() {
+          return (Money::ZERO, Vec::new());
+        }
+        (self.reward.off(remaining), Vec::new())
+      }
+    }
+  }
+}
+
+fn rule_from_section(section: &Section) -> Result<Rule, ConfigError> {
+  let error = |message: String| ConfigError::new(section.line, format!("[{}] {}", section.name, message));
+  // about a key, so on the key's own line (or the header's when it's missing)
+  let error_at = |key: &str, message: String| {
+    ConfigError::new(section.line_of(key), format!("[{}] {}", section.name, message))
+  };
+  let required = |key: &'static str| section.get(key).ok_or_else(|| error(format!("{} is required", key)));
+  let targets = |key: &'static str| -> Result<Vec<Target>, ConfigError> {
+    let targets = required(key)?
+      .split(',')
+      .map(|name| parse_target(name).ok_or_else(|| error_at(key, format!("unknown item \"{}\"", name.trim()))))
+      .collect::<Result<Vec<Target>, ConfigError>>()?;
+    Ok(targets)
+  };
+
+  let reward = match (section.get("percent_off"), section.get("amount_off")) {
+    (Some(percent), None) => match percent.trim_end_matches('%').trim().parse::<u32>() {
+      Ok(percent) if percent <= 100 => Reward::PercentOff { basis_points: percent * 100 },
+      _ => {
+        let message = format!("bad percent_off \"{}\" (expected a whole percentage)", percent);
+        return Err(error_at("percent_off", message));
+      }
+    },
+    (None, Some(amount)) => match amount.parse::<Money>() {
+      Ok(amount) if amount.cents() > 0 => Reward::AmountOff(amount),
+      _ => return Err(error_at("amount_off", format!("bad amount_off \"{}\"", amount))),
+    },
+    _ => return Err(error(String::from("needs exactly one of percent_off or amount_off"))),
+  };
+
+  let kind = match required("kind")? {
+    "nth_item" => {
+      let items = match targets("items")?.as_slice() {
+        [target] => *target,
+        _ => return Err(error_at("items", String::from("nth_item takes a single item"))),
+      };
+      let nth = required("nth")?;
+      match nth.parse::<u32>() {
+        Ok(nth) if nth > 0 => RuleKind::NthItem { items, nth },
+        _ => return Err(error_at("nth", format!("bad nth \"{}\"", nth))),
+      }
+    }
+    "combo" => RuleKind::Combo { items: targets("items")? },
+    "happy_hour" => {
+      let hours = required("hours")?;
+      let utc_offset_minutes = match section.get("utc_offset") {
+        Some(offset) => {
+          parse_utc_offset(offset).ok_or_else(|| error_at("utc_offset", format!("bad utc_offset \"{}\"", offset)))?
+        }
+        None => 0,
+      };
+      RuleKind::HappyHour {
+        items: targets("items")?,
+        hours: parse_hours(hours)
+          .ok_or_else(|| error_at("hours", format!("bad hours \"{}\" (expected HH:MM-HH:MM)", hours)))?,
+        utc_offset_minutes,
+      }
+    }
+    "coupon" => {
+      let minimum = match section.get("minimum") {
+        Some(minimum) => minimum.parse().map_err(|_| error_at("minimum", format!("bad minimum \"{}\"", minimum)))?,
+        None => Money::ZERO,
+      };
+      RuleKind::Coupon {
+        code: required("code")?.to_string(),
+        minimum,
+      }
+    }
+    other => return Err(error_at("kind", format!("unknown kind \"{}\"", other))),
+  };
+
+  let priority = match section.get("priority") {
+    Some(priority) => priority.parse().map_err(|_| error_at("priority", format!("bad priority \"{}\"", priority)))?,
+    None => 0,
+  };
+  let stacks = match section.get("stacks") {
+    None | Some("true") => true,
+    Some("false") => false,
+    Some(other) => return Err(error_at("stacks", format!("bad stacks \"{}\" (expected true or false)", other))),
+  };
+  Ok(Rule {
+    name: section.name.clone(),
+    kind,
+    reward,
+    priority,
+    stacks,
+  })
+}
+
+fn parse_target(name: &str) -> Option<Target> {
+  match name.trim().to_lowercase().as_str() {
+    "coffee" | "any coffee" => Some(Target::AnyCoffee),
+    "any" | "anything" => Some(Target::Anything),
+    other => other.parse().ok().map(Target::Drink),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::date::Date;
+  use crate::order::Order;
+  use crate::pricing::PriceTable;
+  use crate::CoffeeFlavor;
+
+  const PROMOTIONS: &str = "
+[second latte half price]
+kind = nth_item
+items = latte
+nth = 2
+percent_off = 50
+priority = 10
+
+[coffee and a soda]
+kind = combo
+items = coffee, soda
+amount_off = 1.00
+
+[happy hour]
+kind = happy_hour
+items = beer, soda
+hours = 16:00-18:00
+percent_off = 25
+
+[staff]
+kind = coupon
+code = STAFF
+percent_off = 50
+priority = 100
+stacks = false
+";
+
+  fn discounts(order: &[(Drinks, u32)], hour: u32, coupons: &[&str]) -> Vec<(String, i64)> {
+    let mut drinks = Order::new();
+    for (drink, quantity) in order {
+      drinks.add(*drink, *quantity).unwrap();
+    }
+    let occasion = Occasion {
+      at: Timestamp::from_date_time(Date::new(2024, 5, 1).unwrap(), hour, 30, 0),
+      coupons: coupons.iter().map(|c| c.to_string()).collect(),
+      free_coffees: 0,
+    };
+    let promotions = Promotions::from_config(PROMOTIONS).unwrap();
+    let bill = PriceTable::standard().price_with_promotions(&drinks, &promotions, &occasion).unwrap();
+    bill.discounts.into_iter().map(|d| (d.name, d.amount.cents())).collect()
+  }
+
+  const LATTE: Drinks = Drinks::Coffee(CoffeeFlavor::Latte);
+
+  #[test]
+  fn each_drink_gets_one_item_discount() {
+    // two of the three lattes go to the half price deal (it has the higher priority), which
+    // leaves one latte for the combo. the soda in the combo misses out on happy hour
+    let applied = discounts(&[(LATTE, 3), (Drinks::Soda, 1), (Drinks::Beer, 1)], 16, &[]);
+    assert_eq!(
+      applied,
+      vec![
+        (String::from("second latte half price"), 225),
+        (String::from("coffee and a soda"), 100),
+        (String::from("happy hour"), 150),
+      ]
+    );
+    // outside happy hour
+    assert_eq!(discounts(&[(Drinks::Beer, 2)], 12, &[]), vec![]);
+  }
+
+  #[test]
+  fn big_orders_are_worked_out_by_the_line() {
+    // 998 of the lattes make 499 half price pairs, which leaves one for the combo with a soda
+    let applied = discounts(&[(LATTE, 999), (Drinks::Soda, 999)], 12, &[]);
+    assert_eq!(
+      applied,
+      vec![(String::from("second latte half price"), 499 * 225), (String::from("coffee and a soda"), 100)]
+    );
+    let applied = discounts(&[(Drinks::Coffee(CoffeeFlavor::Mocha), 999), (Drinks::Soda, 999)], 12, &[]);
+    assert_eq!(applied, vec![(String::from("coffee and a soda"), 999 * 100)]);
+  }
+
+  #[test]
+  fn a_coupon_that_doesnt_stack_goes_alone() {
+    let applied = discounts(&[(LATTE, 2)], 16, &["staff"]);
+    assert_eq!(applied, vec![(String::from("staff"), 450)]);
+  }
+
+  #[test]
+  fn free_coffees_skip_drinks_already_discounted() {
+    let promotions = Promotions::from_config(PROMOTIONS).unwrap();
+    let mut order = Order::new();
+    order.add(LATTE, 2).unwrap();
+    order.add(Drinks::Coffee(CoffeeFlavor::Americano), 1).unwrap();
+    let occasion = Occasion {
+      free_coffees: 2,
+      ..Occasion::at(Timestamp::from_date_time(Date::new(2024, 5, 1).unwrap(), 9, 0, 0))
+    };
+    let bill = PriceTable::standard().price_with_promotions(&order, &promotions, &occasion).unwrap();
+    // both lattes went to the half price deal, so only the americano is left to be free
+    let free: Vec<i64> = bill.discounts.iter().filter(|d| d.name == FREE_COFFEE).map(|d| d.amount.cents()).collect();
+    assert_eq!(free, vec![350]);
+  }
+
+  #[test]
+  fn amounts_off_never_go_below_nothing() {
+    assert_eq!(Reward::AmountOff(Money::from_cents(500)).off(Money::from_cents(350)), Money::from_cents(350));
+    assert_eq!(Reward::PercentOff { basis_points: 2500 }.off(Money::from_cents(225)), Money::from_cents(56));
+  }
+
+  #[test]
+  fn config_mistakes_point_at_the_rule() {
+    let err = Promotions::from_config("[bad]\nkind = combo\nitems = latte, scone\npercent_off = 10").unwrap_err();
+    // on the line with the item, which is the line to fix
+    assert_eq!(err.to_string(), "line 3: [bad] unknown item \"scone\"");
+    let err = Promotions::from_config("[bad]\nkind = coupon\ncode = X").unwrap_err();
+    assert_eq!(err.to_string(), "line 1: [bad] needs exactly one of percent_off or amount_off");
+  }
+}
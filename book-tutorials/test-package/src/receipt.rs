@@ -0,0 +1,236 @@
+// receipts for a checked-out order, as a plain text slip for the customer, Markdown for pasting
+// into notes and JSON for the bookkeeping import. a Receipt is built once from the Bill and then
+// only rendered, so all three always agree.
+
+use crate::date::Timestamp;
+use crate::json::Json;
+use crate::money::Money;
+use crate::pricing::Bill;
+use crate::promo::Discount;
+
+// characters across the printed slip
+pub const SLIP_WIDTH: usize = 40;
+
+const SHOP_NAME: &str = "test-package cafe";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptLine {
+  pub quantity: u32,
+  pub name: String,
+  pub modifiers: Vec<String>,
+  pub unit_price: Money,
+  pub total: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+  pub issued: Timestamp,
+  pub lines: Vec<ReceiptLine>,
+  pub discounts: Vec<Discount>,
+  pub subtotal: Money,
+  pub tax: Money,
+  pub total: Money,
+}
+impl Receipt {
+  pub fn new(bill: &Bill, issued: Timestamp) -> Self {
+    let drinks = bill.lines.iter().map(|line| ReceiptLine {
+      quantity: line.quantity,
+      name: line.item.name(),
+      modifiers: line.item.modifiers(),
+      unit_price: line.unit_price,
+      total: line.total,
+    });
+    // weighed produce shows what the scale said where a drink shows its customizations
+    let produce = bill.produce.iter().map(|line| ReceiptLine {
+      quantity: line.quantity(),
+      name: line.item.name(),
+      modifiers: line.weighing().into_iter().collect(),
+      unit_price: line.unit_price(),
+      total: line.total,
+    });
+    let lines = drinks.chain(produce).collect();
+    Self {
+      issued,
+      lines,
+      discounts: bill.discounts.clone(),
+      subtotal: bill.subtotal,
+      tax: bill.tax,
+      total: bill.total,
+    }
+  }
+
+  // the slip handed to the customer: SLIP_WIDTH characters wide, prices lined up on the right
+  pub fn to_text(&self) -> String {
+    let mut out = String::new();
+    let rule = "-".repeat(SLIP_WIDTH);
+    out.push_str(&centered(SHOP_NAME));
+    out.push_str(&centered(&self.issued.to_string()));
+    out.push_str(&rule);
+    out.push('\n');
+    for line in &self.lines {
+      out.push_str(&slip_row(&format!("{} x {}", line.quantity, line.name), &line.total.to_string()));
+      for modifier in &line.modifiers {
+        out.push_str(&format!("    {}\n", modifier));
+      }
+      if line.quantity > 1 {
+        out.push_str(&format!("    @ {} each\n", line.unit_price));
+      }
+    }
+    out.push_str(&rule);
+    out.push('\n');
+    out.push_str(&slip_row("subtotal", &self.subtotal.to_string()));
+    // each promotion on its own line, between the subtotal it comes off and the tax
+    for discount in &self.discounts {
+      out.push_str(&slip_row(&discount.name, &(-discount.amount).to_string()));
+    }
+    out.push_str(&slip_row("tax", &self.tax.to_string()));
+    out.push_str(&slip_row("TOTAL", &self.total.to_string()));
+    out
+  }
+
+  pub fn to_markdown(&self) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("### {}\n\n", SHOP_NAME));
+    out.push_str(&format!("_{}_\n\n", self.issued));
+    out.push_str("| Qty | Item | Modifiers | Each | Price |\n");
+    out.push_str("|----:|------|-----------|-----:|------:|\n");
+    for line in &self.lines {
+      out.push_str(&format!(
+        "| {} | {} | {} | {} | {} |\n",
+        line.quantity,
+        markdown_escape(&line.name),
+        markdown_escape(&line.modifiers.join(", ")),
+        line.unit_price,
+        line.total
+      ));
+    }
+    out.push_str(&format!("| | **Subtotal** | | | {} |\n", self.subtotal));
+    for discount in &self.discounts {
+      out.push_str(&format!("| | {} | | | {} |\n", markdown_escape(&discount.name), -discount.amount));
+    }
+    out.push_str(&format!("| | **Tax** | | | {} |\n", self.tax));
+    out.push_str(&format!("| | **Total** | | | **{}** |\n", self.total));
+    out
+  }
+
+  // amounts are whole cents, so the import never has to parse "$4.50"
+  pub fn to_json(&self) -> Json {
+    let lines: Vec<Json> = self
+      .lines
+      .iter()
+      .map(|line| {
+        Json::object()
+          .field("quantity", line.quantity)
+          .field("name", line.name.as_str())
+          .field("modifiers", line.modifiers.clone())
+          .field("unit_price_cents", line.unit_price.cents())
+          .field("total_cents", line.total.cents())
+      })
+      .collect();
+    let discounts: Vec<Json> = self
+      .discounts
+      .iter()
+      .map(|discount| {
+        Json::object()
+          .field("name", discount.name.as_str())
+          .field("amount_cents", discount.amount.cents())
+      })
+      .collect();
+    Json::object()
+      .field("issued", self.issued.to_string())
+      .field("lines", lines)
+      .field("discounts", discounts)
+      .field("subtotal_cents", self.subtotal.cents())
+      .field("tax_cents", self.tax.cents())
+      .field("total_cents", self.total.cents())
+  }
+}
+
+// the label on the left and the amount on the right. a label too long to share the line with
+// the amount gets the amount on a line of its own
+fn slip_row(label: &str, amount: &str) -> String {
+  let label_width = label.chars().count();
+  let amount_width = amount.chars().count();
+  if label_width + 1 + amount_width <= SLIP_WIDTH {
+    format!("{}{:>width$}\n", label, amount, width = SLIP_WIDTH - label_width)
+  } else {
+    format!("{}\n{:>width$}\n", label, amount, width = SLIP_WIDTH)
+  }
+}
+
+fn centered(text: &str) -> String {
+  format!("{}\n", format!("{:^width$}", text, width = SLIP_WIDTH).trim_end())
+}
+
+fn markdown_escape(s: &str) -> String {
+  s.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::custom::{CustomDrink, Milk, Size};
+  use crate::date::Date;
+  use crate::order::Order;
+  use crate::pricing::PriceTable;
+  use crate::promo::{Occasion, Promotions};
+  use crate::{CoffeeFlavor, Drinks};
+
+  fn receipt() -> Receipt {
+    let latte = CustomDrink::builder(Drinks::Coffee(CoffeeFlavor::Latte))
+      .size(Size::Large)
+      .milk(Milk::Oat)
+      .build()
+      .unwrap();
+    let mut order = Order::new();
+    order.add(latte, 2).unwrap();
+    order.add(Drinks::Water, 1).unwrap();
+    let bill = PriceTable::standard().price(&order).unwrap();
+    Receipt::new(&bill, Timestamp::from_date_time(Date::new(2024, 5, 1).unwrap(), 9, 30, 0))
+  }
+
+  #[test]
+  fn text_slip_lines_up_prices() {
+    let text = receipt().to_text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0].trim(), SHOP_NAME);
+    assert_eq!(lines[1].trim(), "2024-05-01T09:30:00Z");
+    assert_eq!(lines[3], "2 x large Latte (coffee)          $11.70");
+    assert_eq!(lines[4], "    oat milk");
+    assert_eq!(lines[5], "    @ $5.85 each");
+    assert!(text.lines().all(|line| line.chars().count() <= SLIP_WIDTH));
+    assert!(text.ends_with("TOTAL                             $13.75\n"));
+  }
+
+  #[test]
+  fn markdown_is_a_table() {
+    let markdown = receipt().to_markdown();
+    assert!(markdown.contains("| 2 | large Latte (coffee) | oat milk | $5.85 | $11.70 |"));
+    assert!(markdown.contains("| | **Total** | | | **$13.75** |"));
+  }
+
+  #[test]
+  fn json_has_amounts_in_cents() {
+    let json = receipt().to_json().to_string();
+    assert!(json.starts_with(r#"{"issued":"2024-05-01T09:30:00Z","lines":[{"quantity":2"#));
+    assert!(json.ends_with(r#""subtotal_cents":1270,"tax_cents":105,"total_cents":1375}"#));
+  }
+
+  #[test]
+  fn discounts_get_their_own_lines() {
+    let promotions = Promotions::from_config("[two lattes]\nkind = nth_item\nitems = latte\nnth = 2\namount_off = 1")
+      .unwrap();
+    let mut order = Order::new();
+    order.add(Drinks::Coffee(CoffeeFlavor::Latte), 2).unwrap();
+    let issued = Timestamp::from_date_time(Date::new(2024, 5, 1).unwrap(), 9, 30, 0);
+    let bill = PriceTable::standard().price_with_promotions(&order, &promotions, &Occasion::at(issued)).unwrap();
+    let receipt = Receipt::new(&bill, issued);
+
+    let text = receipt.to_text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[7], "two lattes                        -$1.00");
+    assert!(text.ends_with("TOTAL                              $8.66\n"));
+    assert!(receipt.to_markdown().contains("| | two lattes | | | -$1.00 |"));
+    assert!(receipt.to_json().to_string().contains(r#""discounts":[{"name":"two lattes","amount_cents":100}]"#));
+  }
+}
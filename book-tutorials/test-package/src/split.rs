@@ -0,0 +1,276 @@
+// splitting one bill between several payers, and tips on top.
+//
+// a bill can be split evenly, by who had which line, or into amounts the payers name themselves.
+// however it's split, the shares add up to the bill's total to the cent: amounts are shared out
+// in proportion and the cents left over by rounding go to whoever lost the most to it (the
+// largest remainder method), the earliest payer winning ties.
+//
+// a tip is worked out once for the whole order and shared the same way. tips aren't revenue, so
+// they're kept apart from the bill everywhere - on the shares, in the ledger and in the reports.
+
+use std::fmt;
+
+use crate::money::Money;
+use crate::pricing::Bill;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tip {
+  // of the bill after discounts and before tax, e.g. 1500 is 15%
+  Percent { basis_points: u32 },
+  Amount(Money),
+}
+impl Tip {
+  pub fn on(&self, bill: &Bill) -> Money {
+    match self {
+      Tip::Percent { basis_points } => (bill.subtotal - bill.discount_total()).basis_points(*basis_points),
+      Tip::Amount(amount) => *amount,
+    }
+  }
+}
+// "15%", "12.5%" or "$2.50"
+impl fmt::Display for Tip {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Tip::Percent { basis_points } if basis_points % 100 == 0 => write!(f, "{}%", basis_points / 100),
+      Tip::Percent { basis_points } => {
+        let fraction = format!("{:02}", basis_points % 100);
+        write!(f, "{}.{}%", basis_points / 100, fraction.trim_end_matches('0'))
+      }
+      Tip::Amount(amount) => write!(f, "{}", amount),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Split {
+  // between this many payers
+  Evenly(u32),
+  // the payer (numbered from 1) for each line of the bill. tax and discounts are shared in
+  // proportion to what each payer's lines cost
+  ByLine(Vec<u32>),
+  // what each payer pays, which has to come to the bill's total
+  Amounts(Vec<Money>),
+}
+
+// what one payer owes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Share {
+  // numbered from 1
+  pub payer: u32,
+  pub amount: Money,
+  pub tip: Money,
+}
+impl Share {
+  pub fn paid(&self) -> Money {
+    self.amount + self.tip
+  }
+}
+
+// the whole bill paid by one payer
+pub fn single(bill: &Bill, tip: Option<Tip>) -> Share {
+  Share {
+    payer: 1,
+    amount: bill.total,
+    tip: tip.map_or(Money::ZERO, |tip| tip.on(bill)),
+  }
+}
+
+pub fn split(bill: &Bill, how: &Split, tip: Option<Tip>) -> Result<Vec<Share>, SplitError> {
+  let amounts = match how {
+    Split::Evenly(0) => return Err(SplitError::NoPayers),
+    // nobody pays less than a cent, unless the bill is nothing at all
+    Split::Evenly(payers) if i64::from(*payers) > bill.total.cents().max(1) => {
+      return Err(SplitError::TooManyPayers { payers: *payers, most: bill.total.cents().max(1) as u64 })
+    }
+    Split::Evenly(payers) => allocate(bill.total, &vec![1; *payers as usize]),
+    Split::ByLine(payers) => {
+      let totals = bill.line_totals();
+      if payers.len() != totals.len() {
+        return Err(SplitError::WrongLineCount { lines: totals.len(), given: payers.len() });
+      }
+      if payers.contains(&0) {
+        return Err(SplitError::NoSuchPayer);
+      }
+      let count = payers.iter().copied().max().ok_or(SplitError::NoPayers)?;
+      // every payer needs a line of their own
+      if count as usize > totals.len() {
+        return Err(SplitError::TooManyPayers { payers: count, most: totals.len() as u64 });
+      }
+      let mut weights = vec![0; count as usize];
+      for (total, payer) in totals.iter().zip(payers) {
+        weights[*payer as usize - 1] += total.cents();
+      }
+      if let Some(index) = weights.iter().position(|weight| *weight == 0) {
+        return Err(SplitError::NothingToPay(index as u32 + 1));
+      }
+      allocate(bill.total, &weights)
+    }
+    Split::Amounts(amounts) => {
+      if amounts.is_empty() {
+        return Err(SplitError::NoPayers);
+      }
+      if amounts.iter().any(|amount| amount.cents() < 0) {
+        return Err(SplitError::NegativeAmount);
+      }
+      let given = amounts
+        .iter()
+        .try_fold(Money::ZERO, |total, amount| total.checked_add(*amount))
+        .ok_or(SplitError::TooLarge)?;
+      if given != bill.total {
+        return Err(SplitError::DoesntAddUp { given, total: bill.total });
+      }
+      amounts.clone()
+    }
+  };
+  let tip = tip.map_or(Money::ZERO, |tip| tip.on(bill));
+  let weights: Vec<i64> = amounts.iter().map(Money::cents).collect();
+  // a bill that comes to nothing still shares its tip evenly
+  let tips = if weights.iter().all(|w| *w == 0) {
+    allocate(tip, &vec![1; weights.len()])
+  } else {
+    allocate(tip, &weights)
+  };
+  Ok(amounts
+    .into_iter()
+    .zip(tips)
+    .enumerate()
+    .map(|(index, (amount, tip))| Share {
+      payer: index as u32 + 1,
+      amount,
+      tip,
+    })
+    .collect())
+}
+
+// `total` shared out in proportion to `weights` so that the parts add up to it exactly. weights
+// can't be negative, and at least one has to be more than zero
+pub fn allocate(total: Money, weights: &[i64]) -> Vec<Money> {
+  let sum: i64 = weights.iter().sum();
+  assert!(sum > 0 && weights.iter().all(|w| *w >= 0), "allocate needs positive weights");
+  let total = total.cents();
+  // i128 so a big total times a big weight can't overflow
+  let exact: Vec<(i64, i64)> = weights
+    .iter()
+    .map(|weight| {
+      let scaled = i128::from(total) * i128::from(*weight);
+      let part = scaled.div_euclid(i128::from(sum));
+      (part as i64, scaled.rem_euclid(i128::from(sum)) as i64)
+    })
+    .collect();
+  let mut parts: Vec<i64> = exact.iter().map(|(part, _)| *part).collect();
+  let short = total - parts.iter().sum::<i64>();
+  let mut by_remainder: Vec<usize> = (0..parts.len()).collect();
+  by_remainder.sort_by_key(|&i| std::cmp::Reverse(exact[i].1));
+  for &index in by_remainder.iter().take(short as usize) {
+    parts[index] += 1;
+  }
+  parts.into_iter().map(Money::from_cents).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitError {
+  NoPayers,
+  // payers are numbered from 1
+  NoSuchPayer,
+  WrongLineCount { lines: usize, given: usize },
+  // more payers than there are cents (split evenly) or lines (split by line) to go round
+  TooManyPayers { payers: u32, most: u64 },
+  // a payer number was skipped when splitting by line
+  NothingToPay(u32),
+  NegativeAmount,
+  // the amounts add up to more than can be counted
+  TooLarge,
+  DoesntAddUp { given: Money, total: Money },
+}
+impl fmt::Display for SplitError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SplitError::NoPayers => write!(f, "a bill needs at least one payer"),
+      SplitError::NoSuchPayer => write!(f, "payers are numbered from 1"),
+      SplitError::WrongLineCount { lines, given } => {
+        write!(f, "the bill has {} line(s) but {} payer(s) were given for them", lines, given)
+      }
+      SplitError::TooManyPayers { payers, most } => {
+        write!(f, "this bill can't be split between {} payers (at most {})", payers, most)
+      }
+      SplitError::NothingToPay(payer) => write!(f, "payer {} has no lines to pay for", payer),
+      SplitError::NegativeAmount => write!(f, "nobody can pay less than nothing"),
+      SplitError::TooLarge => write!(f, "the amounts add up to more than the till can count"),
+      SplitError::DoesntAddUp { given, total } => {
+        write!(f, "the amounts come to {} but the bill is {}", given, total)
+      }
+    }
+  }
+}
+impl std::error::Error for SplitError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::order::Order;
+  use crate::pricing::PriceTable;
+  use crate::{CoffeeFlavor, Drinks};
+
+  fn bill() -> Bill {
+    let mut order = Order::new();
+    order.add(Drinks::Coffee(CoffeeFlavor::Latte), 1).unwrap();
+    order.add(Drinks::Beer, 1).unwrap();
+    order.add(Drinks::Water, 1).unwrap();
+    // 11.50 + 0.95 tax
+    PriceTable::standard().price(&order).unwrap()
+  }
+
+  fn amounts(shares: &[Share]) -> Vec<(i64, i64)> {
+    shares.iter().map(|share| (share.amount.cents(), share.tip.cents())).collect()
+  }
+
+  #[test]
+  fn even_splits_add_up_to_the_cent() {
+    let shares = split(&bill(), &Split::Evenly(3), Some(Tip::Percent { basis_points: 1500 })).unwrap();
+    // 12.45 three ways, and a 1.73 tip three ways
+    assert_eq!(amounts(&shares), vec![(415, 58), (415, 58), (415, 57)]);
+    for payers in 1..=7 {
+      let shares = split(&bill(), &Split::Evenly(payers), None).unwrap();
+      assert_eq!(shares.iter().map(|s| s.amount).sum::<Money>(), bill().total);
+    }
+  }
+
+  #[test]
+  fn by_line_shares_tax_in_proportion() {
+    let shares = split(&bill(), &Split::ByLine(vec![1, 2, 1]), Some(Tip::Amount(Money::from_cents(200)))).unwrap();
+    // payer 1 had 5.50 of the 11.50, payer 2 had 6.00
+    assert_eq!(amounts(&shares), vec![(595, 96), (650, 104)]);
+    assert_eq!(split(&bill(), &Split::ByLine(vec![1, 3, 1]), None), Err(SplitError::NothingToPay(2)));
+    assert!(matches!(split(&bill(), &Split::ByLine(vec![1]), None), Err(SplitError::WrongLineCount { .. })));
+  }
+
+  #[test]
+  fn payers_are_capped_before_anything_is_shared_out() {
+    let err = split(&bill(), &Split::Evenly(u32::MAX), None).unwrap_err();
+    assert_eq!(err, SplitError::TooManyPayers { payers: u32::MAX, most: 1245 });
+    assert_eq!(split(&bill(), &Split::Evenly(1245), None).unwrap().len(), 1245);
+    let err = split(&bill(), &Split::ByLine(vec![1, 2, u32::MAX]), None).unwrap_err();
+    assert_eq!(err.to_string(), "this bill can't be split between 4294967295 payers (at most 3)");
+  }
+
+  #[test]
+  fn custom_amounts_must_cover_the_bill() {
+    let custom = Split::Amounts(vec![Money::from_cents(1000), Money::from_cents(245)]);
+    assert_eq!(amounts(&split(&bill(), &custom, None).unwrap()), vec![(1000, 0), (245, 0)]);
+    let short = Split::Amounts(vec![Money::from_cents(1000)]);
+    assert_eq!(
+      split(&bill(), &short, None).unwrap_err().to_string(),
+      "the amounts come to $10.00 but the bill is $12.45"
+    );
+    let overflowing = Split::Amounts(vec![Money::from_cents(i64::MAX), Money::from_cents(1)]);
+    assert_eq!(split(&bill(), &overflowing, None), Err(SplitError::TooLarge));
+  }
+
+  #[test]
+  fn leftover_cents_go_to_the_largest_remainders() {
+    let parts = allocate(Money::from_cents(100), &[1, 1, 1]);
+    assert_eq!(parts, vec![Money::from_cents(34), Money::from_cents(33), Money::from_cents(33)]);
+    let parts = allocate(Money::from_cents(10), &[1, 2, 7]);
+    assert_eq!(parts, vec![Money::from_cents(1), Money::from_cents(2), Money::from_cents(7)]);
+  }
+}
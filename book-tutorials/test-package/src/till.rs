@@ -0,0 +1,1029 @@
+// the till: a little command language for ringing up orders, and a loop that reads those
+// commands from any input and writes its replies to any output. the till binary hooks it up to
+// stdin/stdout, so it can be used by hand or fed a script.
+//
+//   menu                      what we sell, what it costs and what's in it
+//   add [qty] <drink> [opts]  e.g. "add 2 latte large oat extra shot vanilla iced"
+//   add [qty] <produce>       produce sold by the piece, e.g. "add 2 squash" or "add dill pickles"
+//   weigh <grams> <produce>   produce sold by weight, off the scale, e.g. "weigh 412 beets"
+//   remove <line>             take a line off the cart, numbered as shown by "cart"
+//   cart                      what's been rung up so far
+//   id <YYYY-MM-DD>           record the date of birth on the customer's ID
+//   coupon <code>             use a coupon on the current order
+//   split <how>               share the bill: "even 3", "lines 1 2 1" (a payer for each line),
+//                             "amounts 10.00 5.45", or "none"
+//   tip <amount|percent>      e.g. "tip 2.50", "tip 15%" or "tip none"
+//   member <phone|card>       find the customer's loyalty profile for this order
+//   join <phone> <name>       sign the customer up to the loyalty card
+//   redeem                    use one of the member's free coffees on this order
+//   checkout                  print the receipt and start a new order
+//   waste [days]              write off what's expired and show what spoiled over the last 7 days, or
+//                             however many are given
+//   quit                      close the till
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use crate::custom::{CustomDrink, CustomizeError, Milk, Size, Syrup, Temperature};
+use crate::date::{Date, Timestamp};
+use crate::felicity_farmstand::{Availability, Farmstand, Pricing, Vegetables};
+use crate::inventory::StockLedger;
+use crate::ledger::Ledger;
+use crate::loyalty::CustomerBook;
+use crate::money::Money;
+use crate::nutrition::Nutrition;
+use crate::order::{Order, MAX_QUANTITY};
+use crate::owen_oblivion::{PerishableStock, WasteReport};
+use crate::policy::{AgePolicy, AuditLog, IdCheck};
+use crate::pricing::{Bill, PriceTable, PricingError};
+use crate::promo::{Occasion, Promotions, FREE_COFFEE};
+use crate::split::{self, Share, Split, Tip};
+use crate::receipt::Receipt;
+use crate::{Drinks, ParseDrinkError};
+
+const HELP: &str = "commands: menu, add [qty] <drink> [size] [milk] [extra shot] [syrup] [iced|hot], \
+add [qty] <produce>, weigh <grams> <produce>, remove <line>, cart, id <date of birth>, coupon <code>, split even|lines|amounts|none ..., tip <amount|percent>, \
+member <phone|card>, join <phone> <name>, redeem, checkout, waste [days], help, quit";
+
+// how far back "waste" looks unless it's told
+const WASTE_DAYS: u32 = 7;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+  Menu,
+  Add { item: CustomDrink, quantity: u32 },
+  AddProduce { item: Vegetables, quantity: u32 },
+  Weigh { item: Vegetables, grams: u32 },
+  // the line number as shown to the user, starting at 1
+  Remove(usize),
+  Cart,
+  // the date of birth on the customer's ID
+  Id(Date),
+  Coupon(String),
+  // None goes back to one payer
+  Split(Option<Split>),
+  Tip(Option<Tip>),
+  // a phone or card number
+  Member(String),
+  Join { phone: String, name: String },
+  Redeem,
+  Checkout,
+  // the waste report for this many days, today included
+  Waste(u32),
+  Help,
+  Quit,
+}
+
+impl FromStr for Command {
+  type Err = CommandError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let words: Vec<String> = s.split_whitespace().map(|w| w.to_lowercase()).collect();
+    let (verb, args) = match words.split_first() {
+      Some((verb, args)) => (verb.as_str(), args),
+      None => return Err(CommandError::Empty),
+    };
+    let no_args = |command| {
+      if args.is_empty() {
+        Ok(command)
+      } else {
+        Err(CommandError::UnexpectedArguments(verb.to_string()))
+      }
+    };
+    match verb {
+      "menu" => no_args(Command::Menu),
+      "cart" => no_args(Command::Cart),
+      "checkout" => no_args(Command::Checkout),
+      "redeem" => no_args(Command::Redeem),
+      "help" => no_args(Command::Help),
+      "quit" | "exit" => no_args(Command::Quit),
+      "remove" => match args {
+        [line] => match line.parse::<usize>() {
+          Ok(line) if line > 0 => Ok(Command::Remove(line)),
+          _ => Err(CommandError::BadLineNumber(line.clone())),
+        },
+        [] => Err(CommandError::MissingArgument("remove", "a line number")),
+        _ => Err(CommandError::UnexpectedArguments(verb.to_string())),
+      },
+      "add" => parse_add(args),
+      "weigh" => match args {
+        [grams, item @ ..] if !item.is_empty() => {
+          let grams = match grams.strip_suffix('g').unwrap_or(grams).parse::<u32>() {
+            Ok(grams) if grams > 0 => grams,
+            _ => return Err(CommandError::BadWeight(grams.clone())),
+          };
+          let item = Vegetables::from_name(&item.join(" ")).ok_or_else(|| CommandError::Produce(item.join(" ")))?;
+          Ok(Command::Weigh { item, grams })
+        }
+        _ => Err(CommandError::MissingArgument("weigh", "a weight in grams and what was weighed")),
+      },
+      "split" => parse_split(args),
+      "member" => match args {
+        [number] => Ok(Command::Member(number.clone())),
+        [] => Err(CommandError::MissingArgument("member", "a phone or card number")),
+        _ => Err(CommandError::UnexpectedArguments(verb.to_string())),
+      },
+      // names keep the case they were typed in
+      "join" => match s.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        [_, phone, name @ ..] if !name.is_empty() => Ok(Command::Join {
+          phone: phone.to_string(),
+          name: name.join(" "),
+        }),
+        _ => Err(CommandError::MissingArgument("join", "a phone number and a name")),
+      },
+      "tip" => match args {
+        [tip] if tip == "none" => Ok(Command::Tip(None)),
+        [tip] => parse_tip(tip).map(|tip| Command::Tip(Some(tip))),
+        [] => Err(CommandError::MissingArgument("tip", "an amount or a percentage")),
+        _ => Err(CommandError::UnexpectedArguments(verb.to_string())),
+      },
+      "id" => match args {
+        [date] => date.parse().map(Command::Id).map_err(|_| CommandError::BadDate(date.clone())),
+        [] => Err(CommandError::MissingArgument("id", "a date of birth (YYYY-MM-DD)")),
+        _ => Err(CommandError::UnexpectedArguments(verb.to_string())),
+      },
+      "waste" => match args {
+        [] => Ok(Command::Waste(WASTE_DAYS)),
+        [days] => match days.parse::<u32>() {
+          Ok(days) if days > 0 => Ok(Command::Waste(days)),
+          _ => Err(CommandError::BadDays(days.clone())),
+        },
+        _ => Err(CommandError::UnexpectedArguments(verb.to_string())),
+      },
+      "coupon" => match args {
+        [code] => Ok(Command::Coupon(code.clone())),
+        [] => Err(CommandError::MissingArgument("coupon", "a code")),
+        _ => Err(CommandError::UnexpectedArguments(verb.to_string())),
+      },
+      other => Err(CommandError::Unknown(other.to_string())),
+    }
+  }
+}
+
+fn parse_split(args: &[String]) -> Result<Command, CommandError> {
+  let (how, rest) = match args.split_first() {
+    Some((how, rest)) => (how.as_str(), rest),
+    None => return Err(CommandError::MissingArgument("split", "even, lines, amounts or none")),
+  };
+  let payer = |word: &String| word.parse::<u32>().map_err(|_| CommandError::BadPayer(word.clone()));
+  let amount = |word: &String| word.parse::<Money>().map_err(|_| CommandError::BadAmount(word.clone()));
+  match (how, rest) {
+    ("none", []) => Ok(Command::Split(None)),
+    ("even", [payers]) => Ok(Command::Split(Some(Split::Evenly(payer(payers)?)))),
+    ("even", []) => Err(CommandError::MissingArgument("split even", "a number of payers")),
+    ("lines", []) => Err(CommandError::MissingArgument("split lines", "a payer for each line")),
+    ("lines", payers) => {
+      let payers = payers.iter().map(payer).collect::<Result<_, _>>()?;
+      Ok(Command::Split(Some(Split::ByLine(payers))))
+    }
+    ("amounts", []) => Err(CommandError::MissingArgument("split amounts", "what each payer pays")),
+    ("amounts", amounts) => {
+      let amounts = amounts.iter().map(amount).collect::<Result<_, _>>()?;
+      Ok(Command::Split(Some(Split::Amounts(amounts))))
+    }
+    ("none" | "even", _) => Err(CommandError::UnexpectedArguments(format!("split {}", how))),
+    (other, _) => Err(CommandError::BadSplit(other.to_string())),
+  }
+}
+
+// "15%" is a percentage of the bill, anything else an amount
+fn parse_tip(word: &str) -> Result<Tip, CommandError> {
+  let bad = || CommandError::BadAmount(word.to_string());
+  match word.strip_suffix('%') {
+    // a percentage has the same shape as an amount, and 12.5 percent is 1250 basis points
+    Some(percent) => match percent.parse::<Money>() {
+      Ok(basis_points) if (0..=10_000).contains(&basis_points.cents()) => {
+        Ok(Tip::Percent { basis_points: basis_points.cents() as u32 })
+      }
+      _ => Err(bad()),
+    },
+    None => match word.parse::<Money>() {
+      Ok(amount) if amount.cents() >= 0 => Ok(Tip::Amount(amount)),
+      _ => Err(bad()),
+    },
+  }
+}
+
+// pull the quantity and any customizations out of the words after "add", and treat whatever is
+// left over as the name of the drink
+fn parse_add(args: &[String]) -> Result<Command, CommandError> {
+  let mut quantity = 1;
+  let mut size = None;
+  let mut milk = None;
+  let mut no_milk = false;
+  let mut extra_shots: u8 = 0;
+  let mut syrups = Vec::new();
+  let mut temperature = None;
+  let mut name = Vec::new();
+
+  let mut i = 0;
+  while i < args.len() {
+    let word = args[i].as_str();
+    let next = args.get(i + 1).map(String::as_str);
+    let number = word.parse::<u32>().ok();
+    match (word, next, number) {
+      (_, Some("shot" | "shots"), Some(n)) => {
+        extra_shots = extra_shots.saturating_add(u8::try_from(n).unwrap_or(u8::MAX));
+        i += 1;
+      }
+      (_, _, Some(n)) if i == 0 => quantity = n,
+      ("extra", Some("hot"), _) => {
+        temperature = Some(Temperature::ExtraHot);
+        i += 1;
+      }
+      ("extra", Some("shot" | "shots"), _) => {
+        extra_shots = extra_shots.saturating_add(1);
+        i += 1;
+      }
+      ("shot" | "shots", _, _) => extra_shots = extra_shots.saturating_add(1),
+      ("no", Some("milk"), _) => {
+        no_milk = true;
+        i += 1;
+      }
+      _ => {
+        if let Some(s) = Size::from_name(word) {
+          size = Some(s);
+        } else if let Some(m) = Milk::from_name(word) {
+          milk = Some(m);
+          if next == Some("milk") {
+            i += 1;
+          }
+        } else if let Some(s) = Syrup::from_name(word) {
+          syrups.push(s);
+          if next == Some("syrup") {
+            i += 1;
+          }
+        } else if let Some(t) = Temperature::from_name(word) {
+          temperature = Some(t);
+        } else {
+          name.push(word);
+        }
+      }
+    }
+    i += 1;
+  }
+
+  if name.is_empty() {
+    return Err(CommandError::MissingArgument("add", "a drink"));
+  }
+  if quantity == 0 {
+    return Err(CommandError::ZeroQuantity);
+  }
+  if quantity > MAX_QUANTITY {
+    return Err(CommandError::TooMany(quantity));
+  }
+  // produce has no customizations, so anything that had some is a drink
+  let plain =
+    size.is_none() && milk.is_none() && !no_milk && extra_shots == 0 && syrups.is_empty() && temperature.is_none();
+  if plain {
+    if let Some(item) = Vegetables::from_name(&name.join(" ")) {
+      return Ok(Command::AddProduce { item, quantity });
+    }
+  }
+  let drink: Drinks = name.join(" ").parse().map_err(CommandError::Drink)?;
+  let mut builder = CustomDrink::builder(drink);
+  if let Some(size) = size {
+    builder = builder.size(size);
+  }
+  if no_milk {
+    builder = builder.no_milk();
+  }
+  if let Some(milk) = milk {
+    builder = builder.milk(milk);
+  }
+  builder = builder.extra_shots(extra_shots);
+  for syrup in syrups {
+    builder = builder.syrup(syrup);
+  }
+  if let Some(temperature) = temperature {
+    builder = builder.temperature(temperature);
+  }
+  let item = builder.build().map_err(CommandError::Customize)?;
+  Ok(Command::Add { item, quantity })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+  Empty,
+  Unknown(String),
+  // (command, what it needs)
+  MissingArgument(&'static str, &'static str),
+  UnexpectedArguments(String),
+  BadLineNumber(String),
+  BadDate(String),
+  BadDays(String),
+  BadWeight(String),
+  // not something the farm stand knows
+  Produce(String),
+  BadAmount(String),
+  BadPayer(String),
+  // not a way of splitting a bill
+  BadSplit(String),
+  ZeroQuantity,
+  // more than MAX_QUANTITY in one go
+  TooMany(u32),
+  Drink(ParseDrinkError),
+  Customize(CustomizeError),
+}
+impl fmt::Display for CommandError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CommandError::Empty => write!(f, "no command given"),
+      CommandError::Unknown(verb) => write!(f, "unknown command \"{}\" ({})", verb, HELP),
+      CommandError::MissingArgument(verb, what) => write!(f, "{} needs {}", verb, what),
+      CommandError::UnexpectedArguments(verb) => write!(f, "{} doesn't take any arguments", verb),
+      CommandError::BadLineNumber(line) => write!(f, "\"{}\" is not a line number", line),
+      CommandError::BadDate(date) => write!(f, "\"{}\" is not a date (expected YYYY-MM-DD)", date),
+      CommandError::BadDays(days) => write!(f, "\"{}\" is not a number of days", days),
+      CommandError::BadWeight(grams) => write!(f, "\"{}\" is not a weight in grams", grams),
+      CommandError::Produce(name) => write!(f, "unknown produce \"{}\"", name),
+      CommandError::BadAmount(amount) => {
+        write!(f, "\"{}\" is not an amount (expected something like 2.50, or 15% for a tip)", amount)
+      }
+      CommandError::BadPayer(payer) => write!(f, "\"{}\" is not a payer number", payer),
+      CommandError::BadSplit(how) => {
+        write!(f, "can't split a bill by \"{}\" (try even, lines, amounts or none)", how)
+      }
+      CommandError::ZeroQuantity => write!(f, "can't add zero of something"),
+      CommandError::TooMany(quantity) => {
+        write!(f, "can't add {} at once (at most {} of one thing)", quantity, MAX_QUANTITY)
+      }
+      CommandError::Drink(e) => write!(f, "{}", e),
+      CommandError::Customize(e) => write!(f, "{}", e),
+    }
+  }
+}
+impl std::error::Error for CommandError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      CommandError::Drink(e) => Some(e),
+      CommandError::Customize(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+pub struct Till {
+  prices: PriceTable,
+  // the farm stand's board. empty unless it's set, so a cafe-only till sells no produce
+  farmstand: Farmstand,
+  promotions: Promotions,
+  // when set, checking out takes the order out of stock and refuses orders that can't be made
+  stock: Option<StockLedger>,
+  // when set, checking out uses up milk and produce oldest first, and what's expired is written off
+  perishables: Option<PerishableStock>,
+  // when set, every checked-out order is written to it
+  ledger: Option<Ledger>,
+  // when set, orders with age-restricted drinks need an ID check to be checked out
+  policy: Option<AgePolicy>,
+  audit: AuditLog,
+  // who is working the till, for the audit trail
+  operator: String,
+  // the ID checked for the current order
+  id_check: Option<IdCheck>,
+  // coupons given for the current order
+  coupons: Vec<String>,
+  // loyalty profiles, when the till has a loyalty card
+  customers: Option<CustomerBook>,
+  // the member the current order is for, and whether they're using a free coffee on it
+  customer: Option<u64>,
+  redeem: bool,
+  // how the current order is being paid for, when it isn't one payer
+  split: Option<Split>,
+  tip: Option<Tip>,
+  order: Order,
+  // every order checked out since the till was opened
+  sales: Vec<Bill>,
+  tips: Money,
+  show_prompt: bool,
+}
+impl Till {
+  pub fn new(prices: PriceTable) -> Self {
+    Self {
+      prices,
+      farmstand: Farmstand::new(),
+      promotions: Promotions::new(),
+      stock: None,
+      perishables: None,
+      ledger: None,
+      policy: None,
+      audit: AuditLog::new(),
+      operator: String::from("till"),
+      id_check: None,
+      coupons: Vec::new(),
+      customers: None,
+      customer: None,
+      redeem: false,
+      split: None,
+      tip: None,
+      order: Order::new(),
+      sales: Vec::new(),
+      tips: Money::ZERO,
+      show_prompt: false,
+    }
+  }
+
+  // print a "> " before reading each command. handy by hand, noise in a script
+  pub fn show_prompt(&mut self, show: bool) {
+    self.show_prompt = show;
+  }
+
+  pub fn set_farmstand(&mut self, farmstand: Farmstand) {
+    self.farmstand = farmstand;
+  }
+
+  pub fn set_promotions(&mut self, promotions: Promotions) {
+    self.promotions = promotions;
+  }
+
+  pub fn set_customers(&mut self, customers: CustomerBook) {
+    self.customers = Some(customers);
+  }
+
+  pub fn customers(&self) -> Option<&CustomerBook> {
+    self.customers.as_ref()
+  }
+
+  pub fn set_stock(&mut self, stock: StockLedger) {
+    self.stock = Some(stock);
+  }
+
+  pub fn stock(&self) -> Option<&StockLedger> {
+    self.stock.as_ref()
+  }
+
+  pub fn set_perishables(&mut self, perishables: PerishableStock) {
+    self.perishables = Some(perishables);
+  }
+
+  pub fn perishables(&self) -> Option<&PerishableStock> {
+    self.perishables.as_ref()
+  }
+
+  pub fn set_ledger(&mut self, ledger: Ledger) {
+    self.ledger = Some(ledger);
+  }
+
+  pub fn set_policy(&mut self, policy: AgePolicy, audit: AuditLog) {
+    self.policy = Some(policy);
+    self.audit = audit;
+  }
+
+  pub fn audit(&self) -> &AuditLog {
+    &self.audit
+  }
+
+  pub fn set_operator(&mut self, operator: impl Into<String>) {
+    self.operator = operator.into();
+  }
+
+  pub fn order(&self) -> &Order {
+    &self.order
+  }
+
+  pub fn sales(&self) -> &[Bill] {
+    &self.sales
+  }
+
+  // tips taken since the till was opened. they aren't part of any sale
+  pub fn tips(&self) -> Money {
+    self.tips
+  }
+
+  // read commands until "quit" or the end of the input
+  pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut lines = input.lines();
+    loop {
+      if self.show_prompt {
+        write!(output, "> ")?;
+        output.flush()?;
+      }
+      let line = match lines.next() {
+        Some(line) => line?,
+        None => break,
+      };
+      if line.trim().is_empty() {
+        continue;
+      }
+      match line.parse::<Command>() {
+        Ok(Command::Quit) => break,
+        Ok(command) => self.execute(command, &mut output)?,
+        Err(e) => writeln!(output, "{}", e)?,
+      }
+    }
+    self.close(&mut output)
+  }
+
+  pub fn execute(&mut self, command: Command, output: &mut impl Write) -> io::Result<()> {
+    match command {
+      Command::Menu => self.print_menu(output),
+      Command::Add { item, quantity } => match self.prices.unit_price(&item) {
+        Some(price) => {
+          let mut order = self.order.clone();
+          if let Err(e) = order.add(item.clone(), quantity) {
+            return writeln!(output, "{}", e);
+          }
+          // turned away now rather than at checkout, while there's still time to order something else
+          if let Some(Err(e)) = self.stock.as_ref().map(|stock| stock.check(&order)) {
+            return writeln!(output, "{}", e);
+          }
+          self.order = order;
+          writeln!(output, "added {} x {} ({} each)", quantity, item, price)
+        }
+        None => writeln!(output, "{}", PricingError::Unpriced(item.drink())),
+      },
+      Command::AddProduce { item, quantity } => match self.farmstand.sell(&item, quantity) {
+        Ok(line) => {
+          let rate = line.rate;
+          if let Err(e) = self.order.add_produce(line) {
+            return writeln!(output, "{}", e);
+          }
+          writeln!(output, "added {} x {} ({} each)", quantity, item, rate)?;
+          self.flag_out_of_season(&item, output)
+        }
+        Err(e) => writeln!(output, "{}", e),
+      },
+      Command::Weigh { item, grams } => match self.farmstand.weigh(&item, grams) {
+        Ok(line) => {
+          let (rate, total) = (line.rate, line.total);
+          if let Err(e) = self.order.add_produce(line) {
+            return writeln!(output, "{}", e);
+          }
+          writeln!(output, "added {} g {} ({}/kg) = {}", grams, item, rate, total)?;
+          self.flag_out_of_season(&item, output)
+        }
+        Err(e) => writeln!(output, "{}", e),
+      },
+      // drinks are numbered first, then produce
+      Command::Remove(line) => {
+        let drinks = self.order.lines().len();
+        if line <= drinks {
+          let removed = self.order.remove(line - 1).expect("the line is on the order");
+          writeln!(output, "removed {} x {}", removed.quantity, removed.item)
+        } else {
+          match self.order.remove_produce(line - 1 - drinks) {
+            Some(removed) => writeln!(output, "removed {} {}", removed.measure, removed.item),
+            None => writeln!(output, "there is no line {} on the cart", line),
+          }
+        }
+      }
+      Command::Cart => self.print_cart(output),
+      Command::Id(date_of_birth) => {
+        self.id_check = Some(IdCheck {
+          date_of_birth,
+          checked_by: self.operator.clone(),
+        });
+        writeln!(output, "ID checked: born {}", date_of_birth)
+      }
+      Command::Coupon(code) => {
+        if !self.promotions.has_coupon(&code) {
+          writeln!(output, "there is no coupon \"{}\"", code)
+        } else if self.coupons.iter().any(|c| c.eq_ignore_ascii_case(&code)) {
+          writeln!(output, "coupon {} is already on this order", code)
+        } else {
+          writeln!(output, "coupon {} will be used at checkout", code)?;
+          self.coupons.push(code);
+          Ok(())
+        }
+      }
+      Command::Split(split) => {
+        match &split {
+          None => writeln!(output, "one payer for this order")?,
+          Some(Split::Evenly(payers)) => writeln!(output, "splitting this order evenly {} ways", payers)?,
+          Some(Split::ByLine(_)) => writeln!(output, "splitting this order by line")?,
+          Some(Split::Amounts(amounts)) => {
+            writeln!(output, "splitting this order into {} amounts", amounts.len())?
+          }
+        }
+        self.split = split;
+        Ok(())
+      }
+      Command::Tip(tip) => {
+        match &tip {
+          None => writeln!(output, "no tip")?,
+          Some(tip) => writeln!(output, "tip of {}", tip)?,
+        }
+        self.tip = tip;
+        Ok(())
+      }
+      Command::Member(number) => {
+        let customer = self.customers.as_ref().and_then(|book| book.lookup(&number));
+        match customer {
+          Some(customer) => {
+            writeln!(output, "{}", customer.greeting())?;
+            if customer.free_coffees > 0 {
+              writeln!(output, "{} free coffee(s) on the card", customer.free_coffees)?;
+            }
+            self.customer = Some(customer.id);
+            Ok(())
+          }
+          None => writeln!(output, "no member with the number {}", number),
+        }
+      }
+      Command::Join { phone, name } => {
+        let book = match &mut self.customers {
+          Some(book) => book,
+          None => return writeln!(output, "this till has no loyalty card"),
+        };
+        match book.enroll(&name, Some(&phone), None) {
+          Ok(id) => {
+            self.customer = Some(id);
+            writeln!(output, "{}", book.get(id).map(|c| c.greeting()).unwrap_or_default())
+          }
+          Err(e) => writeln!(output, "{}", e),
+        }
+      }
+      Command::Redeem => {
+        let customer = self.customer.and_then(|id| self.customers.as_ref()?.get(id));
+        match customer {
+          None => writeln!(output, "find the member first (member <phone|card>)"),
+          Some(customer) if customer.free_coffees == 0 => {
+            writeln!(output, "{} has no free coffees yet", customer.name)
+          }
+          Some(_) => {
+            self.redeem = true;
+            writeln!(output, "a free coffee will come off at checkout")
+          }
+        }
+      }
+      Command::Checkout => self.checkout(output),
+      Command::Waste(days) => self.print_waste(days, output),
+      Command::Help => writeln!(output, "{}", HELP),
+      Command::Quit => self.close(output),
+    }
+  }
+
+  fn print_menu(&self, output: &mut impl Write) -> io::Result<()> {
+    for drink in Drinks::all() {
+      if let Some(price) = self.prices.price_of(&drink) {
+        writeln!(output, "{:<24}{:>8}  {}", drink.to_string(), price, Nutrition::of_drink(drink))?;
+      }
+    }
+    let names = |names: Vec<&str>| names.join(", ");
+    writeln!(output, "sizes: {}", names(Size::ALL.iter().map(|s| s.name()).collect()))?;
+    writeln!(output, "milks: {}", names(Milk::ALL.iter().map(|m| m.name()).collect()))?;
+    writeln!(output, "syrups: {}", names(Syrup::ALL.iter().map(|s| s.name()).collect()))?;
+    if self.farmstand.items().next().is_some() {
+      writeln!(output, "from the farm stand:")?;
+      let today = Date::today();
+      for (item, pricing) in self.farmstand.items() {
+        let how = match pricing {
+          Pricing::PerUnit(_) => "",
+          Pricing::PerKilogram(_) => " (weigh it)",
+        };
+        write!(output, "{:<28}{}{}", item.to_string(), pricing, how)?;
+        let availability = self.farmstand.availability(item, today);
+        if availability.is_available() {
+          writeln!(output)?;
+        } else {
+          writeln!(output, " - {}", availability)?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  // it can still be sold - there may be some left in the cooler - but whoever's on the till
+  // should know before promising more
+  fn flag_out_of_season(&self, item: &Vegetables, output: &mut impl Write) -> io::Result<()> {
+    match self.farmstand.availability(item, Date::today()) {
+      availability @ Availability::OutOfSeason { .. } => writeln!(output, "note: {} is {}", item, availability),
+      _ => Ok(()),
+    }
+  }
+
+  fn print_cart(&self, output: &mut impl Write) -> io::Result<()> {
+    if self.order.is_empty() {
+      return writeln!(output, "the cart is empty");
+    }
+    match self.price(Timestamp::now()) {
+      Ok(bill) => {
+        for (number, line) in bill.lines.iter().enumerate() {
+          writeln!(output, "{}. {} x {} = {}", number + 1, line.quantity, line.item, line.total)?;
+        }
+        for (number, line) in bill.produce.iter().enumerate() {
+          writeln!(output, "{}. {}", bill.lines.len() + number + 1, line)?;
+        }
+        for discount in &bill.discounts {
+          writeln!(output, "{}: {}", discount.name, -discount.amount)?;
+        }
+        writeln!(output, "subtotal: {}", bill.subtotal - bill.discount_total())
+      }
+      Err(e) => writeln!(output, "{}", e),
+    }
+  }
+
+  fn checkout(&mut self, output: &mut impl Write) -> io::Result<()> {
+    if self.order.is_empty() {
+      return writeln!(output, "nothing to check out");
+    }
+    let now = Timestamp::now();
+    let bill = match self.price(now) {
+      Ok(bill) => bill,
+      Err(e) => return writeln!(output, "{}", e),
+    };
+    // worked out before anything is taken out of stock, so a split that doesn't work stops here
+    let shares = match &self.split {
+      Some(how) => match split::split(&bill, how, self.tip) {
+        Ok(shares) => shares,
+        Err(e) => return writeln!(output, "{}", e),
+      },
+      None => vec![split::single(&bill, self.tip)],
+    };
+    if let Some(policy) = &self.policy {
+      if let Err(e) = policy.authorize(&self.order, self.id_check.as_ref(), now, &mut self.audit) {
+        return writeln!(output, "{}", e);
+      }
+    }
+    // the stock count is only checked until the perishables have been taken, so an order that's
+    // short of either takes nothing out of both
+    if let Some(Err(e)) = self.stock.as_ref().map(|stock| stock.check(&self.order)) {
+      return writeln!(output, "{}", e);
+    }
+    if let Some(Err(e)) = self.perishables.as_mut().map(|perishables| perishables.fulfill(&self.order, now.date())) {
+      return writeln!(output, "{}", e);
+    }
+    if let Some(stock) = &mut self.stock {
+      stock.fulfill(&self.order).expect("checked before the perishables were taken");
+    }
+    write!(output, "{}", Receipt::new(&bill, now).to_text())?;
+    print_shares(&shares, output)?;
+    if let Some(ledger) = &mut self.ledger {
+      // the customer has paid either way, so a ledger problem is reported but doesn't undo the sale
+      if let Err(e) = ledger.record(now, &bill, &shares) {
+        writeln!(output, "warning: this sale was not recorded: {}", e)?;
+      }
+    }
+    if let (Some(book), Some(id)) = (&mut self.customers, self.customer) {
+      // like the ledger, a loyalty problem doesn't undo the sale
+      let redeemed = bill.discounts.iter().filter(|discount| discount.name == FREE_COFFEE).count() as u32;
+      let result = if redeemed > 0 { book.redeem(id) } else { Ok(()) };
+      match result.and_then(|_| book.record_visit(id, now, &self.order, redeemed)) {
+        Ok(0) => {}
+        Ok(earned) => writeln!(output, "that's {} more free coffee(s) on the card!", earned)?,
+        Err(e) => writeln!(output, "warning: the loyalty card was not updated: {}", e)?,
+      }
+    }
+    self.tips += shares.iter().map(|share| share.tip).sum();
+    self.sales.push(bill);
+    self.reset_order();
+    Ok(())
+  }
+
+  fn print_waste(&mut self, days: u32, output: &mut impl Write) -> io::Result<()> {
+    let perishables = match &mut self.perishables {
+      Some(perishables) => perishables,
+      None => return writeln!(output, "this till doesn't keep track of perishables"),
+    };
+    let today = Date::today();
+    perishables.write_off_expired(today);
+    let from = today.add_days(1 - i64::from(days));
+    write!(output, "{}", WasteReport::new(from, today, perishables.write_offs()))
+  }
+
+  fn reset_order(&mut self) {
+    self.order = Order::new();
+    self.id_check = None;
+    self.coupons.clear();
+    self.split = None;
+    self.tip = None;
+    self.customer = None;
+    self.redeem = false;
+  }
+
+  // the current order as it would be billed at `at`, promotions and all
+  fn price(&self, at: Timestamp) -> Result<Bill, PricingError> {
+    let occasion = Occasion {
+      at,
+      coupons: self.coupons.clone(),
+      free_coffees: u32::from(self.redeem),
+    };
+    self.prices.price_with_promotions(&self.order, &self.promotions, &occasion)
+  }
+
+  fn close(&mut self, output: &mut impl Write) -> io::Result<()> {
+    if !self.order.is_empty() {
+      writeln!(output, "abandoning an order of {} item(s)", self.order.item_count())?;
+      self.reset_order();
+    }
+    let takings: Money = self.sales.iter().map(|bill| bill.total).sum();
+    writeln!(output, "{} order(s) checked out, {} taken, {} in tips", self.sales.len(), takings, self.tips)
+  }
+}
+
+// who pays what, under the receipt. nothing to say for one payer who didn't tip
+fn print_shares(shares: &[Share], output: &mut impl Write) -> io::Result<()> {
+  if let [share] = shares {
+    if share.tip.is_zero() {
+      return Ok(());
+    }
+  }
+  for share in shares {
+    if share.tip.is_zero() {
+      writeln!(output, "payer {} pays {}", share.payer, share.amount)?;
+    } else {
+      writeln!(output, "payer {} pays {} + {} tip = {}", share.payer, share.amount, share.tip, share.paid())?;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::inventory::Ingredient;
+  use crate::owen_oblivion::Perishable;
+  use crate::CoffeeFlavor;
+
+  #[test]
+  fn parses_add_with_customizations() {
+    let command: Command = "add 2 large oat latte extra shot vanilla iced".parse().unwrap();
+    let item = CustomDrink::builder(Drinks::Coffee(CoffeeFlavor::Latte))
+      .size(Size::Large)
+      .milk(Milk::Oat)
+      .extra_shots(1)
+      .syrup(Syrup::Vanilla)
+      .temperature(Temperature::Iced)
+      .build()
+      .unwrap();
+    assert_eq!(command, Command::Add { item, quantity: 2 });
+  }
+
+  #[test]
+  fn rejects_bad_commands() {
+    assert_eq!("remove zero".parse::<Command>(), Err(CommandError::BadLineNumber("zero".into())));
+    assert_eq!("add".parse::<Command>(), Err(CommandError::MissingArgument("add", "a drink")));
+    assert!(matches!("add 2 shots water".parse::<Command>(), Err(CommandError::Customize(_))));
+    assert!(matches!("brew".parse::<Command>(), Err(CommandError::Unknown(_))));
+    assert_eq!("add 4000000000 water".parse::<Command>(), Err(CommandError::TooMany(4_000_000_000)));
+  }
+
+  #[test]
+  fn a_line_stops_at_the_most_of_one_thing() {
+    let script = "add 600 water\nadd 600 water\nadd 600 dill pickles\nadd 600 dill pickles\ncart\n";
+    let mut till = Till::new(PriceTable::standard());
+    till.set_farmstand(Farmstand::standard());
+    let mut output = Vec::new();
+    till.run(script.as_bytes(), &mut output).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let too_many = "can't add 600 x medium Water: at most 999 of one thing go on an order and there are already 600";
+    assert!(output.contains(too_many));
+    assert!(output.contains("can't add 600 x Pickles (dill)"));
+    assert!(output.contains("1. 600 x medium Water"));
+  }
+
+  #[test]
+  fn runs_a_script() {
+    let script = "add latte large\nadd water\nremove 2\ncart\ncheckout\nquit\nadd beer\n";
+    let mut till = Till::new(PriceTable::standard());
+    let mut output = Vec::new();
+    till.run(script.as_bytes(), &mut output).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("removed 1 x medium Water"));
+    assert!(output.contains("1. 1 x large Latte (coffee) = $5.25"));
+    assert_eq!(till.sales().len(), 1);
+    // nothing after quit is read
+    assert!(!output.contains("Beer"));
+  }
+
+  #[test]
+  fn beer_needs_an_id_check_at_checkout() {
+    let mut till = Till::new(PriceTable::standard());
+    till.set_policy(AgePolicy::new("uk", 18), AuditLog::new());
+    till.set_operator("sam");
+
+    let mut output = Vec::new();
+    till.run("add beer\ncheckout\nid 1990-01-01\ncheckout\n".as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("age-restricted items need an ID check first"));
+    assert_eq!(till.sales().len(), 1);
+    assert_eq!(till.audit().entries().len(), 2);
+    assert_eq!(till.audit().entries()[1].checked_by.as_deref(), Some("sam"));
+  }
+
+  #[test]
+  fn drinks_that_are_out_of_stock_are_turned_away() {
+    let mut stock = StockLedger::new();
+    stock.receive(Ingredient::Cup, 5);
+    stock.receive(Ingredient::SodaBottle, 1);
+    let mut till = Till::new(PriceTable::standard());
+    till.set_stock(stock);
+
+    let mut output = Vec::new();
+    till.run("add 2 soda\nadd soda\nadd soda\ncheckout\n".as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("can't make line 1 (medium Soda): out of soda (needs 2 bottles, only 1 left)"));
+    assert_eq!(output.matches("added").count(), 1);
+    assert_eq!(till.sales().len(), 1);
+    assert_eq!(till.stock().unwrap().on_hand(&Ingredient::SodaBottle), 0);
+  }
+
+  #[test]
+  fn checkout_uses_milk_in_date_and_reports_what_spoiled() {
+    let whole = Perishable::Milk(Milk::Whole);
+    let today = Date::today();
+    let mut perishables = PerishableStock::new();
+    // whole milk keeps 10 days
+    perishables.receive(whole.clone(), 1_000, Money::from_cents(200), today.add_days(-20));
+    perishables.receive(whole.clone(), 300, Money::from_cents(60), today);
+    let mut stock = StockLedger::new();
+    stock.receive(Ingredient::Cup, 5);
+    stock.receive(Ingredient::EspressoShot, 10);
+    stock.receive(Ingredient::Milk(Milk::Whole), 5_000);
+    let mut till = Till::new(PriceTable::standard());
+    till.set_perishables(perishables);
+    till.set_stock(stock);
+
+    let mut output = Vec::new();
+    till.run("add latte\ncheckout\nadd latte\ncheckout\nwaste\n".as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("not enough whole milk in date: 240 needed, 60 left"));
+    assert_eq!(till.sales().len(), 1);
+    assert_eq!(till.perishables().unwrap().on_hand(&whole, today), 60);
+    // the order that couldn't be made took nothing out of the stock count either
+    assert_eq!(till.stock().unwrap().on_hand(&Ingredient::Cup), 4);
+    assert!(output.contains("lots written off:     1\ncost of waste:        $2.00\n"));
+  }
+
+  #[test]
+  fn farm_stand_produce_checks_out_with_the_drinks() {
+    let mut till = Till::new(PriceTable::standard());
+    till.set_farmstand(Farmstand::standard());
+    let mut output = Vec::new();
+    let script = "add latte\nadd 2 dill pickles\nweigh 412 beets\nadd beets\nweigh 500 squash\n\
+      remove 2\ncart\ncheckout\n";
+    till.run(script.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("added 412 g Beets ($6.00/kg) = $2.47"));
+    assert!(output.contains("Beets is sold by weight, so it needs weighing"));
+    assert!(output.contains("Squash is sold by the piece, not by weight"));
+    assert!(output.contains("removed 2 x Pickles (dill)"));
+    assert!(output.contains("2. 412 g Beets = $2.47"));
+    assert!(output.contains("    412 g @ $6.00/kg"));
+    // 8.25% of 4.50 + 2.47, taxed together
+    assert_eq!(till.sales()[0].subtotal, Money::from_cents(697));
+    assert_eq!(till.sales()[0].total, Money::from_cents(755));
+  }
+
+  #[test]
+  fn coupons_come_off_at_checkout() {
+    let mut till = Till::new(PriceTable::standard());
+    let promotions = "[ten percent off]\nkind = coupon\ncode = SAVE10\npercent_off = 10";
+    till.set_promotions(Promotions::from_config(promotions).unwrap());
+
+    let mut output = Vec::new();
+    till.run("add 2 mocha\ncoupon nope\ncoupon save10\ncart\ncheckout\n".as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("there is no coupon \"nope\""));
+    assert!(output.contains("ten percent off: -$0.95"));
+    assert_eq!(till.sales()[0].total, Money::from_cents(926));
+  }
+
+  #[test]
+  fn splits_and_tips_at_checkout() {
+    let mut till = Till::new(PriceTable::standard());
+    let mut output = Vec::new();
+    let script = "add latte\nadd beer\nadd water\nsplit lines 1 2\ncheckout\nsplit lines 1 2 1\ntip 15%\ncheckout\n";
+    till.run(script.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("the bill has 3 line(s) but 2 payer(s) were given for them"));
+    assert!(output.contains("payer 1 pays $5.95 + $0.83 tip = $6.78"));
+    assert!(output.contains("payer 2 pays $6.50 + $0.90 tip = $7.40"));
+    assert_eq!(till.tips(), Money::from_cents(173));
+    assert!(output.ends_with("1 order(s) checked out, $12.45 taken, $1.73 in tips\n"));
+  }
+
+  #[test]
+  fn parses_splits_and_tips() {
+    assert_eq!("split even 3".parse::<Command>(), Ok(Command::Split(Some(Split::Evenly(3)))));
+    assert_eq!("tip 12.5%".parse::<Command>(), Ok(Command::Tip(Some(Tip::Percent { basis_points: 1250 }))));
+    assert_eq!("tip 2".parse::<Command>(), Ok(Command::Tip(Some(Tip::Amount(Money::from_cents(200))))));
+    assert_eq!("split thirds".parse::<Command>(), Err(CommandError::BadSplit("thirds".into())));
+    assert_eq!("tip lots".parse::<Command>(), Err(CommandError::BadAmount("lots".into())));
+  }
+
+  #[test]
+  fn members_collect_and_use_free_coffees() {
+    let mut book = CustomerBook::in_memory();
+    book.set_punches_per_reward(2);
+    let mut till = Till::new(PriceTable::standard());
+    till.set_customers(book);
+
+    let mut output = Vec::new();
+    let script =
+      "join 555-010-2030 Sam Smith\nadd 2 mocha\ncheckout\nmember 5550102030\nadd latte\nredeem\ncheckout\n";
+    till.run(script.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("welcome, Sam Smith!"));
+    assert!(output.contains("that's 1 more free coffee(s) on the card!"));
+    assert!(output.contains("welcome back, Sam Smith, your usual Mocha?"));
+    assert!(output.contains("free coffee (loyalty card)        -$4.50"));
+    assert_eq!(till.sales()[1].total, Money::ZERO);
+    let sam = &till.customers().unwrap().customers()[0];
+    // the free latte earned no punch
+    assert_eq!((sam.free_coffees, sam.punches), (0, 0));
+  }
+}